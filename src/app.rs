@@ -1,8 +1,18 @@
 use crate::{
-    tabs::{BrokerTab, GroupTab, Tab, TopicTab},
+    codec::{avro::AvroDecoder, protobuf::ProtobufDecoder, DeserializerRegistry},
+    config::Config,
+    schema_registry::SchemaRegistry,
+    tabs::{edit_externally, BrokerTab, GroupTab, Tab, TopicTab},
+    template::TemplateStore,
     theme::THEME,
+    Args,
 };
 use color_eyre::{eyre::Context, Result};
+use crossterm::{
+    event::{DisableBracketedPaste, EnableBracketedPaste},
+    execute,
+    terminal::{enable_raw_mode, EnterAlternateScreen},
+};
 use ratatui::{
     buffer::Buffer,
     crossterm::event::{Event, EventStream, KeyEventKind},
@@ -15,11 +25,11 @@ use ratatui::{
 
 use futures::StreamExt;
 use rdkafka::{
-    admin::AdminClient, client::DefaultClientContext, config::ClientConfig, consumer::BaseConsumer,
+    admin::AdminClient, client::DefaultClientContext, consumer::BaseConsumer,
     producer::FutureProducer,
 };
 
-use std::{sync::{Arc, Mutex}, time::Duration};
+use std::{io::stdout, sync::{Arc, Mutex}, time::Duration};
 use strum::IntoEnumIterator;
 
 pub struct App {
@@ -41,26 +51,51 @@ pub enum Mode {
     Tab,
     Quit,
     Refresh,
+    /// Editing the payload of the send form in `$EDITOR`.
+    Editor,
 }
 
 impl App {
     const FRAMES_PER_SECOND: f32 = 60.0;
 
-    pub fn new(brokers: String, group: Option<String>) -> Result<Self> {
-        let mut config = ClientConfig::new();
-        let mut config = config.set("bootstrap.servers", brokers);
-        if let Some(group) = group {
-            config = config.set("group.id", group);
-        }
+    pub fn new(args: Args) -> Result<Self> {
+        let config = args.client_config();
         let consumer: BaseConsumer = config.create().wrap_err("Consumer creation failed")?;
         let producer: FutureProducer = config.create().wrap_err("Producer creation failed")?;
         let admin = config
             .create::<AdminClient<DefaultClientContext>>()
             .wrap_err("Admin creation failed")?;
 
-        let topic_tab = TopicTab::new();
+        let config_dir = match args.config_dir {
+            Some(dir) => dir,
+            None => Config::default_dir()?,
+        };
+        let settings = Config::load(&config_dir)?;
+        let templates = TemplateStore::new(&config_dir);
+        let avro = match args.schema_registry {
+            Some(url) => Some(AvroDecoder::new(SchemaRegistry::new(&url)?)),
+            None => None,
+        };
+        let protobuf = if args.proto_descriptor.is_empty() {
+            None
+        } else {
+            Some(ProtobufDecoder::new(
+                &args.proto_descriptor,
+                &args.proto_key,
+                &args.proto_payload,
+            )?)
+        };
+        let deserializers =
+            DeserializerRegistry::new(config_dir, settings.formats).with_decoders(avro, protobuf);
+
+        let topic_tab = TopicTab::new(
+            Arc::new(deserializers),
+            config.clone(),
+            args.max_messages,
+            templates,
+        );
         let broker_tab = BrokerTab::new();
-        let group_tab = GroupTab::new();
+        let group_tab = GroupTab::new(config.clone());
         Ok(Self {
             mode: Mode::default(),
             tab: Tab::default(),
@@ -104,6 +139,31 @@ impl App {
                 }
                 Some(Ok(event)) = events.next() => self.handle_event(&event).await?,
             }
+
+            if self.mode == Mode::Editor {
+                // The stream would otherwise read the keys meant for the editor.
+                drop(events);
+                self.edit_payload(&mut terminal)?;
+                events = EventStream::new();
+                self.mode = Mode::Tab;
+            }
+        }
+        Ok(())
+    }
+
+    /// Suspends the UI while the payload is edited in `$EDITOR`.
+    fn edit_payload(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        execute!(stdout(), DisableBracketedPaste)?;
+        ratatui::restore();
+        let edited = edit_externally(&self.topic_tab.send_payload());
+        // The same modes `main` starts with.
+        enable_raw_mode()?;
+        execute!(stdout(), EnterAlternateScreen, EnableBracketedPaste)?;
+        terminal.clear()?;
+
+        match edited {
+            Ok(payload) => self.topic_tab.set_send_payload(&payload),
+            Err(e) => self.topic_tab.set_error(e.to_string()),
         }
         Ok(())
     }
@@ -139,7 +199,13 @@ impl App {
                 Mode::Tab => match self.tab {
                     Tab::Topic => {
                         self.topic_tab
-                            .handle_key_press(key, self.consumer.clone(), &self.producer, &self.admin)
+                            .handle_key_press(
+                                key,
+                                self.consumer.clone(),
+                                &self.producer,
+                                &self.admin,
+                                self.broker_tab.broker_list.items.len(),
+                            )
                             .await?
                     }
                     Tab::Group => self.group_tab.handle_key_press(key)?,
@@ -147,9 +213,18 @@ impl App {
                 },
                 _ => self.mode,
             },
+            Event::Paste(text) if self.mode == Mode::Tab && self.tab == Tab::Topic => {
+                self.topic_tab.handle_paste(text);
+                self.mode
+            }
             _ => self.mode,
         };
 
+        if self.mode == Mode::Refresh {
+            self.refresh_matadata().await?;
+            self.mode = Mode::Tab;
+        }
+
         Ok(())
     }
 
@@ -0,0 +1,496 @@
+pub mod avro;
+pub mod plain;
+pub mod protobuf;
+
+use std::{
+    collections::HashMap,
+    fmt,
+    path::PathBuf,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use color_eyre::{eyre::eyre, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use strum::{Display, EnumIter, IntoEnumIterator};
+
+use crate::{
+    config::Config, kafka::KafkaMessage, payload::json::parse_json, schema_registry::SchemaType,
+};
+use avro::{AvroDecoder, AvroEncoder};
+use plain::{
+    CborDeserializer, HexDeserializer, IntDeserializer, JsonDeserializer, MessagePackDeserializer,
+    StringDeserializer, UuidDeserializer,
+};
+use protobuf::{ProtobufDecoder, ProtobufEncoder};
+
+/// First byte of a value in the Confluent wire format, it is followed by the
+/// schema id as a big-endian u32 and the encoded data.
+const MAGIC_BYTE: u8 = 0;
+
+/// Splits a Confluent framed value into its schema id and the encoded data.
+pub fn confluent_frame(bytes: &[u8]) -> Option<(u32, &[u8])> {
+    match bytes {
+        [MAGIC_BYTE, a, b, c, d, data @ ..] => Some((u32::from_be_bytes([*a, *b, *c, *d]), data)),
+        _ => None,
+    }
+}
+
+/// Frames encoded data with its schema id, the reverse of `confluent_frame`.
+pub fn confluent_framed(id: u32, data: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(data.len() + 5);
+    bytes.push(MAGIC_BYTE);
+    bytes.extend_from_slice(&id.to_be_bytes());
+    bytes.extend_from_slice(data);
+    bytes
+}
+
+/// The part of a message a deserializer is applied to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Key,
+    Payload,
+}
+
+/// Turns the raw bytes of a key or a payload into a value to show.
+#[async_trait]
+pub trait Deserializer: Send + Sync {
+    /// `None` leaves the bytes to be shown as they are.
+    async fn deserialize(&self, topic: &str, field: Field, bytes: &[u8]) -> Result<Option<Value>>;
+}
+
+/// The formats a key or a payload can be read as.
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Display, EnumIter, Serialize, Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum Format {
+    /// Protobuf for mapped topics, Confluent framed Avro, JSON documents and
+    /// the raw bytes for everything else.
+    #[default]
+    Auto,
+    String,
+    Json,
+    Hex,
+    Avro,
+    Protobuf,
+    MessagePack,
+    Cbor,
+    Int16,
+    Int32,
+    Int64,
+    Uuid,
+}
+
+impl Format {
+    /// Formats the send form takes JSON for and encodes with a schema.
+    pub fn is_schema(self) -> bool {
+        matches!(self, Format::Avro | Format::Protobuf)
+    }
+
+    pub fn next(self) -> Self {
+        let formats: Vec<Self> = Self::iter().collect();
+        let index = formats.iter().position(|f| *f == self).unwrap_or(0);
+        formats[(index + 1) % formats.len()]
+    }
+}
+
+/// A topic name, or a pattern where `*` matches any run of characters.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct TopicPattern(String);
+
+impl TopicPattern {
+    pub fn matches(&self, topic: &str) -> bool {
+        let pattern: Vec<char> = self.0.chars().collect();
+        let topic: Vec<char> = topic.chars().collect();
+        let (mut p, mut t) = (0, 0);
+        // Position of the last `*` and of the topic char it was tried at.
+        let mut star = None;
+        while t < topic.len() {
+            if p < pattern.len() && pattern[p] == '*' {
+                star = Some((p, t));
+                p += 1;
+            } else if p < pattern.len() && pattern[p] == topic[t] {
+                p += 1;
+                t += 1;
+            } else if let Some((star_p, star_t)) = star {
+                // Let the `*` swallow one more char and retry.
+                star = Some((star_p, star_t + 1));
+                p = star_p + 1;
+                t = star_t + 1;
+            } else {
+                return false;
+            }
+        }
+        pattern[p..].iter().all(|c| *c == '*')
+    }
+}
+
+impl FromStr for TopicPattern {
+    type Err = color_eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err(eyre!("Empty topic pattern"));
+        }
+        Ok(Self(s.to_string()))
+    }
+}
+
+impl fmt::Display for TopicPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// The formats of the keys and payloads of the topics matching `topic`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FormatMapping {
+    pub topic: TopicPattern,
+    #[serde(default)]
+    pub key: Format,
+    #[serde(default)]
+    pub payload: Format,
+}
+
+/// Picks the deserializer for the key and the payload of a message by its
+/// topic. The mappings can be changed while running and are saved to the
+/// config.
+pub struct DeserializerRegistry {
+    deserializers: HashMap<Format, Arc<dyn Deserializer>>,
+    mappings: Mutex<Vec<FormatMapping>>,
+    config_dir: PathBuf,
+    /// Held while the mappings are written, so the last write has the
+    /// latest mappings.
+    saving: tokio::sync::Mutex<()>,
+    /// Kept for encoding what the send form produces.
+    avro: Option<Arc<AvroDecoder>>,
+    protobuf: Option<Arc<ProtobufDecoder>>,
+}
+
+impl DeserializerRegistry {
+    /// Avro and Protobuf need to be registered once they are configured.
+    pub fn new(config_dir: PathBuf, mappings: Vec<FormatMapping>) -> Self {
+        let mut registry = Self {
+            deserializers: HashMap::new(),
+            mappings: Mutex::new(mappings),
+            config_dir,
+            saving: tokio::sync::Mutex::new(()),
+            avro: None,
+            protobuf: None,
+        };
+        registry.register(Format::Auto, Arc::new(AutoDeserializer::default()));
+        registry.register(Format::String, Arc::new(StringDeserializer));
+        registry.register(Format::Json, Arc::new(JsonDeserializer));
+        registry.register(Format::Hex, Arc::new(HexDeserializer));
+        registry.register(Format::MessagePack, Arc::new(MessagePackDeserializer));
+        registry.register(Format::Cbor, Arc::new(CborDeserializer));
+        registry.register(Format::Int16, Arc::new(IntDeserializer { size: 2 }));
+        registry.register(Format::Int32, Arc::new(IntDeserializer { size: 4 }));
+        registry.register(Format::Int64, Arc::new(IntDeserializer { size: 8 }));
+        registry.register(Format::Uuid, Arc::new(UuidDeserializer));
+        registry
+    }
+
+    /// Also registers the decoders for the `Auto` format.
+    pub fn with_decoders(
+        mut self,
+        avro: Option<AvroDecoder>,
+        protobuf: Option<ProtobufDecoder>,
+    ) -> Self {
+        let avro = avro.map(Arc::new);
+        let protobuf = protobuf.map(Arc::new);
+        if let Some(avro) = &avro {
+            self.register(Format::Avro, avro.clone());
+        }
+        if let Some(protobuf) = &protobuf {
+            self.register(Format::Protobuf, protobuf.clone());
+        }
+        self.register(
+            Format::Auto,
+            Arc::new(AutoDeserializer {
+                avro: avro.clone(),
+                protobuf: protobuf.clone(),
+            }),
+        );
+        self.avro = avro;
+        self.protobuf = protobuf;
+        self
+    }
+
+    pub fn register(&mut self, format: Format, deserializer: Arc<dyn Deserializer>) {
+        self.deserializers.insert(format, deserializer);
+    }
+
+    /// The key and the payload format of `topic`.
+    pub fn formats(&self, topic: &str) -> (Format, Format) {
+        let mappings = self.mappings.lock().unwrap();
+        mappings
+            .iter()
+            .find(|m| m.topic.matches(topic))
+            .map(|m| (m.key, m.payload))
+            .unwrap_or_default()
+    }
+
+    /// The schema format a key or a payload of `topic` is encoded with when
+    /// sent, `Auto` counts as Protobuf for topics mapped to a message type.
+    pub fn schema_format(&self, topic: &str, field: Field) -> Option<Format> {
+        let format = match (self.formats(topic), field) {
+            ((key, _), Field::Key) => key,
+            ((_, payload), Field::Payload) => payload,
+        };
+        match format {
+            format if format.is_schema() => Some(format),
+            Format::Auto => self
+                .protobuf
+                .as_ref()
+                .and_then(|protobuf| protobuf.message_type(topic, field))
+                .map(|_| Format::Protobuf),
+            _ => None,
+        }
+    }
+
+    /// The schema format `bytes` of `topic` are encoded with. Under `Auto`,
+    /// Confluent framed bytes count as Avro.
+    pub fn read_schema_format(&self, topic: &str, field: Field, bytes: &[u8]) -> Option<Format> {
+        if let Some(format) = self.schema_format(topic, field) {
+            return Some(format);
+        }
+        let format = match (self.formats(topic), field) {
+            ((key, _), Field::Key) => key,
+            ((_, payload), Field::Payload) => payload,
+        };
+        (format == Format::Auto && self.avro.is_some() && confluent_frame(bytes).is_some())
+            .then_some(Format::Avro)
+    }
+
+    /// Whether a key or a payload read from `source` can be sent to `target`
+    /// as it is: neither topic encodes it with a schema, or both the same.
+    pub fn same_encoding(&self, source: &str, target: &str, field: Field, bytes: &[u8]) -> bool {
+        let message_type = |topic| {
+            self.protobuf
+                .as_ref()
+                .and_then(|protobuf| protobuf.message_type(topic, field))
+                .map(|descriptor| descriptor.full_name().to_string())
+        };
+        match (
+            self.read_schema_format(source, field, bytes),
+            self.read_schema_format(target, field, bytes),
+        ) {
+            (Some(Format::Protobuf), Some(Format::Protobuf)) => {
+                message_type(source) == message_type(target)
+            }
+            (read, sent) => read == sent,
+        }
+    }
+
+    /// Looks up the schema a key or a payload of `topic` is encoded with, so
+    /// it can encode any number of messages.
+    pub async fn encoder(&self, topic: &str, field: Field) -> Result<Encoder> {
+        let Some(format) = self.schema_format(topic, field) else {
+            return Ok(Encoder::Text);
+        };
+        // Subjects named after the topic, the registry's default strategy.
+        let subject = match field {
+            Field::Key => format!("{}-key", topic),
+            Field::Payload => format!("{}-value", topic),
+        };
+
+        match (format, &self.avro, &self.protobuf) {
+            (Format::Avro, Some(avro), _) => Ok(Encoder::Avro(avro.encoder(&subject).await?)),
+            (Format::Protobuf, avro, Some(protobuf)) => {
+                // Without a registered Protobuf schema the message is sent
+                // unframed, which the decoder reads as well.
+                let id = match avro {
+                    Some(avro) => {
+                        avro.registry()
+                            .latest_id(&subject, SchemaType::Protobuf)
+                            .await
+                    }
+                    None => None,
+                };
+                Ok(Encoder::Protobuf(protobuf.encoder(topic, field, id)?))
+            }
+            _ => Err(eyre!("{} is not configured", format)),
+        }
+    }
+
+    /// Changes the format of a single topic, `save_formats` keeps it.
+    pub fn set_format(&self, topic: &str, field: Field, format: Format) {
+        let (key, payload) = self.formats(topic);
+        let mut mappings = self.mappings.lock().unwrap();
+        let index = match mappings.iter().position(|m| m.topic.matches(topic)) {
+            Some(index) if mappings[index].topic.0 == topic => index,
+            // Put the topic in front of the pattern that matched it so far.
+            _ => {
+                mappings.insert(
+                    0,
+                    FormatMapping {
+                        topic: TopicPattern(topic.to_string()),
+                        key,
+                        payload,
+                    },
+                );
+                0
+            }
+        };
+        match field {
+            Field::Key => mappings[index].key = format,
+            Field::Payload => mappings[index].payload = format,
+        }
+    }
+
+    /// Writes the mappings into the config file on a blocking thread.
+    pub async fn save_formats(&self) -> Result<()> {
+        let _saving = self.saving.lock().await;
+        let formats = self.mappings.lock().unwrap().clone();
+        let dir = self.config_dir.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut config = Config::load(&dir)?;
+            config.formats = formats;
+            config.save(&dir)
+        })
+        .await?
+    }
+
+    /// Fills in the decoded key and payload, errors end up in `decode_error`.
+    pub async fn decode_message(&self, message: &mut KafkaMessage) {
+        let (key_format, payload_format) = self.formats(&message.topic);
+        message.decoded_key = None;
+        message.decoded_payload = None;
+        message.decode_error = None;
+
+        let mut errors = Vec::new();
+        if let Some(key) = &message.key {
+            match self
+                .decode(&message.topic, Field::Key, key_format, key)
+                .await
+            {
+                Ok(value) => message.decoded_key = value,
+                Err(e) => errors.push(format!("key: {}", e)),
+            }
+        }
+        if let Some(payload) = &message.payload {
+            match self
+                .decode(&message.topic, Field::Payload, payload_format, payload)
+                .await
+            {
+                Ok(value) => message.decoded_payload = value,
+                Err(e) => errors.push(format!("payload: {}", e)),
+            }
+        }
+        if !errors.is_empty() {
+            message.decode_error = Some(errors.join(", "));
+        }
+    }
+
+    async fn decode(
+        &self,
+        topic: &str,
+        field: Field,
+        format: Format,
+        bytes: &[u8],
+    ) -> Result<Option<Value>> {
+        match self.deserializers.get(&format) {
+            Some(deserializer) => deserializer.deserialize(topic, field, bytes).await,
+            None => Err(eyre!("{} is not configured", format)),
+        }
+    }
+}
+
+/// Encodes what was typed for a key or a payload. With a schema it has to
+/// be JSON matching the schema, anything else is sent as typed.
+pub enum Encoder {
+    Text,
+    Avro(AvroEncoder),
+    Protobuf(ProtobufEncoder),
+}
+
+impl Encoder {
+    pub fn encode(&self, text: &str) -> Result<Vec<u8>> {
+        let json = || serde_json::from_str(text).map_err(|e| eyre!("Invalid JSON: {}", e));
+        match self {
+            Encoder::Text => Ok(text.as_bytes().to_vec()),
+            Encoder::Avro(encoder) => encoder.encode(json()?),
+            Encoder::Protobuf(encoder) => encoder.encode(json()?),
+        }
+    }
+}
+
+/// Guesses the format, it only decodes what it is sure about.
+#[derive(Default)]
+struct AutoDeserializer {
+    avro: Option<Arc<AvroDecoder>>,
+    protobuf: Option<Arc<ProtobufDecoder>>,
+}
+
+#[async_trait]
+impl Deserializer for AutoDeserializer {
+    /// Topics mapped to a Protobuf type take precedence over the registry.
+    async fn deserialize(&self, topic: &str, field: Field, bytes: &[u8]) -> Result<Option<Value>> {
+        if let Some(protobuf) = &self.protobuf {
+            if let Some(value) = protobuf.decode(topic, field, bytes)? {
+                return Ok(Some(value));
+            }
+        }
+        if let Some(avro) = &self.avro {
+            if let Some(value) = avro.decode(bytes).await? {
+                return Ok(Some(value));
+            }
+        }
+        Ok(parse_json(bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::schema_registry::SchemaRegistry;
+
+    use super::*;
+
+    #[test]
+    fn tells_when_bytes_can_be_sent_as_they_are() {
+        let mapping = |topic: &str, payload| FormatMapping {
+            topic: TopicPattern(topic.to_string()),
+            key: Format::Auto,
+            payload,
+        };
+        let registry = DeserializerRegistry::new(
+            std::env::temp_dir(),
+            vec![
+                mapping("orders", Format::Avro),
+                mapping("orders-copy", Format::Avro),
+                mapping("logs", Format::String),
+            ],
+        )
+        .with_decoders(
+            Some(AvroDecoder::new(
+                SchemaRegistry::new("http://localhost:8081").unwrap(),
+            )),
+            None,
+        );
+        let framed = confluent_framed(1, b"data");
+        assert_eq!(
+            registry.read_schema_format("events", Field::Payload, &framed),
+            Some(Format::Avro)
+        );
+        assert_eq!(
+            registry.read_schema_format("events", Field::Payload, b"text"),
+            None
+        );
+
+        assert!(registry.same_encoding("orders", "orders-copy", Field::Payload, &framed));
+        assert!(registry.same_encoding("logs", "events", Field::Payload, b"text"));
+        assert!(registry.same_encoding("events", "logs", Field::Payload, b"text"));
+        assert!(registry.same_encoding("events", "orders", Field::Payload, &framed));
+        assert!(registry.same_encoding("events", "events", Field::Payload, &framed));
+        assert!(!registry.same_encoding("events", "logs", Field::Payload, &framed));
+        assert!(!registry.same_encoding("logs", "orders", Field::Payload, &framed));
+        assert!(registry.same_encoding("orders", "logs", Field::Key, &framed));
+        assert!(registry.same_encoding("orders", "logs", Field::Key, b"text"));
+    }
+}
@@ -0,0 +1,317 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use apache_avro::{
+    from_avro_datum_schemata, to_avro_datum_schemata, types::Value as AvroValue, Schema,
+};
+use async_trait::async_trait;
+use color_eyre::{eyre::eyre, Result};
+use serde_json::Value;
+
+use super::{confluent_frame, confluent_framed, Deserializer, Field};
+use crate::schema_registry::{is_transient, RegisteredSchema, SchemaRegistry, SchemaType};
+
+/// How long a schema is not asked for again after a transient failure.
+const RETRY_AFTER: Duration = Duration::from_secs(30);
+
+/// Decodes Confluent framed Avro with writer schemas from the registry.
+pub struct AvroDecoder {
+    registry: SchemaRegistry,
+    /// Parsed schemas by id. Failures are kept as well, so a missing schema
+    /// or an unreachable registry is not waited on for every message.
+    schemas: Mutex<HashMap<u32, CachedSchema>>,
+    retry_after: Duration,
+}
+
+#[derive(Clone)]
+enum CachedSchema {
+    Avro(Arc<AvroSchema>),
+    /// A Protobuf or JSON schema, left to other decoders.
+    Other,
+    Failed(String),
+    /// A transient failure, the schema is fetched again from `retry` on.
+    Unavailable {
+        error: String,
+        retry: Instant,
+    },
+}
+
+#[async_trait]
+impl Deserializer for AvroDecoder {
+    async fn deserialize(&self, _: &str, _: Field, bytes: &[u8]) -> Result<Option<Value>> {
+        if confluent_frame(bytes).is_none() {
+            return Err(eyre!("Not Confluent framed"));
+        }
+        match self.decode(bytes).await? {
+            Some(value) => Ok(Some(value)),
+            None => Err(eyre!("The registry schema is not Avro")),
+        }
+    }
+}
+
+/// Encodes JSON with a registered schema, Confluent framed.
+pub struct AvroEncoder {
+    id: u32,
+    schema: Arc<AvroSchema>,
+}
+
+impl AvroEncoder {
+    pub fn encode(&self, json: Value) -> Result<Vec<u8>> {
+        let id = self.id;
+        let schemata = || self.schema.references.iter().collect();
+        let value = AvroValue::from(json)
+            .resolve_schemata(&self.schema.schema, schemata())
+            .map_err(|e| eyre!("Does not match schema {}: {}", id, e))?;
+        let data = to_avro_datum_schemata(&self.schema.schema, schemata(), value)
+            .map_err(|e| eyre!("Failed to encode with schema {}: {}", id, e))?;
+        Ok(confluent_framed(id, &data))
+    }
+}
+
+struct AvroSchema {
+    schema: Schema,
+    /// Named types the schema refers to.
+    references: Vec<Schema>,
+}
+
+impl AvroDecoder {
+    pub fn new(registry: SchemaRegistry) -> Self {
+        Self {
+            registry,
+            schemas: Mutex::new(HashMap::new()),
+            retry_after: RETRY_AFTER,
+        }
+    }
+
+    pub fn registry(&self) -> &SchemaRegistry {
+        &self.registry
+    }
+
+    /// An encoder for the latest schema of `subject`.
+    pub async fn encoder(&self, subject: &str) -> Result<AvroEncoder> {
+        let latest = self.registry.schema_by_subject(subject, "latest").await?;
+        if latest.schema_type != SchemaType::Avro {
+            return Err(eyre!("The latest schema of {} is not Avro", subject));
+        }
+        let id = latest.id;
+        let schema = self
+            .schema(id)
+            .await?
+            .ok_or_else(|| eyre!("Schema {} is not Avro", id))?;
+        Ok(AvroEncoder { id, schema })
+    }
+
+    /// Returns `None` when `bytes` are not Confluent framed Avro.
+    pub async fn decode(&self, bytes: &[u8]) -> Result<Option<Value>> {
+        let Some((id, mut data)) = confluent_frame(bytes) else {
+            return Ok(None);
+        };
+        let Some(schema) = self.schema(id).await? else {
+            return Ok(None);
+        };
+
+        let value = from_avro_datum_schemata(
+            &schema.schema,
+            schema.references.iter().collect(),
+            &mut data,
+            None,
+        )
+        .map_err(|e| eyre!("Invalid Avro for schema {}: {}", id, e))?;
+        let json = Value::try_from(value).map_err(|e| eyre!(e))?;
+        Ok(Some(json))
+    }
+
+    async fn schema(&self, id: u32) -> Result<Option<Arc<AvroSchema>>> {
+        let cached = self.schemas.lock().unwrap().get(&id).cloned();
+        let cached = cached.filter(|cached| match cached {
+            CachedSchema::Unavailable { retry, .. } => Instant::now() < *retry,
+            _ => true,
+        });
+        let cached = match cached {
+            Some(cached) => cached,
+            None => {
+                let fetched = match self.registry.schema_by_id(id).await {
+                    Ok(schema) if schema.schema_type != SchemaType::Avro => Ok(CachedSchema::Other),
+                    Ok(schema) => self
+                        .parse(&schema)
+                        .await
+                        .map(|s| CachedSchema::Avro(Arc::new(s))),
+                    Err(e) => Err(e),
+                };
+                let cached = match fetched {
+                    Err(e) if is_transient(&e) => {
+                        let unavailable = CachedSchema::Unavailable {
+                            error: format!("{:#}", e),
+                            retry: Instant::now() + self.retry_after,
+                        };
+                        self.schemas.lock().unwrap().insert(id, unavailable);
+                        return Err(e);
+                    }
+                    fetched => fetched.unwrap_or_else(|e| CachedSchema::Failed(format!("{:#}", e))),
+                };
+                self.schemas.lock().unwrap().insert(id, cached.clone());
+                cached
+            }
+        };
+
+        match cached {
+            CachedSchema::Avro(schema) => Ok(Some(schema)),
+            CachedSchema::Other => Ok(None),
+            CachedSchema::Failed(e) | CachedSchema::Unavailable { error: e, .. } => Err(eyre!(e)),
+        }
+    }
+
+    /// Parses the schema together with everything it references, the
+    /// references may refer to further subjects.
+    async fn parse(&self, schema: &RegisteredSchema) -> Result<AvroSchema> {
+        let mut sources = Vec::new();
+        let mut seen = HashSet::new();
+        let mut pending = schema.references.clone();
+        while let Some(reference) = pending.pop() {
+            if !seen.insert((reference.subject.clone(), reference.version)) {
+                continue;
+            }
+            let referenced = self
+                .registry
+                .schema_by_subject(&reference.subject, &reference.version.to_string())
+                .await?;
+            pending.extend(referenced.references);
+            sources.push(referenced.schema);
+        }
+
+        if sources.is_empty() {
+            let schema = Schema::parse_str(&schema.schema)
+                .map_err(|e| eyre!("Invalid Avro schema {}: {}", schema.id, e))?;
+            return Ok(AvroSchema {
+                schema,
+                references: Vec::new(),
+            });
+        }
+
+        // Parsed as a list the schemas can refer to each other's named types,
+        // the schema itself goes last.
+        sources.push(schema.schema.clone());
+        let sources: Vec<&str> = sources.iter().map(String::as_str).collect();
+        let mut references = Schema::parse_list(&sources)
+            .map_err(|e| eyre!("Invalid Avro schema {}: {}", schema.id, e))?;
+        let schema = references.pop().expect("the schema was parsed last");
+        Ok(AvroSchema { schema, references })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use apache_avro::to_avro_datum;
+    use serde_json::json;
+
+    use super::*;
+    use crate::schema_registry::tests::serve;
+
+    const USER: &str = r#"{"type":"record","name":"User","fields":[{"name":"name","type":"string"},{"name":"age","type":"int"}]}"#;
+
+    fn user(id: u32, name: &str, age: i32) -> Vec<u8> {
+        let schema = Schema::parse_str(USER).unwrap();
+        let value = AvroValue::Record(vec![
+            ("name".to_string(), AvroValue::String(name.to_string())),
+            ("age".to_string(), AvroValue::Int(age)),
+        ]);
+        confluent_framed(id, &to_avro_datum(&schema, value).unwrap())
+    }
+
+    fn schema_body(schema: &str) -> String {
+        json!({ "schema": schema }).to_string()
+    }
+
+    #[tokio::test]
+    async fn decodes_with_the_registered_schema() {
+        let (url, requests) = serve(|path| match path {
+            "/schemas/ids/3" => (200, schema_body(USER)),
+            _ => (404, String::new()),
+        })
+        .await;
+        let decoder = AvroDecoder::new(SchemaRegistry::new(&url).unwrap());
+
+        let value = decoder.decode(&user(3, "ann", 42)).await.unwrap();
+        assert_eq!(value, Some(json!({ "name": "ann", "age": 42 })));
+        let value = decoder.decode(&user(3, "bob", 7)).await.unwrap();
+        assert_eq!(value, Some(json!({ "name": "bob", "age": 7 })));
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn leaves_unframed_and_other_schemas_alone() {
+        let (url, _) = serve(|path| match path {
+            "/schemas/ids/4" => (
+                200,
+                json!({ "schemaType": "PROTOBUF", "schema": "syntax = \"proto3\";" }).to_string(),
+            ),
+            _ => (404, String::new()),
+        })
+        .await;
+        let decoder = AvroDecoder::new(SchemaRegistry::new(&url).unwrap());
+
+        assert_eq!(decoder.decode(b"plain text").await.unwrap(), None);
+        assert_eq!(decoder.decode(&user(4, "ann", 42)).await.unwrap(), None);
+        let error = decoder
+            .deserialize("topic", Field::Payload, &user(4, "ann", 42))
+            .await
+            .unwrap_err();
+        assert_eq!(error.to_string(), "The registry schema is not Avro");
+    }
+
+    #[tokio::test]
+    async fn unknown_id_is_not_fetched_again() {
+        let (url, requests) = serve(|_| (404, r#"{"error_code":40403}"#.to_string())).await;
+        let decoder = AvroDecoder::new(SchemaRegistry::new(&url).unwrap());
+
+        let error = decoder.decode(&user(9, "ann", 42)).await.unwrap_err();
+        assert!(error.to_string().contains("Failed to fetch schema 9"));
+        assert!(decoder.decode(&user(9, "ann", 42)).await.is_err());
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn transient_failures_are_retried_after_a_backoff() {
+        let failed = Arc::new(AtomicBool::new(false));
+        let (url, requests) = serve(move |_| {
+            if failed.swap(true, Ordering::SeqCst) {
+                (200, schema_body(USER))
+            } else {
+                (503, "unavailable".to_string())
+            }
+        })
+        .await;
+        let decoder = AvroDecoder {
+            retry_after: Duration::from_millis(200),
+            ..AvroDecoder::new(SchemaRegistry::new(&url).unwrap())
+        };
+
+        assert!(decoder.decode(&user(5, "ann", 42)).await.is_err());
+        // Not asked again until the backoff is over.
+        let error = decoder.decode(&user(5, "ann", 42)).await.unwrap_err();
+        assert!(error.to_string().contains("503"), "{}", error);
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        let value = decoder.decode(&user(5, "ann", 42)).await.unwrap();
+        assert_eq!(value, Some(json!({ "name": "ann", "age": 42 })));
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn invalid_data_names_the_schema() {
+        let (url, _) = serve(|_| (200, schema_body(USER))).await;
+        let decoder = AvroDecoder::new(SchemaRegistry::new(&url).unwrap());
+
+        let error = decoder
+            .decode(&confluent_framed(6, &[0x80]))
+            .await
+            .unwrap_err();
+        assert!(error.to_string().starts_with("Invalid Avro for schema 6"));
+    }
+}
@@ -0,0 +1,301 @@
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ciborium::Value as CborValue;
+use color_eyre::{eyre::eyre, Result};
+use rmpv::Value as MessagePackValue;
+use serde_json::{Number, Value};
+use uuid::Uuid;
+
+use super::{Deserializer, Field};
+use crate::payload::hex;
+
+pub struct StringDeserializer;
+
+#[async_trait]
+impl Deserializer for StringDeserializer {
+    async fn deserialize(&self, _: &str, _: Field, bytes: &[u8]) -> Result<Option<Value>> {
+        let text = std::str::from_utf8(bytes).map_err(|e| eyre!("Invalid UTF-8: {}", e))?;
+        Ok(Some(Value::from(text)))
+    }
+}
+
+/// Unlike the `Auto` format, bare scalars are accepted too.
+pub struct JsonDeserializer;
+
+#[async_trait]
+impl Deserializer for JsonDeserializer {
+    async fn deserialize(&self, _: &str, _: Field, bytes: &[u8]) -> Result<Option<Value>> {
+        let value = serde_json::from_slice(bytes).map_err(|e| eyre!("Invalid JSON: {}", e))?;
+        Ok(Some(value))
+    }
+}
+
+pub struct HexDeserializer;
+
+#[async_trait]
+impl Deserializer for HexDeserializer {
+    async fn deserialize(&self, _: &str, _: Field, bytes: &[u8]) -> Result<Option<Value>> {
+        Ok(Some(Value::from(hex(bytes))))
+    }
+}
+
+pub struct MessagePackDeserializer;
+
+#[async_trait]
+impl Deserializer for MessagePackDeserializer {
+    async fn deserialize(&self, _: &str, _: Field, bytes: &[u8]) -> Result<Option<Value>> {
+        let value = rmpv::decode::read_value(&mut &bytes[..])
+            .map_err(|e| eyre!("Invalid MessagePack: {}", e))?;
+        Ok(Some(message_pack_to_json(value)))
+    }
+}
+
+/// Binary and extension data become base64, strings that are not UTF-8 are
+/// read lossily and map keys that are not strings are written as their JSON.
+fn message_pack_to_json(value: MessagePackValue) -> Value {
+    match value {
+        MessagePackValue::Nil => Value::Null,
+        MessagePackValue::Boolean(b) => Value::from(b),
+        MessagePackValue::Integer(n) => match (n.as_i64(), n.as_u64()) {
+            (Some(n), _) => Value::from(n),
+            (_, Some(n)) => Value::from(n),
+            _ => Value::Null,
+        },
+        MessagePackValue::F32(n) => float(n as f64),
+        MessagePackValue::F64(n) => float(n),
+        MessagePackValue::String(text) => {
+            Value::from(String::from_utf8_lossy(&text.into_bytes()).into_owned())
+        }
+        MessagePackValue::Binary(bytes) | MessagePackValue::Ext(_, bytes) => {
+            Value::from(STANDARD.encode(bytes))
+        }
+        MessagePackValue::Array(items) => items.into_iter().map(message_pack_to_json).collect(),
+        MessagePackValue::Map(entries) => Value::Object(
+            entries
+                .into_iter()
+                .map(|(key, value)| {
+                    let key = match message_pack_to_json(key) {
+                        Value::String(key) => key,
+                        key => key.to_string(),
+                    };
+                    (key, message_pack_to_json(value))
+                })
+                .collect(),
+        ),
+    }
+}
+
+/// JSON has no NaN or infinity, they are written as text.
+fn float(n: f64) -> Value {
+    match Number::from_f64(n) {
+        Some(n) => Value::Number(n),
+        None => Value::from(n.to_string()),
+    }
+}
+
+pub struct CborDeserializer;
+
+#[async_trait]
+impl Deserializer for CborDeserializer {
+    async fn deserialize(&self, _: &str, _: Field, bytes: &[u8]) -> Result<Option<Value>> {
+        let value: CborValue =
+            ciborium::from_reader(bytes).map_err(|e| eyre!("Invalid CBOR: {}", e))?;
+        Ok(Some(cbor_to_json(value)))
+    }
+}
+
+/// Byte strings become base64, tags are left out and map keys that are not
+/// text are written as their JSON.
+fn cbor_to_json(value: CborValue) -> Value {
+    match value {
+        CborValue::Integer(n) => {
+            let n = i128::from(n);
+            match (i64::try_from(n), u64::try_from(n)) {
+                (Ok(n), _) => Value::from(n),
+                (_, Ok(n)) => Value::from(n),
+                _ => Value::from(n.to_string()),
+            }
+        }
+        CborValue::Bytes(bytes) => Value::from(STANDARD.encode(bytes)),
+        CborValue::Float(n) => float(n),
+        CborValue::Text(text) => Value::from(text),
+        CborValue::Bool(b) => Value::from(b),
+        CborValue::Null => Value::Null,
+        CborValue::Tag(_, value) => cbor_to_json(*value),
+        CborValue::Array(items) => items.into_iter().map(cbor_to_json).collect(),
+        CborValue::Map(entries) => Value::Object(
+            entries
+                .into_iter()
+                .map(|(key, value)| {
+                    let key = match cbor_to_json(key) {
+                        Value::String(key) => key,
+                        key => key.to_string(),
+                    };
+                    (key, cbor_to_json(value))
+                })
+                .collect(),
+        ),
+        _ => Value::Null,
+    }
+}
+
+/// Big-endian two's complement integers, as written by the Kafka short,
+/// integer and long serializers.
+pub struct IntDeserializer {
+    pub size: usize,
+}
+
+#[async_trait]
+impl Deserializer for IntDeserializer {
+    async fn deserialize(&self, _: &str, _: Field, bytes: &[u8]) -> Result<Option<Value>> {
+        let value = match (self.size, bytes) {
+            (2, &[a, b]) => i16::from_be_bytes([a, b]) as i64,
+            (4, &[a, b, c, d]) => i32::from_be_bytes([a, b, c, d]) as i64,
+            (8, bytes) if bytes.len() == 8 => i64::from_be_bytes(bytes.try_into()?),
+            _ => return Err(eyre!("Expected {} bytes, got {}", self.size, bytes.len())),
+        };
+        Ok(Some(Value::from(value)))
+    }
+}
+
+/// A UUID in its 16 byte binary form, the text form is left to `String`.
+pub struct UuidDeserializer;
+
+#[async_trait]
+impl Deserializer for UuidDeserializer {
+    async fn deserialize(&self, _: &str, _: Field, bytes: &[u8]) -> Result<Option<Value>> {
+        let uuid = Uuid::from_slice(bytes).map_err(|e| eyre!("Invalid UUID: {}", e))?;
+        Ok(Some(Value::from(uuid.to_string())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    async fn cbor(value: CborValue) -> Result<Option<Value>> {
+        let mut bytes = Vec::new();
+        ciborium::into_writer(&value, &mut bytes).unwrap();
+        CborDeserializer
+            .deserialize("topic", Field::Payload, &bytes)
+            .await
+    }
+
+    #[tokio::test]
+    async fn cbor_maps_and_scalars() {
+        let value = CborValue::Map(vec![
+            (CborValue::from("name"), CborValue::from("ann")),
+            (CborValue::from("age"), CborValue::from(42)),
+            (CborValue::from("score"), CborValue::from(1.5)),
+            (
+                CborValue::from("tags"),
+                CborValue::from(vec![CborValue::from(true), CborValue::Null]),
+            ),
+        ]);
+        assert_eq!(
+            cbor(value).await.unwrap(),
+            Some(json!({ "name": "ann", "age": 42, "score": 1.5, "tags": [true, null] }))
+        );
+    }
+
+    #[tokio::test]
+    async fn cbor_bytes_keys_and_tags() {
+        let value = CborValue::Map(vec![
+            (CborValue::from(1), CborValue::Bytes(vec![0, 1, 2, 255])),
+            (CborValue::Bytes(b"k".to_vec()), CborValue::from(u64::MAX)),
+            (
+                CborValue::from(false),
+                CborValue::Tag(1, Box::new(CborValue::from(1_700_000_000))),
+            ),
+            (CborValue::from("nan"), CborValue::from(f64::NAN)),
+        ]);
+        assert_eq!(
+            cbor(value).await.unwrap(),
+            Some(json!({
+                "1": "AAEC/w==",
+                "aw==": u64::MAX,
+                "false": 1_700_000_000,
+                "nan": "NaN",
+            }))
+        );
+    }
+
+    async fn message_pack(value: MessagePackValue) -> Result<Option<Value>> {
+        let mut bytes = Vec::new();
+        rmpv::encode::write_value(&mut bytes, &value).unwrap();
+        MessagePackDeserializer
+            .deserialize("topic", Field::Payload, &bytes)
+            .await
+    }
+
+    #[tokio::test]
+    async fn message_pack_maps_and_scalars() {
+        let value = MessagePackValue::Map(vec![
+            (
+                MessagePackValue::from("name"),
+                MessagePackValue::from("ann"),
+            ),
+            (MessagePackValue::from("age"), MessagePackValue::from(42)),
+            (MessagePackValue::from("score"), MessagePackValue::from(1.5)),
+            (
+                MessagePackValue::from("tags"),
+                MessagePackValue::from(vec![MessagePackValue::from(true), MessagePackValue::Nil]),
+            ),
+        ]);
+        assert_eq!(
+            message_pack(value).await.unwrap(),
+            Some(json!({ "name": "ann", "age": 42, "score": 1.5, "tags": [true, null] }))
+        );
+    }
+
+    #[tokio::test]
+    async fn message_pack_binary_keys_and_ext() {
+        let value = MessagePackValue::Map(vec![
+            (
+                MessagePackValue::from(1),
+                MessagePackValue::Binary(vec![0, 1, 2, 255]),
+            ),
+            (
+                MessagePackValue::Binary(b"k".to_vec()),
+                MessagePackValue::from(u64::MAX),
+            ),
+            (
+                MessagePackValue::from(false),
+                MessagePackValue::Ext(-1, vec![0, 0, 0, 1]),
+            ),
+            (
+                MessagePackValue::from("nan"),
+                MessagePackValue::F32(f32::NAN),
+            ),
+        ]);
+        assert_eq!(
+            message_pack(value).await.unwrap(),
+            Some(json!({
+                "1": "AAEC/w==",
+                "aw==": u64::MAX,
+                "false": "AAAAAQ==",
+                "nan": "NaN",
+            }))
+        );
+    }
+
+    #[tokio::test]
+    async fn invalid_message_pack() {
+        let error = MessagePackDeserializer
+            .deserialize("topic", Field::Payload, &[0x92, 0x01])
+            .await
+            .unwrap_err();
+        assert!(error.to_string().starts_with("Invalid MessagePack"));
+    }
+
+    #[tokio::test]
+    async fn invalid_cbor() {
+        let error = CborDeserializer
+            .deserialize("topic", Field::Payload, &[0xff])
+            .await
+            .unwrap_err();
+        assert!(error.to_string().starts_with("Invalid CBOR"));
+    }
+}
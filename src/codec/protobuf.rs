@@ -0,0 +1,372 @@
+use std::{fs, path::PathBuf};
+
+use async_trait::async_trait;
+use color_eyre::{
+    eyre::{eyre, Context},
+    Result,
+};
+use prost::Message;
+use prost_reflect::{DescriptorPool, DynamicMessage, MessageDescriptor, SerializeOptions};
+use serde_json::Value;
+
+use super::{confluent_frame, confluent_framed, Deserializer, Field, TopicPattern};
+
+/// Decodes Protobuf with message types from compiled descriptor sets, as
+/// written by `protoc --include_imports --descriptor_set_out`.
+pub struct ProtobufDecoder {
+    keys: Vec<(TopicPattern, MessageDescriptor)>,
+    payloads: Vec<(TopicPattern, MessageDescriptor)>,
+}
+
+impl ProtobufDecoder {
+    /// Mappings are given as `pattern=package.Message`, the first pattern
+    /// matching a topic wins.
+    pub fn new(descriptors: &[PathBuf], keys: &[String], payloads: &[String]) -> Result<Self> {
+        let mut pool = DescriptorPool::new();
+        for path in descriptors {
+            let bytes =
+                fs::read(path).wrap_err_with(|| format!("Failed to read {}", path.display()))?;
+            pool.decode_file_descriptor_set(bytes.as_slice())
+                .wrap_err_with(|| format!("Invalid descriptor set {}", path.display()))?;
+        }
+
+        Ok(Self {
+            keys: parse_mappings(&pool, keys)?,
+            payloads: parse_mappings(&pool, payloads)?,
+        })
+    }
+
+    /// The message type mapped to the topic, if any.
+    pub fn message_type(&self, topic: &str, field: Field) -> Option<&MessageDescriptor> {
+        let mappings = match field {
+            Field::Key => &self.keys,
+            Field::Payload => &self.payloads,
+        };
+        mappings
+            .iter()
+            .find(|(p, _)| p.matches(topic))
+            .map(|(_, descriptor)| descriptor)
+    }
+
+    /// Returns `None` when no message type is mapped to the topic.
+    pub fn decode(&self, topic: &str, field: Field, bytes: &[u8]) -> Result<Option<Value>> {
+        let Some(descriptor) = self.message_type(topic, field) else {
+            return Ok(None);
+        };
+
+        // A field number can't be 0, so plain Protobuf never starts with the
+        // magic byte.
+        let (descriptor, data) = match confluent_frame(bytes) {
+            Some((_, framed)) => {
+                let (indexes, data) = message_indexes(framed)?;
+                (indexed_message(descriptor, &indexes)?, data)
+            }
+            None => (descriptor.clone(), bytes),
+        };
+
+        let name = descriptor.full_name().to_string();
+        let message = DynamicMessage::decode(descriptor, data)
+            .map_err(|e| eyre!("Invalid Protobuf for {}: {}", name, e))?;
+        let options = SerializeOptions::new().skip_default_fields(false);
+        let value = message.serialize_with_options(serde_json::value::Serializer, &options)?;
+        Ok(Some(value))
+    }
+
+    /// An encoder for the message type mapped to the topic. With a schema id
+    /// the messages are Confluent framed.
+    pub fn encoder(
+        &self,
+        topic: &str,
+        field: Field,
+        schema_id: Option<u32>,
+    ) -> Result<ProtobufEncoder> {
+        let descriptor = self
+            .message_type(topic, field)
+            .ok_or_else(|| eyre!("No message type mapped to {}", topic))?;
+        Ok(ProtobufEncoder {
+            descriptor: descriptor.clone(),
+            schema_id,
+        })
+    }
+}
+
+/// Encodes JSON as a message type.
+pub struct ProtobufEncoder {
+    descriptor: MessageDescriptor,
+    schema_id: Option<u32>,
+}
+
+impl ProtobufEncoder {
+    pub fn encode(&self, json: Value) -> Result<Vec<u8>> {
+        let message = DynamicMessage::deserialize(self.descriptor.clone(), json)
+            .map_err(|e| eyre!("Does not match {}: {}", self.descriptor.full_name(), e))?;
+
+        let data = message.encode_to_vec();
+        Ok(match self.schema_id {
+            Some(id) => {
+                let mut framed = encode_message_indexes(&self.descriptor);
+                framed.extend_from_slice(&data);
+                confluent_framed(id, &framed)
+            }
+            None => data,
+        })
+    }
+}
+
+#[async_trait]
+impl Deserializer for ProtobufDecoder {
+    async fn deserialize(&self, topic: &str, field: Field, bytes: &[u8]) -> Result<Option<Value>> {
+        match self.decode(topic, field, bytes)? {
+            Some(value) => Ok(Some(value)),
+            None => Err(eyre!("No message type mapped to {}", topic)),
+        }
+    }
+}
+
+fn parse_mappings(
+    pool: &DescriptorPool,
+    mappings: &[String],
+) -> Result<Vec<(TopicPattern, MessageDescriptor)>> {
+    mappings
+        .iter()
+        .map(|mapping| {
+            let (pattern, name) = mapping
+                .split_once('=')
+                .ok_or_else(|| eyre!("Invalid mapping \"{}\", expected pattern=type", mapping))?;
+            let descriptor = pool
+                .get_message_by_name(name.trim())
+                .ok_or_else(|| eyre!("Unknown message type {}", name.trim()))?;
+            Ok((pattern.parse()?, descriptor))
+        })
+        .collect()
+}
+
+/// Reads the message indexes that follow the schema id in Confluent framed
+/// Protobuf. They are zigzag varints, a count followed by the indexes, with
+/// a single 0 standing for the first message of the file.
+fn message_indexes(mut data: &[u8]) -> Result<(Vec<usize>, &[u8])> {
+    let mut next = || -> Result<i64> {
+        let n = prost::encoding::decode_varint(&mut data)
+            .map_err(|e| eyre!("Invalid message indexes: {}", e))?;
+        Ok((n >> 1) as i64 ^ -((n & 1) as i64))
+    };
+
+    let count = next()?;
+    let indexes = match count {
+        0 => vec![0],
+        count if count < 0 => return Err(eyre!("Invalid message index count {}", count)),
+        count => (0..count)
+            .map(|_| next().map(|i| i as usize))
+            .collect::<Result<_>>()?,
+    };
+    Ok((indexes, data))
+}
+
+/// Writes the message indexes of `descriptor` as `message_indexes` reads
+/// them.
+fn encode_message_indexes(descriptor: &MessageDescriptor) -> Vec<u8> {
+    let mut indexes = Vec::new();
+    let mut message = descriptor.clone();
+    while let Some(parent) = message.parent_message() {
+        indexes.push(
+            parent
+                .child_messages()
+                .position(|m| m == message)
+                .unwrap_or(0),
+        );
+        message = parent;
+    }
+    let file = message.parent_file();
+    indexes.push(file.messages().position(|m| m == message).unwrap_or(0));
+    indexes.reverse();
+
+    let mut bytes = Vec::new();
+    if indexes == [0] {
+        prost::encoding::encode_varint(0, &mut bytes);
+        return bytes;
+    }
+    // Zigzag encoded, which doubles non-negative numbers.
+    prost::encoding::encode_varint(indexes.len() as u64 * 2, &mut bytes);
+    for index in indexes {
+        prost::encoding::encode_varint(index as u64 * 2, &mut bytes);
+    }
+    bytes
+}
+
+/// Finds the message the indexes point at, they walk the top level messages
+/// of the file that declares `descriptor` and then their nested messages.
+fn indexed_message(descriptor: &MessageDescriptor, indexes: &[usize]) -> Result<MessageDescriptor> {
+    let file = descriptor.parent_file();
+    let mut message: Option<MessageDescriptor> = None;
+    for index in indexes {
+        let next = match &message {
+            None => file.messages().nth(*index),
+            Some(parent) => parent.child_messages().nth(*index),
+        };
+        message =
+            Some(next.ok_or_else(|| {
+                eyre!("Message index {:?} not found in {}", indexes, file.name())
+            })?);
+    }
+    message.ok_or_else(|| eyre!("Empty message indexes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use prost_reflect::prost_types::{
+        field_descriptor_proto::{Label, Type},
+        DescriptorProto, FieldDescriptorProto, FileDescriptorProto,
+    };
+    use serde_json::json;
+
+    use super::*;
+
+    fn field(name: &str, number: i32, kind: Type) -> FieldDescriptorProto {
+        FieldDescriptorProto {
+            name: Some(name.to_string()),
+            number: Some(number),
+            label: Some(Label::Optional as i32),
+            r#type: Some(kind as i32),
+            json_name: Some(name.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn message(
+        name: &str,
+        fields: Vec<FieldDescriptorProto>,
+        nested: Vec<DescriptorProto>,
+    ) -> DescriptorProto {
+        DescriptorProto {
+            name: Some(name.to_string()),
+            field: fields,
+            nested_type: nested,
+            ..Default::default()
+        }
+    }
+
+    /// `Order` with `Order.Item` and `Order.Item.Detail` nested in it,
+    /// followed by `Refund`.
+    fn pool() -> DescriptorPool {
+        let detail = message("Detail", vec![field("gift", 1, Type::Bool)], vec![]);
+        let item = message("Item", vec![field("sku", 1, Type::String)], vec![detail]);
+        let order = message(
+            "Order",
+            vec![field("id", 1, Type::String), field("total", 2, Type::Int32)],
+            vec![item],
+        );
+        let refund = message("Refund", vec![field("order_id", 1, Type::String)], vec![]);
+        let file = FileDescriptorProto {
+            name: Some("shop.proto".to_string()),
+            package: Some("shop".to_string()),
+            message_type: vec![order, refund],
+            syntax: Some("proto3".to_string()),
+            ..Default::default()
+        };
+        let mut pool = DescriptorPool::new();
+        pool.add_file_descriptor_proto(file).unwrap();
+        pool
+    }
+
+    fn descriptor(name: &str) -> MessageDescriptor {
+        pool().get_message_by_name(name).unwrap()
+    }
+
+    #[test]
+    fn reads_message_indexes() {
+        assert_eq!(message_indexes(&[0, 7]).unwrap(), (vec![0], &[7][..]));
+        // Zigzag: a count of 2, then 1 and 0.
+        assert_eq!(
+            message_indexes(&[4, 2, 0, 7]).unwrap(),
+            (vec![1, 0], &[7][..])
+        );
+        assert_eq!(
+            message_indexes(&[1]).unwrap_err().to_string(),
+            "Invalid message index count -1"
+        );
+        // The continuation bit is set but nothing follows.
+        let truncated = message_indexes(&[0x80]).unwrap_err().to_string();
+        assert!(
+            truncated.starts_with("Invalid message indexes"),
+            "{}",
+            truncated
+        );
+        let missing = message_indexes(&[4, 2]).unwrap_err().to_string();
+        assert!(
+            missing.starts_with("Invalid message indexes"),
+            "{}",
+            missing
+        );
+    }
+
+    #[test]
+    fn message_indexes_round_trip() {
+        let order = descriptor("shop.Order");
+        let cases = [
+            ("shop.Order", vec![0u8]),
+            ("shop.Refund", vec![2, 2]),
+            ("shop.Order.Item", vec![4, 0, 0]),
+            ("shop.Order.Item.Detail", vec![6, 0, 0, 0]),
+        ];
+        for (name, expected) in cases {
+            let message = descriptor(name);
+            let bytes = encode_message_indexes(&message);
+            assert_eq!(bytes, expected, "{}", name);
+            let (indexes, rest) = message_indexes(&bytes).unwrap();
+            assert!(rest.is_empty());
+            assert_eq!(indexed_message(&order, &indexes).unwrap().full_name(), name);
+        }
+    }
+
+    #[test]
+    fn finds_nested_messages() {
+        let refund = descriptor("shop.Refund");
+        let found = |indexes: &[usize]| indexed_message(&refund, indexes).unwrap();
+        assert_eq!(found(&[0]).full_name(), "shop.Order");
+        assert_eq!(found(&[1]).full_name(), "shop.Refund");
+        assert_eq!(found(&[0, 0, 0]).full_name(), "shop.Order.Item.Detail");
+        assert_eq!(
+            indexed_message(&refund, &[1, 0]).unwrap_err().to_string(),
+            "Message index [1, 0] not found in shop.proto"
+        );
+        assert_eq!(
+            indexed_message(&refund, &[]).unwrap_err().to_string(),
+            "Empty message indexes"
+        );
+    }
+
+    #[test]
+    fn decodes_framed_and_unframed_payloads() {
+        let decoder = ProtobufDecoder {
+            keys: Vec::new(),
+            payloads: vec![("orders".parse().unwrap(), descriptor("shop.Order"))],
+        };
+        let order = json!({ "id": "o-1", "total": 42 });
+        let encode = |id| {
+            decoder
+                .encoder("orders", Field::Payload, id)
+                .unwrap()
+                .encode(order.clone())
+                .unwrap()
+        };
+        let framed = encode(Some(9));
+        let unframed = encode(None);
+        assert_eq!(confluent_frame(&framed).unwrap().0, 9);
+        assert_eq!(&framed[5..], [&[0][..], &unframed].concat());
+
+        for bytes in [&framed, &unframed] {
+            assert_eq!(
+                decoder.decode("orders", Field::Payload, bytes).unwrap(),
+                Some(order.clone())
+            );
+        }
+        assert_eq!(
+            decoder.decode("refunds", Field::Payload, &framed).unwrap(),
+            None
+        );
+        assert!(decoder
+            .decode("orders", Field::Key, &framed)
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,51 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::{
+    eyre::{eyre, Context},
+    Result,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::codec::FormatMapping;
+
+const CONFIG_FILE: &str = "config.toml";
+
+/// Settings changed from within the app, kept in `config.toml` of the config
+/// directory.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Key and payload formats by topic pattern, the first match wins.
+    pub formats: Vec<FormatMapping>,
+}
+
+impl Config {
+    /// `~/.config/kata` or the platform equivalent.
+    pub fn default_dir() -> Result<PathBuf> {
+        dirs::config_dir()
+            .map(|dir| dir.join("kata"))
+            .ok_or_else(|| eyre!("No config directory, pass --config-dir"))
+    }
+
+    /// A missing config file is an empty config.
+    pub fn load(dir: &Path) -> Result<Self> {
+        let path = dir.join(CONFIG_FILE);
+        match fs::read_to_string(&path) {
+            Ok(text) => {
+                toml::from_str(&text).wrap_err_with(|| format!("Invalid {}", path.display()))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).wrap_err_with(|| format!("Failed to read {}", path.display())),
+        }
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        let path = dir.join(CONFIG_FILE);
+        fs::create_dir_all(dir).wrap_err_with(|| format!("Failed to create {}", dir.display()))?;
+        fs::write(&path, toml::to_string(self)?)
+            .wrap_err_with(|| format!("Failed to write {}", path.display()))
+    }
+}
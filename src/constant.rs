@@ -1,4 +1,9 @@
 use std::time::Duration;
 
 pub const SEND_TIMEOUT: Duration = Duration::from_secs(1);
-pub const POLL_TIMEOUT: Duration = Duration::from_secs(3);
\ No newline at end of file
+/// How long the message viewer blocks on a poll, also how soon it notices
+/// being paused or resumed.
+pub const POLL_TIMEOUT: Duration = Duration::from_millis(100);
+pub const METADATA_TIMEOUT: Duration = Duration::from_secs(5);
+pub const ADMIN_TIMEOUT: Duration = Duration::from_secs(10);
+pub const REGISTRY_TIMEOUT: Duration = Duration::from_secs(5);
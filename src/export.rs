@@ -0,0 +1,446 @@
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use clap::ValueEnum;
+use color_eyre::{
+    eyre::{eyre, Context},
+    Result,
+};
+use rdkafka::{
+    config::ClientConfig,
+    consumer::{BaseConsumer, Consumer},
+};
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumIter};
+
+use crate::{
+    constant::METADATA_TIMEOUT,
+    kafka::{KafkaHeader, KafkaMessage, KafkaTopic},
+    search::{RangeScanner, SearchProgress, SearchRange},
+};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Display, EnumIter, ValueEnum)]
+pub enum ExportFormat {
+    /// One JSON object a line, the format imports read.
+    #[default]
+    #[strum(to_string = "JSON Lines")]
+    Jsonl,
+    #[strum(to_string = "CSV")]
+    Csv,
+    /// A directory with the payload of every message in a file of its own.
+    #[strum(to_string = "Raw payload files")]
+    Raw,
+}
+
+impl ExportFormat {
+    pub fn next(self) -> Self {
+        match self {
+            ExportFormat::Jsonl => ExportFormat::Csv,
+            ExportFormat::Csv => ExportFormat::Raw,
+            ExportFormat::Raw => ExportFormat::Jsonl,
+        }
+    }
+
+    /// Where a topic is exported to unless told otherwise.
+    pub fn default_path(self, topic: &str) -> PathBuf {
+        match self {
+            ExportFormat::Jsonl => PathBuf::from(format!("{}.jsonl", topic)),
+            ExportFormat::Csv => PathBuf::from(format!("{}.csv", topic)),
+            ExportFormat::Raw => PathBuf::from(topic),
+        }
+    }
+}
+
+/// A message as exported. Keys, values and headers that are not UTF-8 are
+/// written as base64, so the bytes read back are the bytes exported.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedMessage {
+    pub topic: String,
+    pub partition: i32,
+    pub offset: i64,
+    /// Milliseconds since the epoch.
+    pub timestamp: Option<i64>,
+    pub key: Option<String>,
+    #[serde(default)]
+    pub key_encoding: Encoding,
+    pub value: Option<String>,
+    #[serde(default)]
+    pub value_encoding: Encoding,
+    #[serde(default)]
+    pub headers: Vec<ExportedHeader>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedHeader {
+    pub key: String,
+    pub value: String,
+    #[serde(default)]
+    pub encoding: Encoding,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Display, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    Base64,
+}
+
+impl Encoding {
+    fn encode(bytes: &[u8]) -> (String, Encoding) {
+        match std::str::from_utf8(bytes) {
+            Ok(text) => (text.to_string(), Encoding::Utf8),
+            Err(_) => (STANDARD.encode(bytes), Encoding::Base64),
+        }
+    }
+
+    fn decode(self, text: &str) -> Result<Vec<u8>> {
+        match self {
+            Encoding::Utf8 => Ok(text.as_bytes().to_vec()),
+            Encoding::Base64 => STANDARD
+                .decode(text)
+                .map_err(|e| eyre!("Invalid base64: {}", e)),
+        }
+    }
+}
+
+impl From<&KafkaMessage> for ExportedMessage {
+    fn from(message: &KafkaMessage) -> Self {
+        let (key, key_encoding) = match message.key.as_deref().map(Encoding::encode) {
+            Some((key, encoding)) => (Some(key), encoding),
+            None => (None, Encoding::Utf8),
+        };
+        let (value, value_encoding) = match message.payload.as_deref().map(Encoding::encode) {
+            Some((value, encoding)) => (Some(value), encoding),
+            None => (None, Encoding::Utf8),
+        };
+        Self {
+            topic: message.topic.clone(),
+            partition: message.partition,
+            offset: message.offset,
+            timestamp: message.timestamp.millis(),
+            key,
+            key_encoding,
+            value,
+            value_encoding,
+            headers: message
+                .headers
+                .iter()
+                .map(|header| {
+                    let (value, encoding) = Encoding::encode(&header.value);
+                    ExportedHeader {
+                        key: header.key.clone(),
+                        value,
+                        encoding,
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+impl ExportedMessage {
+    pub fn key_bytes(&self) -> Result<Option<Vec<u8>>> {
+        self.key
+            .as_deref()
+            .map(|key| self.key_encoding.decode(key))
+            .transpose()
+    }
+
+    pub fn value_bytes(&self) -> Result<Option<Vec<u8>>> {
+        self.value
+            .as_deref()
+            .map(|value| self.value_encoding.decode(value))
+            .transpose()
+    }
+
+    pub fn kafka_headers(&self) -> Result<Vec<KafkaHeader>> {
+        self.headers
+            .iter()
+            .map(|header| {
+                Ok(KafkaHeader {
+                    key: header.key.clone(),
+                    value: header.encoding.decode(&header.value)?,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Writes messages one at a time in one of the export formats.
+pub struct Exporter {
+    format: ExportFormat,
+    output: Output,
+    written: usize,
+}
+
+enum Output {
+    File(BufWriter<File>),
+    Directory(PathBuf),
+}
+
+impl Exporter {
+    pub fn create(format: ExportFormat, path: &Path) -> Result<Self> {
+        let created = || format!("Failed to create {}", path.display());
+        let output = match format {
+            ExportFormat::Raw => {
+                fs::create_dir_all(path).wrap_err_with(created)?;
+                Output::Directory(path.to_path_buf())
+            }
+            _ => Output::File(BufWriter::new(File::create(path).wrap_err_with(created)?)),
+        };
+        let mut exporter = Self {
+            format,
+            output,
+            written: 0,
+        };
+        if let (ExportFormat::Csv, Output::File(file)) = (format, &mut exporter.output) {
+            writeln!(
+                file,
+                "topic,partition,offset,timestamp,key,key_encoding,value,value_encoding,headers"
+            )?;
+        }
+        Ok(exporter)
+    }
+
+    pub fn write(&mut self, message: &KafkaMessage) -> Result<()> {
+        match &mut self.output {
+            Output::Directory(dir) => {
+                // Tombstones have no payload to write.
+                let Some(payload) = &message.payload else {
+                    return Ok(());
+                };
+                let name = format!(
+                    "{}-{}-{}.bin",
+                    message.topic, message.partition, message.offset
+                );
+                fs::write(dir.join(name), payload)?;
+            }
+            Output::File(file) if self.format == ExportFormat::Csv => {
+                let message = ExportedMessage::from(message);
+                let fields = [
+                    csv_field(&message.topic),
+                    message.partition.to_string(),
+                    message.offset.to_string(),
+                    message.timestamp.map(|t| t.to_string()).unwrap_or_default(),
+                    csv_optional(message.key.as_deref()),
+                    message.key_encoding.to_string(),
+                    csv_optional(message.value.as_deref()),
+                    message.value_encoding.to_string(),
+                    csv_field(&serde_json::to_string(&message.headers)?),
+                ];
+                writeln!(file, "{}", fields.join(","))?;
+            }
+            Output::File(file) => {
+                serde_json::to_writer(&mut *file, &ExportedMessage::from(message))?;
+                writeln!(file)?;
+            }
+        }
+        self.written += 1;
+        Ok(())
+    }
+
+    /// Flushes the file and returns the number of messages written.
+    pub fn finish(mut self) -> Result<usize> {
+        if let Output::File(file) = &mut self.output {
+            file.flush()?;
+        }
+        Ok(self.written)
+    }
+}
+
+/// Exports a range of a topic and returns the number of messages written.
+/// `progress` counts the messages scanned so far.
+pub async fn export_range(
+    config: &ClientConfig,
+    topic: &KafkaTopic,
+    range: &SearchRange,
+    mut exporter: Exporter,
+    progress: Arc<Mutex<SearchProgress>>,
+) -> Result<usize> {
+    let mut scanner = RangeScanner::new(config, topic, range, progress).await?;
+    while let Some(message) = scanner.next().await? {
+        exporter.write(&message)?;
+    }
+    exporter.finish()
+}
+
+/// Exports a range of the topic called `name`, for the command line.
+pub async fn export_topic(
+    config: &ClientConfig,
+    name: &str,
+    range: &SearchRange,
+    format: ExportFormat,
+    path: &Path,
+) -> Result<usize> {
+    let consumer: BaseConsumer = config.create()?;
+    let metadata = consumer.fetch_metadata(Some(name), METADATA_TIMEOUT)?;
+    let topic = match metadata.topics().first() {
+        Some(topic) if topic.error().is_none() => KafkaTopic::from(topic),
+        _ => return Err(eyre!("Unknown topic {}", name)),
+    };
+    let exporter = Exporter::create(format, path)?;
+    let progress = Arc::new(Mutex::new(SearchProgress::default()));
+    export_range(config, &topic, range, exporter, progress).await
+}
+
+/// Quoted when needed, a missing value is left empty while an empty one is
+/// written as `""`.
+fn csv_optional(value: Option<&str>) -> String {
+    match value {
+        None => String::new(),
+        Some("") => "\"\"".to_string(),
+        Some(value) => csv_field(value),
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// An export asked for in the UI.
+#[derive(Debug, Clone)]
+pub struct Export {
+    pub topic: KafkaTopic,
+    pub source: ExportSource,
+    pub format: ExportFormat,
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Clone)]
+pub enum ExportSource {
+    /// The received messages the filter lets through.
+    Shown,
+    Range(SearchRange),
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kafka::KafkaTimestamp;
+
+    use super::*;
+
+    fn message(offset: i64, key: Option<&[u8]>, payload: Option<&[u8]>) -> KafkaMessage {
+        KafkaMessage {
+            topic: "orders".to_string(),
+            partition: 2,
+            offset,
+            timestamp: KafkaTimestamp::CreateTime(1_700_000_000_000),
+            headers: Vec::new(),
+            payload: payload.map(<[u8]>::to_vec),
+            key: key.map(<[u8]>::to_vec),
+            decoded_payload: None,
+            decoded_key: None,
+            decode_error: None,
+        }
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("kata-export-{}-{}", std::process::id(), name))
+    }
+
+    /// Returns the path and the number of messages written.
+    fn export(format: ExportFormat, name: &str, messages: &[KafkaMessage]) -> (PathBuf, usize) {
+        let path = temp_path(name);
+        let mut exporter = Exporter::create(format, &path).unwrap();
+        for message in messages {
+            exporter.write(message).unwrap();
+        }
+        (path, exporter.finish().unwrap())
+    }
+
+    #[test]
+    fn quotes_csv_fields() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+        assert_eq!(csv_field("cr\r"), "\"cr\r\"");
+        assert_eq!(csv_optional(None), "");
+        assert_eq!(csv_optional(Some("")), "\"\"");
+        assert_eq!(csv_optional(Some("a,b")), "\"a,b\"");
+    }
+
+    #[test]
+    fn csv_tells_null_from_empty_keys() {
+        let messages = [
+            message(0, None, Some(b"a,\"b\"")),
+            message(1, Some(b""), None),
+        ];
+        let (path, _) = export(ExportFormat::Csv, "keys.csv", &messages);
+        let text = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "topic,partition,offset,timestamp,key,key_encoding,value,value_encoding,headers",
+                "orders,2,0,1700000000000,,utf8,\"a,\"\"b\"\"\",utf8,[]",
+                "orders,2,1,1700000000000,\"\",utf8,,utf8,[]",
+            ]
+        );
+    }
+
+    #[test]
+    fn json_lines_encode_binary_as_base64() {
+        let mut binary = message(0, Some(&[0xff, 0x00]), Some(&[0, 159, 146, 150]));
+        binary.headers = vec![KafkaHeader {
+            key: "trace".to_string(),
+            value: vec![0xc3],
+        }];
+        let text = message(1, Some(b"k"), Some(b"line\n\"quoted\""));
+        let (path, _) = export(ExportFormat::Jsonl, "binary.jsonl", &[binary, text]);
+        let exported = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let lines: Vec<ExportedMessage> = exported
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].key.as_deref(), Some("/wA="));
+        assert_eq!(lines[0].key_encoding, Encoding::Base64);
+        assert_eq!(lines[0].value.as_deref(), Some("AJ+Slg=="));
+        assert_eq!(lines[0].value_encoding, Encoding::Base64);
+        assert_eq!(lines[0].headers[0].value, "ww==");
+        assert_eq!(lines[0].headers[0].encoding, Encoding::Base64);
+        assert_eq!(lines[1].value.as_deref(), Some("line\n\"quoted\""));
+        assert_eq!(lines[1].value_encoding, Encoding::Utf8);
+        assert!(exported
+            .lines()
+            .nth(1)
+            .unwrap()
+            .contains(r#""line\n\"quoted\"""#));
+    }
+
+    #[test]
+    fn raw_files_are_named_after_the_position() {
+        let messages = [
+            message(7, None, Some(&[1, 2, 3])),
+            message(8, Some(b"deleted"), None),
+            message(9, None, Some(b"")),
+        ];
+        let (path, written) = export(ExportFormat::Raw, "raw", &messages);
+        let mut names: Vec<String> = fs::read_dir(&path)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        let seven = fs::read(path.join("orders-2-7.bin")).unwrap();
+        fs::remove_dir_all(&path).unwrap();
+        // The tombstone has no file.
+        assert_eq!(written, 2);
+        assert_eq!(names, ["orders-2-7.bin", "orders-2-9.bin"]);
+        assert_eq!(seven, [1, 2, 3]);
+    }
+}
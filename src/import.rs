@@ -0,0 +1,261 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use color_eyre::{
+    eyre::{eyre, Context},
+    Result,
+};
+use futures::{stream::FuturesUnordered, FutureExt, StreamExt};
+use rdkafka::{
+    message::OwnedHeaders,
+    producer::{FutureProducer, FutureRecord},
+};
+use tokio::time::Instant;
+
+use crate::{
+    export::ExportedMessage,
+    kafka::{delivered, enqueue, KafkaMessage, KafkaTimestamp},
+};
+
+/// Failures beyond this are counted but not kept.
+pub const MAX_FAILURES: usize = 100;
+
+/// Deliveries waited for at the same time.
+const MAX_IN_FLIGHT: usize = 100;
+
+/// Replays an export into a topic.
+#[derive(Debug, Clone)]
+pub struct Import {
+    pub path: PathBuf,
+    pub topic: String,
+    /// Into the partitions the messages were read from, or wherever the
+    /// producer puts their key.
+    pub keep_partitions: bool,
+    /// With the timestamps they were read with, or the time they are sent.
+    pub keep_timestamps: bool,
+    /// Messages a second, as fast as possible when left out.
+    pub rate: Option<u32>,
+    /// Only shows what would be sent.
+    pub dry_run: bool,
+}
+
+/// Shared between the import task and the view.
+#[derive(Debug, Default)]
+pub struct ImportProgress {
+    pub sent: u64,
+    pub failed: u64,
+    /// The first `MAX_FAILURES` failures.
+    pub failures: Vec<String>,
+    pub finished: bool,
+    pub error: Option<String>,
+}
+
+impl TryFrom<ExportedMessage> for KafkaMessage {
+    type Error = color_eyre::Report;
+
+    fn try_from(message: ExportedMessage) -> Result<Self> {
+        Ok(Self {
+            key: message.key_bytes()?,
+            payload: message.value_bytes()?,
+            headers: message.kafka_headers()?,
+            topic: message.topic,
+            partition: message.partition,
+            offset: message.offset,
+            timestamp: match message.timestamp {
+                Some(millis) => KafkaTimestamp::CreateTime(millis),
+                None => KafkaTimestamp::NotAvailable,
+            },
+            decoded_payload: None,
+            decoded_key: None,
+            decode_error: None,
+        })
+    }
+}
+
+/// Reads a JSON Lines export, blank lines are skipped.
+pub fn read_messages(path: &Path) -> Result<Vec<KafkaMessage>> {
+    let text =
+        fs::read_to_string(path).wrap_err_with(|| format!("Failed to read {}", path.display()))?;
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str::<ExportedMessage>(line)
+                .map_err(|e| eyre!(e))
+                .and_then(KafkaMessage::try_from)
+                .wrap_err_with(|| format!("Line {} of {}", i + 1, path.display()))
+        })
+        .collect()
+}
+
+/// Queues the messages one after another, keeping up to `MAX_IN_FLIGHT`
+/// deliveries open, and reports into `progress` as they complete. Retries
+/// of failed deliveries can still change the order within a partition.
+pub async fn run_import(
+    import: Import,
+    messages: Arc<Vec<KafkaMessage>>,
+    producer: FutureProducer,
+    progress: Arc<Mutex<ImportProgress>>,
+) -> Result<()> {
+    let start = Instant::now();
+    let mut in_flight = FuturesUnordered::new();
+    for (i, message) in messages.iter().enumerate() {
+        if let Some(rate) = import.rate.filter(|rate| *rate > 0) {
+            let due = start + Duration::from_secs_f64(i as f64 / rate as f64);
+            // Deliveries are counted while waiting, so the progress keeps up.
+            loop {
+                tokio::select! {
+                    _ = tokio::time::sleep_until(due) => break,
+                    Some(result) = in_flight.next() => record_delivery(&progress, result),
+                }
+            }
+        }
+        while let Some(Some(result)) = in_flight.next().now_or_never() {
+            record_delivery(&progress, result);
+        }
+        if in_flight.len() >= MAX_IN_FLIGHT {
+            if let Some(result) = in_flight.next().await {
+                record_delivery(&progress, result);
+            }
+        }
+
+        let mut record: FutureRecord<[u8], [u8]> = FutureRecord::to(&import.topic);
+        if let Some(key) = &message.key {
+            record = record.key(key.as_slice());
+        }
+        if let Some(payload) = &message.payload {
+            record = record.payload(payload.as_slice());
+        }
+        if !message.headers.is_empty() {
+            let headers = message
+                .headers
+                .iter()
+                .fold(OwnedHeaders::new(), |headers, header| {
+                    headers.add(&header.key, header.value.as_slice())
+                });
+            record = record.headers(headers);
+        }
+        if import.keep_partitions {
+            record = record.partition(message.partition);
+        }
+        if let (true, Some(millis)) = (import.keep_timestamps, message.timestamp.millis()) {
+            record = record.timestamp(millis);
+        }
+
+        let failure = |e| format!("{}:{} {}", message.partition, message.offset, e);
+        match enqueue(&producer, record).await {
+            Ok(delivery) => {
+                in_flight.push(async move { delivered(delivery).await.map_err(failure) })
+            }
+            Err(e) => record_delivery(&progress, Err(failure(e))),
+        }
+    }
+    while let Some(result) = in_flight.next().await {
+        record_delivery(&progress, result);
+    }
+    Ok(())
+}
+
+fn record_delivery(progress: &Mutex<ImportProgress>, result: Result<(), String>) {
+    let mut progress = progress.lock().unwrap();
+    match result {
+        Ok(()) => progress.sent += 1,
+        Err(failure) => {
+            progress.failed += 1;
+            if progress.failures.len() < MAX_FAILURES {
+                progress.failures.push(failure);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        export::{ExportFormat, Exporter},
+        kafka::KafkaHeader,
+    };
+
+    use super::*;
+
+    fn message(offset: i64, key: Option<&[u8]>, payload: Option<&[u8]>) -> KafkaMessage {
+        KafkaMessage {
+            topic: "orders".to_string(),
+            partition: 1,
+            offset,
+            timestamp: KafkaTimestamp::CreateTime(1_700_000_000_000 + offset),
+            headers: Vec::new(),
+            payload: payload.map(<[u8]>::to_vec),
+            key: key.map(<[u8]>::to_vec),
+            decoded_payload: None,
+            decoded_key: None,
+            decode_error: None,
+        }
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("kata-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn exports_read_back_unchanged() {
+        let mut with_headers = message(2, Some(b"k"), Some(b"{\"total\": 3}"));
+        with_headers.headers = vec![
+            KafkaHeader {
+                key: "trace_id".to_string(),
+                value: b"abc".to_vec(),
+            },
+            KafkaHeader {
+                key: "binary".to_string(),
+                value: vec![0, 159, 146, 150],
+            },
+        ];
+        let mut untimed = message(3, None, Some(b"line\nbreak, \"quoted\""));
+        untimed.timestamp = KafkaTimestamp::NotAvailable;
+        let messages = vec![
+            message(0, Some(b"plain"), Some(b"payload")),
+            message(1, Some(&[0xff, 0xfe]), Some(&[0xc3, 0x28])),
+            with_headers,
+            untimed,
+            message(4, Some(b""), None),
+        ];
+
+        let path = temp_path("round-trip.jsonl");
+        let mut exporter = Exporter::create(ExportFormat::Jsonl, &path).unwrap();
+        for message in &messages {
+            exporter.write(message).unwrap();
+        }
+        assert_eq!(exporter.finish().unwrap(), messages.len());
+        let read = read_messages(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(read.len(), messages.len());
+        for (read, written) in read.iter().zip(&messages) {
+            assert_eq!(read.topic, written.topic);
+            assert_eq!(read.partition, written.partition);
+            assert_eq!(read.offset, written.offset);
+            assert_eq!(read.timestamp, written.timestamp);
+            assert_eq!(read.key, written.key);
+            assert_eq!(read.payload, written.payload);
+            assert_eq!(read.headers, written.headers);
+        }
+    }
+
+    #[test]
+    fn reports_the_line_that_does_not_read() {
+        let path = temp_path("invalid.jsonl");
+        let valid =
+            r#"{"topic":"t","partition":0,"offset":0,"timestamp":null,"key":null,"value":"v"}"#;
+        let invalid = r#"{"topic":"t","partition":0,"offset":1,"timestamp":null,"key":"%","key_encoding":"base64","value":null}"#;
+        fs::write(&path, format!("{}\n\n{}\n", valid, invalid)).unwrap();
+        let error = read_messages(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(error.to_string(), format!("Line 3 of {}", path.display()));
+        assert!(format!("{:#}", error).contains("Invalid base64"));
+    }
+}
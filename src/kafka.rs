@@ -1,10 +1,28 @@
+use std::{fmt, time::Duration};
+
+use color_eyre::Result;
 use rdkafka::{
+    consumer::{BaseConsumer, Consumer},
+    error::{KafkaError, RDKafkaErrorCode},
     groups::{GroupInfo, GroupMemberInfo},
-    message::BorrowedMessage,
+    message::{BorrowedMessage, Headers, Timestamp, ToBytes},
     metadata::{MetadataBroker, MetadataPartition, MetadataTopic},
+    producer::{DeliveryFuture, FutureProducer, FutureRecord},
+    topic_partition_list::{Offset, TopicPartitionList},
     Message,
 };
 
+use serde_json::Value;
+use tokio::time::Instant;
+
+use crate::{
+    constant::{METADATA_TIMEOUT, SEND_TIMEOUT},
+    timestamp::format_timestamp,
+};
+
+/// How long to wait before queueing again while the producer queue is full.
+const QUEUE_FULL_BACKOFF: Duration = Duration::from_millis(10);
+
 #[derive(Debug, Clone)]
 pub struct KafkaBroker {
     pub host: String,
@@ -32,10 +50,7 @@ impl From<&MetadataTopic> for KafkaTopic {
 
         Self {
             name: topic.name().to_string(),
-            partitions: partitions
-                .into_iter()
-                .map(|p| KafkaPartition::from(p))
-                .collect(),
+            partitions: partitions.iter().map(KafkaPartition::from).collect(),
         }
     }
 }
@@ -72,13 +87,23 @@ pub struct KafkaGroup {
     pub members: Vec<KafkaGroupMember>,
 }
 
+impl KafkaGroup {
+    /// The topics assigned to the members, each once.
+    pub fn topics(&self) -> Vec<String> {
+        let mut topics: Vec<String> = self
+            .members
+            .iter()
+            .flat_map(|member| member.topics.iter().cloned())
+            .collect();
+        topics.sort();
+        topics.dedup();
+        topics
+    }
+}
+
 impl From<&GroupInfo> for KafkaGroup {
     fn from(group: &GroupInfo) -> Self {
-        let members = group
-            .members()
-            .into_iter()
-            .map(|m| KafkaGroupMember::from(m))
-            .collect();
+        let members = group.members().iter().map(KafkaGroupMember::from).collect();
 
         Self {
             name: group.name().to_string(),
@@ -95,6 +120,9 @@ pub struct KafkaGroupMember {
     pub id: String,
     pub client_id: String,
     pub client_host: String,
+    /// The topics of the partitions assigned to the member, empty for groups
+    /// that are not consumer groups.
+    pub topics: Vec<String>,
 }
 
 impl From<&GroupMemberInfo> for KafkaGroupMember {
@@ -103,33 +131,354 @@ impl From<&GroupMemberInfo> for KafkaGroupMember {
             id: member.id().to_string(),
             client_id: member.client_id().to_string(),
             client_host: member.client_host().to_string(),
+            topics: member
+                .assignment()
+                .and_then(assigned_topics)
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// The topics of a consumer protocol member assignment: a version, then
+/// the topics with their partitions, then user data.
+fn assigned_topics(assignment: &[u8]) -> Option<Vec<String>> {
+    fn take<'a>(bytes: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+        let taken = bytes.get(..len)?;
+        *bytes = &bytes[len..];
+        Some(taken)
+    }
+    fn int(bytes: &mut &[u8]) -> Option<usize> {
+        let len = i32::from_be_bytes(take(bytes, 4)?.try_into().ok()?);
+        usize::try_from(len).ok()
+    }
+
+    let mut bytes = assignment;
+    take(&mut bytes, 2)?;
+    let count = int(&mut bytes)?;
+    let mut topics = Vec::new();
+    for _ in 0..count {
+        let len = u16::from_be_bytes(take(&mut bytes, 2)?.try_into().ok()?) as usize;
+        let topic = std::str::from_utf8(take(&mut bytes, len)?).ok()?;
+        let partitions = int(&mut bytes)?;
+        take(&mut bytes, partitions.checked_mul(4)?)?;
+        topics.push(topic.to_string());
+    }
+    Some(topics)
+}
+
+/// The offset a group committed for a partition, and where the partition
+/// ends.
+#[derive(Debug, Clone)]
+pub struct GroupOffset {
+    pub topic: String,
+    pub partition: i32,
+    pub committed: i64,
+    pub high: i64,
+}
+
+impl GroupOffset {
+    /// Messages the group has not committed yet.
+    pub fn lag(&self) -> i64 {
+        (self.high - self.committed).max(0)
+    }
+}
+
+/// The offsets committed for the partitions of `topics` by the group
+/// `consumer` was created with, by topic and partition. Without topics, as
+/// for a group without members, every topic of the cluster is looked at.
+pub fn group_offsets(consumer: &BaseConsumer, topics: &[String]) -> Result<Vec<GroupOffset>> {
+    let mut list = TopicPartitionList::new();
+    let mut add_partitions = |topic: Option<&str>| -> Result<()> {
+        let metadata = consumer.fetch_metadata(topic, METADATA_TIMEOUT)?;
+        for topic in metadata.topics() {
+            for partition in topic.partitions() {
+                list.add_partition(topic.name(), partition.id());
+            }
         }
+        Ok(())
+    };
+    if topics.is_empty() {
+        add_partitions(None)?;
+    }
+    for topic in topics {
+        add_partitions(Some(topic))?;
     }
+    let committed = consumer.committed_offsets(list, METADATA_TIMEOUT)?;
+
+    let mut offsets = Vec::new();
+    for element in committed.elements() {
+        // Partitions the group never committed are left out.
+        let Offset::Offset(offset) = element.offset() else {
+            continue;
+        };
+        let (_, high) =
+            consumer.fetch_watermarks(element.topic(), element.partition(), METADATA_TIMEOUT)?;
+        offsets.push(GroupOffset {
+            topic: element.topic().to_string(),
+            partition: element.partition(),
+            committed: offset,
+            high,
+        });
+    }
+    offsets.sort_by(|a, b| (&a.topic, a.partition).cmp(&(&b.topic, b.partition)));
+    Ok(offsets)
 }
 
 #[derive(Debug, Clone)]
 pub struct KafkaMessage {
-    // pub topic: String,
-    //pub partition: i32,
+    pub topic: String,
+    pub partition: i32,
     pub offset: i64,
-    pub payload: String,
-    pub key: String,
+    pub timestamp: KafkaTimestamp,
+    pub headers: Vec<KafkaHeader>,
+    /// Raw payload, `None` for a tombstone.
+    pub payload: Option<Vec<u8>>,
+    /// Raw key, `None` for a message without key.
+    pub key: Option<Vec<u8>>,
+    /// The payload as read by its deserializer, filled in after the
+    /// conversion by `DeserializerRegistry::decode_message`.
+    pub decoded_payload: Option<Value>,
+    /// The key as read by its deserializer.
+    pub decoded_key: Option<Value>,
+    /// Why the key or the payload could not be decoded.
+    pub decode_error: Option<String>,
 }
 
 impl<'a> From<BorrowedMessage<'a>> for KafkaMessage {
     fn from(message: BorrowedMessage<'a>) -> Self {
+        let headers = match message.headers() {
+            Some(headers) => (0..headers.count())
+                .filter_map(|i| headers.get(i))
+                .map(|(key, value)| KafkaHeader {
+                    key: key.to_string(),
+                    value: value.to_vec(),
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+
         Self {
-            // topic: message.topic().to_string(),
-            // partition: message.partition(),
+            topic: message.topic().to_string(),
+            partition: message.partition(),
             offset: message.offset(),
-            payload: match message.payload_view::<str>() {
-                Some(Ok(payload)) => payload.to_string(),
-                _ => "Unknown".to_string(),
-            },
-            key: match message.key_view::<str>() {
-                Some(Ok(key)) => key.to_string(),
-                _ => "Unknown".to_string(),
-            },
+            timestamp: KafkaTimestamp::from(message.timestamp()),
+            headers,
+            payload: message.payload().map(<[u8]>::to_vec),
+            key: message.key().map(<[u8]>::to_vec),
+            decoded_payload: None,
+            decoded_key: None,
+            decode_error: None,
         }
     }
 }
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KafkaHeader {
+    pub key: String,
+    pub value: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KafkaTimestamp {
+    NotAvailable,
+    CreateTime(i64),
+    LogAppendTime(i64),
+}
+
+impl KafkaTimestamp {
+    pub fn millis(&self) -> Option<i64> {
+        match self {
+            KafkaTimestamp::NotAvailable => None,
+            KafkaTimestamp::CreateTime(millis) | KafkaTimestamp::LogAppendTime(millis) => {
+                Some(*millis)
+            }
+        }
+    }
+}
+
+impl From<Timestamp> for KafkaTimestamp {
+    fn from(timestamp: Timestamp) -> Self {
+        match timestamp {
+            Timestamp::NotAvailable => KafkaTimestamp::NotAvailable,
+            Timestamp::CreateTime(millis) => KafkaTimestamp::CreateTime(millis),
+            Timestamp::LogAppendTime(millis) => KafkaTimestamp::LogAppendTime(millis),
+        }
+    }
+}
+
+impl fmt::Display for KafkaTimestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KafkaTimestamp::NotAvailable => write!(f, "not available"),
+            KafkaTimestamp::CreateTime(millis) => {
+                write!(f, "{} (create time)", format_timestamp(*millis))
+            }
+            KafkaTimestamp::LogAppendTime(millis) => {
+                write!(f, "{} (log append time)", format_timestamp(*millis))
+            }
+        }
+    }
+}
+
+/// Where reading a topic starts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StartPosition {
+    Beginning,
+    End,
+    /// Explicit offsets by partition, partitions without an entry are not read.
+    Offsets(Vec<(i32, i64)>),
+    /// The last n messages of every partition.
+    LastN(i64),
+    /// The first message at or after the timestamp in milliseconds.
+    Timestamp(i64),
+}
+
+impl fmt::Display for StartPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StartPosition::Beginning => write!(f, "beginning"),
+            StartPosition::End => write!(f, "end"),
+            StartPosition::Offsets(offsets) => {
+                let offsets: Vec<String> = offsets
+                    .iter()
+                    .map(|(partition, offset)| format!("{}:{}", partition, offset))
+                    .collect();
+                write!(f, "offsets {}", offsets.join(", "))
+            }
+            StartPosition::LastN(n) => write!(f, "last {}", n),
+            StartPosition::Timestamp(millis) => write!(f, "{}", format_timestamp(*millis)),
+        }
+    }
+}
+
+impl StartPosition {
+    /// Returns the partitions of `topic` that are read from this position.
+    pub fn partitions<'a>(&self, topic: &'a KafkaTopic) -> Vec<&'a KafkaPartition> {
+        match self {
+            StartPosition::Offsets(offsets) => topic
+                .partitions
+                .iter()
+                .filter(|p| offsets.iter().any(|(id, _)| *id == p.id))
+                .collect(),
+            _ => topic.partitions.iter().collect(),
+        }
+    }
+
+    /// Resolves the position into the offsets to assign the consumer to.
+    pub fn assignment(
+        &self,
+        consumer: &BaseConsumer,
+        topic: &KafkaTopic,
+    ) -> Result<TopicPartitionList> {
+        let mut assignment = TopicPartitionList::new();
+        for partition in self.partitions(topic) {
+            let offset = match self {
+                StartPosition::Beginning => Offset::Beginning,
+                StartPosition::End => Offset::End,
+                StartPosition::Timestamp(millis) => Offset::Offset(*millis),
+                StartPosition::Offsets(offsets) => {
+                    let (low, high) =
+                        consumer.fetch_watermarks(&topic.name, partition.id, METADATA_TIMEOUT)?;
+                    let offset = offsets
+                        .iter()
+                        .find(|(id, _)| *id == partition.id)
+                        .map(|(_, offset)| *offset)
+                        .unwrap_or(low);
+                    Offset::Offset(offset.clamp(low, high))
+                }
+                StartPosition::LastN(n) => {
+                    let (low, high) =
+                        consumer.fetch_watermarks(&topic.name, partition.id, METADATA_TIMEOUT)?;
+                    Offset::Offset((high - n).max(low))
+                }
+            };
+            assignment.add_partition_offset(&topic.name, partition.id, offset)?;
+        }
+
+        if let StartPosition::Timestamp(_) = self {
+            // The offsets hold timestamps here, the broker answers with the
+            // earliest offset whose timestamp is at or after them.
+            assignment = consumer.offsets_for_times(assignment, METADATA_TIMEOUT)?;
+        }
+        Ok(assignment)
+    }
+}
+
+/// Queues the record right away, waiting up to `SEND_TIMEOUT` while the
+/// producer queue is full. Unlike `FutureProducer::send` nothing waits for
+/// the future to be polled, so records are produced in the order they are
+/// queued while their deliveries are awaited together.
+pub async fn enqueue<K, P>(
+    producer: &FutureProducer,
+    mut record: FutureRecord<'_, K, P>,
+) -> Result<DeliveryFuture, KafkaError>
+where
+    K: ToBytes + ?Sized,
+    P: ToBytes + ?Sized,
+{
+    let start = Instant::now();
+    loop {
+        match producer.send_result(record) {
+            Ok(delivery) => return Ok(delivery),
+            Err((KafkaError::MessageProduction(RDKafkaErrorCode::QueueFull), queued))
+                if start.elapsed() < SEND_TIMEOUT =>
+            {
+                record = queued;
+                tokio::time::sleep(QUEUE_FULL_BACKOFF).await;
+            }
+            Err((e, _)) => return Err(e),
+        }
+    }
+}
+
+/// Waits for a record queued by `enqueue` to be delivered.
+pub async fn delivered(delivery: DeliveryFuture) -> Result<(), KafkaError> {
+    match delivery.await {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err((e, _))) => Err(e),
+        Err(_) => Err(KafkaError::Canceled),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assignment(topics: &[(&str, &[i32])]) -> Vec<u8> {
+        let mut bytes = 1i16.to_be_bytes().to_vec();
+        bytes.extend((topics.len() as i32).to_be_bytes());
+        for (topic, partitions) in topics {
+            bytes.extend((topic.len() as i16).to_be_bytes());
+            bytes.extend(topic.as_bytes());
+            bytes.extend((partitions.len() as i32).to_be_bytes());
+            for partition in *partitions {
+                bytes.extend(partition.to_be_bytes());
+            }
+        }
+        // User data.
+        bytes.extend((-1i32).to_be_bytes());
+        bytes
+    }
+
+    #[test]
+    fn reads_assigned_topics() {
+        let bytes = assignment(&[("orders", &[0, 2]), ("payments", &[]), ("audit", &[1])]);
+        assert_eq!(
+            assigned_topics(&bytes),
+            Some(vec![
+                "orders".to_string(),
+                "payments".to_string(),
+                "audit".to_string()
+            ])
+        );
+        assert_eq!(assigned_topics(&assignment(&[])), Some(Vec::new()));
+    }
+
+    #[test]
+    fn refuses_truncated_assignments() {
+        let bytes = assignment(&[("orders", &[0, 2])]);
+        assert_eq!(assigned_topics(&bytes[..bytes.len() - 6]), None);
+        assert_eq!(assigned_topics(&[0]), None);
+        assert_eq!(assigned_topics(&[]), None);
+    }
+}
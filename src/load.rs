@@ -0,0 +1,372 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use color_eyre::{eyre::eyre, Result};
+use futures::{stream::FuturesUnordered, FutureExt, StreamExt};
+use rand::seq::SliceRandom;
+use rdkafka::{
+    message::OwnedHeaders,
+    producer::{FutureProducer, FutureRecord},
+};
+use strum::Display;
+
+use crate::{
+    codec::{DeserializerRegistry, Encoder, Field},
+    import::MAX_FAILURES,
+    kafka::{delivered, enqueue},
+    template::{expand, Template},
+};
+
+/// Deliveries waited for at the same time.
+const MAX_IN_FLIGHT: usize = 1_000;
+
+/// Latency buckets for each power of two, so a bucket is about 3% wide.
+const SUB_BUCKETS: u64 = 32;
+
+/// Messages made from a template and sent to a topic, as fast as possible
+/// or at a steady rate.
+#[derive(Debug, Clone)]
+pub struct LoadTest {
+    pub topic: String,
+    pub template: Template,
+    pub count: u64,
+    /// Messages a second, as fast as possible when left out.
+    pub rate: Option<u32>,
+    pub keys: KeyDistribution,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Display)]
+pub enum KeyDistribution {
+    /// The key of the template, placeholders expanded for each message.
+    #[strum(to_string = "Template key")]
+    Fixed,
+    /// The sequence number of the message.
+    #[strum(to_string = "Sequential")]
+    Sequential,
+    #[strum(to_string = "Random from a set")]
+    Random(Vec<String>),
+}
+
+impl LoadTest {
+    /// The key of the `seq`th message, `None` for a null key.
+    fn key(&self, seq: u64) -> Result<Option<String>> {
+        match &self.keys {
+            KeyDistribution::Fixed => self
+                .template
+                .key
+                .as_deref()
+                .map(|key| expand(key, seq))
+                .transpose(),
+            KeyDistribution::Sequential => Ok(Some(seq.to_string())),
+            KeyDistribution::Random(keys) => Ok(keys.choose(&mut rand::thread_rng()).cloned()),
+        }
+    }
+}
+
+/// Shared between the load task and the view.
+#[derive(Debug)]
+pub struct LoadProgress {
+    pub sent: u64,
+    pub failed: u64,
+    /// The first `MAX_FAILURES` failures.
+    pub failures: Vec<String>,
+    pub latencies: LatencyHistogram,
+    pub started: Instant,
+    pub finished: Option<Instant>,
+    pub error: Option<String>,
+    /// Start of the current second and the deliveries before it.
+    second: (Instant, u64),
+    /// Deliveries a second, over the last full second.
+    pub throughput: f64,
+}
+
+impl LoadProgress {
+    pub fn new() -> Self {
+        let now = Instant::now();
+        Self {
+            sent: 0,
+            failed: 0,
+            failures: Vec::new(),
+            latencies: LatencyHistogram::default(),
+            started: now,
+            finished: None,
+            error: None,
+            second: (now, 0),
+            throughput: 0.0,
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.finished.unwrap_or_else(Instant::now) - self.started
+    }
+
+    /// Deliveries a second since the start.
+    pub fn average(&self) -> f64 {
+        let elapsed = self.elapsed().as_secs_f64();
+        if elapsed > 0.0 {
+            (self.sent + self.failed) as f64 / elapsed
+        } else {
+            0.0
+        }
+    }
+
+    fn record(&mut self, result: Result<Duration, String>) {
+        match result {
+            Ok(latency) => {
+                self.sent += 1;
+                self.latencies.record(latency);
+            }
+            Err(failure) => {
+                self.failed += 1;
+                if self.failures.len() < MAX_FAILURES {
+                    self.failures.push(failure);
+                }
+            }
+        }
+
+        let delivered = self.sent + self.failed;
+        let (start, before) = self.second;
+        let elapsed = start.elapsed();
+        if elapsed >= Duration::from_secs(1) {
+            self.throughput = (delivered - before) as f64 / elapsed.as_secs_f64();
+            self.second = (Instant::now(), delivered);
+        }
+    }
+}
+
+/// Latencies counted in buckets that grow with the latency, so percentiles
+/// stay cheap however many messages are sent.
+#[derive(Debug, Clone)]
+pub struct LatencyHistogram {
+    counts: Vec<u64>,
+    total: u64,
+    max: Duration,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self {
+            counts: vec![
+                0;
+                (64 - SUB_BUCKETS.trailing_zeros() as usize + 1) * SUB_BUCKETS as usize
+            ],
+            total: 0,
+            max: Duration::ZERO,
+        }
+    }
+}
+
+impl LatencyHistogram {
+    pub fn record(&mut self, latency: Duration) {
+        let micros = latency.as_micros().min(u64::MAX as u128) as u64;
+        self.counts[bucket(micros)] += 1;
+        self.total += 1;
+        self.max = self.max.max(latency);
+    }
+
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+
+    /// The latency `percentile` percent of the deliveries were faster than,
+    /// to the start of its bucket.
+    pub fn percentile(&self, percentile: f64) -> Option<Duration> {
+        if self.total == 0 {
+            return None;
+        }
+        let rank = ((percentile / 100.0 * self.total as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (index, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Some(Duration::from_micros(bucket_start(index)));
+            }
+        }
+        Some(self.max)
+    }
+}
+
+/// Values below `SUB_BUCKETS` have a bucket each, above that a power of two
+/// is split into `SUB_BUCKETS` buckets.
+fn bucket(micros: u64) -> usize {
+    if micros < SUB_BUCKETS {
+        return micros as usize;
+    }
+    let bits = SUB_BUCKETS.trailing_zeros() as u64;
+    let exponent = 63 - micros.leading_zeros() as u64;
+    let shift = exponent - bits;
+    ((shift + 1) * SUB_BUCKETS + (micros >> shift) - SUB_BUCKETS) as usize
+}
+
+fn bucket_start(index: usize) -> u64 {
+    let index = index as u64;
+    if index < SUB_BUCKETS {
+        return index;
+    }
+    let shift = index / SUB_BUCKETS - 1;
+    (index % SUB_BUCKETS + SUB_BUCKETS) << shift
+}
+
+/// Sends the messages of a load test, keeping up to `MAX_IN_FLIGHT`
+/// deliveries open, and reports into `progress` as they complete.
+pub async fn run_load(
+    load: LoadTest,
+    deserializers: Arc<DeserializerRegistry>,
+    producer: FutureProducer,
+    progress: Arc<Mutex<LoadProgress>>,
+) -> Result<()> {
+    let key_encoder = deserializers.encoder(&load.topic, Field::Key).await?;
+    let payload_encoder = deserializers.encoder(&load.topic, Field::Payload).await?;
+    // A template that does not work is reported once instead of as a
+    // failure for every message.
+    message(&load, 0, &key_encoder, &payload_encoder)?;
+
+    let start = tokio::time::Instant::now();
+    progress.lock().unwrap().started = Instant::now();
+    let mut in_flight = FuturesUnordered::new();
+    for seq in 0..load.count {
+        if let Some(rate) = load.rate.filter(|rate| *rate > 0) {
+            let due = start + Duration::from_secs_f64(seq as f64 / rate as f64);
+            loop {
+                tokio::select! {
+                    _ = tokio::time::sleep_until(due) => break,
+                    Some(result) = in_flight.next() => progress.lock().unwrap().record(result),
+                }
+            }
+        }
+        while let Some(Some(result)) = in_flight.next().now_or_never() {
+            progress.lock().unwrap().record(result);
+        }
+        if in_flight.len() >= MAX_IN_FLIGHT {
+            if let Some(result) = in_flight.next().await {
+                progress.lock().unwrap().record(result);
+            }
+        }
+
+        let (key, payload, headers) = match message(&load, seq, &key_encoder, &payload_encoder) {
+            Ok(message) => message,
+            Err(e) => {
+                progress
+                    .lock()
+                    .unwrap()
+                    .record(Err(format!("#{} {}", seq, e)));
+                continue;
+            }
+        };
+        let mut record: FutureRecord<[u8], [u8]> = FutureRecord::to(&load.topic);
+        if let Some(key) = &key {
+            record = record.key(key.as_slice());
+        }
+        if let Some(payload) = &payload {
+            record = record.payload(payload.as_slice());
+        }
+        if let Some(partition) = load.template.partition {
+            record = record.partition(partition);
+        }
+        if let Some(headers) = headers {
+            record = record.headers(headers);
+        }
+        // The latency counts from handing the message to the producer, not
+        // from when its delivery is first looked at.
+        let sent = Instant::now();
+        let failure = move |e| format!("#{} {}", seq, e);
+        match enqueue(&producer, record).await {
+            Ok(delivery) => in_flight.push(async move {
+                delivered(delivery)
+                    .await
+                    .map(|()| sent.elapsed())
+                    .map_err(failure)
+            }),
+            Err(e) => progress.lock().unwrap().record(Err(failure(e))),
+        }
+    }
+    while let Some(result) = in_flight.next().await {
+        progress.lock().unwrap().record(result);
+    }
+    Ok(())
+}
+
+type Message = (Option<Vec<u8>>, Option<Vec<u8>>, Option<OwnedHeaders>);
+
+/// The key, payload and headers of the `seq`th message.
+fn message(
+    load: &LoadTest,
+    seq: u64,
+    key_encoder: &Encoder,
+    payload_encoder: &Encoder,
+) -> Result<Message> {
+    let key = match load.key(seq)? {
+        Some(key) => Some(key_encoder.encode(&key).map_err(|e| eyre!("Key: {}", e))?),
+        None => None,
+    };
+    let payload = match &load.template.payload {
+        Some(payload) => Some(
+            payload_encoder
+                .encode(&expand(payload, seq)?)
+                .map_err(|e| eyre!("Payload: {}", e))?,
+        ),
+        None => None,
+    };
+    let headers = match load.template.headers.as_slice() {
+        [] => None,
+        template => {
+            let mut headers = OwnedHeaders::new_with_capacity(template.len());
+            for header in template {
+                headers = headers.add(&header.name, &expand(&header.value, seq)?);
+            }
+            Some(headers)
+        }
+    };
+    Ok((key, payload, headers))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn small_latencies_have_a_bucket_each() {
+        for micros in 0..SUB_BUCKETS {
+            assert_eq!(bucket(micros), micros as usize);
+            assert_eq!(bucket_start(micros as usize), micros);
+        }
+    }
+
+    #[test]
+    fn buckets_cover_every_latency_in_order() {
+        let mut last = 0;
+        for micros in (0..100_000).chain([u64::MAX / 2, u64::MAX]) {
+            let index = bucket(micros);
+            assert!(index >= last, "{} went back to bucket {}", micros, index);
+            assert!(bucket_start(index) <= micros);
+            assert!(index < LatencyHistogram::default().counts.len());
+            last = index;
+        }
+    }
+
+    #[test]
+    fn buckets_stay_narrow() {
+        for micros in [100, 1_000, 12_345, 1_000_000, 987_654_321] {
+            let start = bucket_start(bucket(micros));
+            let next = bucket_start(bucket(micros) + 1);
+            assert!(start <= micros && micros < next);
+            assert!((next - start) as f64 / start as f64 <= 1.0 / SUB_BUCKETS as f64);
+        }
+    }
+
+    #[test]
+    fn percentiles() {
+        let mut histogram = LatencyHistogram::default();
+        assert_eq!(histogram.percentile(50.0), None);
+        for millis in 1..=100 {
+            histogram.record(Duration::from_millis(millis));
+        }
+        let p50 = histogram.percentile(50.0).unwrap();
+        let p99 = histogram.percentile(99.0).unwrap();
+        assert!(p50 <= Duration::from_millis(50) && p50 > Duration::from_millis(48));
+        assert!(p99 <= Duration::from_millis(99) && p99 > Duration::from_millis(96));
+        assert_eq!(histogram.max(), Duration::from_millis(100));
+    }
+}
@@ -1,18 +1,35 @@
 mod app;
+mod codec;
+mod config;
 mod constant;
+mod export;
+mod import;
 mod kafka;
+mod load;
+mod message_buffer;
+mod payload;
+mod query;
+mod redrive;
+mod schema_registry;
+mod search;
 mod tabs;
+mod template;
 mod theme;
+mod timestamp;
 
 use app::App;
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use color_eyre::Result;
 use crossterm::{
+    event::{DisableBracketedPaste, EnableBracketedPaste},
     execute,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen},
 };
+use export::ExportFormat;
+use rdkafka::config::ClientConfig;
+use search::SearchRange;
 
-use std::io::stdout;
+use std::{io::stdout, path::PathBuf};
 
 /// Simple program to greet a person
 #[derive(Parser, Debug)]
@@ -25,6 +42,99 @@ struct Args {
     /// Group id
     #[arg(short, long)]
     group: Option<String>,
+
+    /// Received messages kept in memory, the oldest are dropped beyond it
+    #[arg(long, value_name = "COUNT", default_value_t = 10_000)]
+    max_messages: usize,
+
+    /// Directory of the config file, defaults to ~/.config/kata
+    #[arg(long, value_name = "DIR")]
+    config_dir: Option<PathBuf>,
+
+    /// Schema registry url, used to decode Avro keys and payloads
+    #[arg(long)]
+    schema_registry: Option<String>,
+
+    /// Compiled Protobuf descriptor set, may be given more than once
+    #[arg(long, value_name = "FILE")]
+    proto_descriptor: Vec<PathBuf>,
+
+    /// Decode payloads of matching topics as a Protobuf message type
+    #[arg(long, value_name = "TOPIC_PATTERN=TYPE")]
+    proto_payload: Vec<String>,
+
+    /// Decode keys of matching topics as a Protobuf message type
+    #[arg(long, value_name = "TOPIC_PATTERN=TYPE")]
+    proto_key: Vec<String>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Write a range of a topic to a file instead of starting the UI
+    Export {
+        /// Topic to export
+        topic: String,
+
+        #[arg(short, long, value_enum, default_value = "jsonl")]
+        format: ExportFormat,
+
+        /// File, or directory for raw payloads, defaults to one named after the topic
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// First offset, defaults to the low watermark
+        #[arg(long, conflicts_with = "since")]
+        from: Option<i64>,
+
+        /// Last offset, inclusive, defaults to the high watermark
+        #[arg(long, conflicts_with = "since")]
+        to: Option<i64>,
+
+        /// Start at a time instead, as in "10:42 yesterday" or -1h
+        #[arg(long)]
+        since: Option<String>,
+
+        /// End at a time, defaults to now
+        #[arg(long, requires = "since")]
+        until: Option<String>,
+    },
+}
+
+impl Args {
+    fn client_config(&self) -> ClientConfig {
+        let mut config = ClientConfig::new();
+        config.set("bootstrap.servers", &self.brokers);
+        if let Some(group) = &self.group {
+            config.set("group.id", group);
+        }
+        config
+    }
+}
+
+async fn export(args: &Args, command: &Command) -> Result<()> {
+    let Command::Export {
+        topic,
+        format,
+        output,
+        from,
+        to,
+        since,
+        until,
+    } = command;
+    let range = match since {
+        Some(since) => SearchRange::time(since, until.as_deref().unwrap_or_default())?,
+        None => SearchRange::Offsets {
+            from: *from,
+            to: *to,
+        },
+    };
+    let path = output.clone().unwrap_or_else(|| format.default_path(topic));
+    let count = export::export_topic(&args.client_config(), topic, &range, *format, &path).await?;
+    println!("Exported {} messages to {}", count, path.display());
+    Ok(())
 }
 
 #[tokio::main]
@@ -32,10 +142,15 @@ async fn main() -> Result<()> {
     let args = Args::parse();
 
     color_eyre::install()?;
+    if let Some(command) = &args.command {
+        return export(&args, command).await;
+    }
     let terminal = ratatui::init();
-    execute!(stdout(), EnterAlternateScreen).expect("failed to enter alternate screen");
-    let app_result = App::new(args.brokers, args.group)?.run(terminal).await;
-    execute!(stdout(), LeaveAlternateScreen).expect("failed to leave alternate screen");
+    execute!(stdout(), EnterAlternateScreen, EnableBracketedPaste)
+        .expect("failed to enter alternate screen");
+    let app_result = App::new(args)?.run(terminal).await;
+    execute!(stdout(), DisableBracketedPaste, LeaveAlternateScreen)
+        .expect("failed to leave alternate screen");
     ratatui::restore();
     app_result
 }
@@ -0,0 +1,165 @@
+use std::collections::VecDeque;
+
+use crate::kafka::KafkaMessage;
+
+/// The messages of the viewer, a ring buffer that drops the oldest message
+/// once it is full. Messages are addressed by a sequence number, which stays
+/// the same while older messages are dropped.
+#[derive(Debug)]
+pub struct MessageBuffer {
+    messages: VecDeque<KafkaMessage>,
+    capacity: usize,
+    /// Sequence number of the first message.
+    start: u64,
+    /// Messages dropped since the last clear.
+    dropped: u64,
+    /// Tells the receive task to stop fetching.
+    paused: bool,
+}
+
+impl MessageBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            messages: VecDeque::new(),
+            capacity: capacity.max(1),
+            start: 0,
+            dropped: 0,
+            paused: false,
+        }
+    }
+
+    pub fn push(&mut self, message: KafkaMessage) {
+        if self.messages.len() == self.capacity {
+            self.messages.pop_front();
+            self.start += 1;
+            self.dropped += 1;
+        }
+        self.messages.push_back(message);
+    }
+
+    /// Sequence numbers are not reused, so anything still holding one of
+    /// the cleared messages will not find another message under it.
+    pub fn clear(&mut self) {
+        self.start += self.messages.len() as u64;
+        self.messages.clear();
+        self.dropped = 0;
+        self.paused = false;
+    }
+
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Sequence number of the first message.
+    pub fn start(&self) -> u64 {
+        self.start
+    }
+
+    /// Sequence number the next message will get.
+    pub fn end(&self) -> u64 {
+        self.start + self.messages.len() as u64
+    }
+
+    pub fn get(&self, seq: u64) -> Option<&KafkaMessage> {
+        let index = seq.checked_sub(self.start)?;
+        self.messages.get(index as usize)
+    }
+
+    pub fn get_mut(&mut self, seq: u64) -> Option<&mut KafkaMessage> {
+        let index = seq.checked_sub(self.start)?;
+        self.messages.get_mut(index as usize)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kafka::KafkaTimestamp;
+
+    fn message(offset: i64) -> KafkaMessage {
+        KafkaMessage {
+            topic: "orders".to_string(),
+            partition: 0,
+            offset,
+            timestamp: KafkaTimestamp::NotAvailable,
+            headers: Vec::new(),
+            payload: None,
+            key: None,
+            decoded_payload: None,
+            decoded_key: None,
+            decode_error: None,
+        }
+    }
+
+    fn offsets(buffer: &MessageBuffer) -> Vec<i64> {
+        (buffer.start()..buffer.end())
+            .map(|seq| buffer.get(seq).unwrap().offset)
+            .collect()
+    }
+
+    #[test]
+    fn drops_the_oldest_at_capacity() {
+        let mut buffer = MessageBuffer::new(3);
+        for offset in 0..3 {
+            buffer.push(message(offset));
+        }
+        assert_eq!(buffer.len(), 3);
+        assert_eq!(buffer.dropped(), 0);
+
+        buffer.push(message(3));
+        buffer.push(message(4));
+        assert_eq!(buffer.len(), 3);
+        assert_eq!(buffer.dropped(), 2);
+        assert_eq!((buffer.start(), buffer.end()), (2, 5));
+        assert_eq!(offsets(&buffer), [2, 3, 4]);
+        assert!(buffer.get(1).is_none());
+        assert!(buffer.get_mut(0).is_none());
+        assert!(buffer.get(5).is_none());
+        assert_eq!(buffer.get(4).unwrap().offset, 4);
+    }
+
+    #[test]
+    fn keeps_a_message_at_capacity_zero() {
+        let mut buffer = MessageBuffer::new(0);
+        buffer.push(message(0));
+        buffer.push(message(1));
+        assert_eq!(offsets(&buffer), [1]);
+        assert_eq!(buffer.dropped(), 1);
+    }
+
+    #[test]
+    fn clear_does_not_reuse_sequence_numbers() {
+        let mut buffer = MessageBuffer::new(2);
+        for offset in 0..3 {
+            buffer.push(message(offset));
+        }
+        buffer.set_paused(true);
+        buffer.clear();
+        assert!(buffer.is_empty());
+        assert_eq!(buffer.dropped(), 0);
+        assert!(!buffer.is_paused());
+        assert_eq!((buffer.start(), buffer.end()), (3, 3));
+        assert!(buffer.get(2).is_none());
+
+        buffer.push(message(10));
+        assert!(buffer.get(2).is_none());
+        assert_eq!(buffer.get(3).unwrap().offset, 10);
+        assert_eq!(buffer.end(), 4);
+    }
+}
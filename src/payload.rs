@@ -0,0 +1,257 @@
+pub mod json;
+
+use std::{fmt::Write, io};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ratatui::text::{Line, Span};
+use serde_json::Value;
+use strum::{Display, EnumIter, IntoEnumIterator};
+
+use crate::theme::THEME;
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+/// Where the text of a hex dump row starts, after the offset and 16 bytes.
+const ASCII_COLUMN: usize = 60;
+
+/// How the raw bytes of keys, payloads and headers are shown.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Display, EnumIter)]
+pub enum BytesView {
+    /// UTF-8 text, falling back to a hex dump for binary data.
+    #[default]
+    Text,
+    Hex,
+    Base64,
+}
+
+impl BytesView {
+    pub fn next(self) -> Self {
+        let views: Vec<Self> = Self::iter().collect();
+        let index = views.iter().position(|v| *v == self).unwrap_or(0);
+        views[(index + 1) % views.len()]
+    }
+}
+
+/// Renders a nullable byte field as display lines.
+pub fn bytes_lines(bytes: Option<&[u8]>, view: BytesView) -> Vec<Line<'static>> {
+    let bytes = match bytes {
+        None => return vec![Line::from(null_span())],
+        Some([]) => return vec![Line::from(empty_span())],
+        Some(bytes) => bytes,
+    };
+
+    match view {
+        BytesView::Text => match std::str::from_utf8(bytes) {
+            Ok(text) => text.lines().map(|l| Line::raw(l.to_string())).collect(),
+            Err(_) => hexdump(bytes).into_iter().map(Line::raw).collect(),
+        },
+        BytesView::Hex => hexdump(bytes).into_iter().map(Line::raw).collect(),
+        BytesView::Base64 => vec![Line::raw(STANDARD.encode(bytes))],
+    }
+}
+
+/// Renders a nullable byte field on a single line, cut to `max_chars`. The
+/// text view prefers the compact form of `json` when there is one.
+pub fn bytes_summary(
+    bytes: Option<&[u8]>,
+    json: Option<&Value>,
+    view: BytesView,
+    max_chars: usize,
+) -> Span<'static> {
+    bytes_window(bytes, json, view, 0, max_chars)
+}
+
+/// Like `bytes_summary`, with the first `skip` characters left out for
+/// scrolling sideways.
+pub fn bytes_window(
+    bytes: Option<&[u8]>,
+    json: Option<&Value>,
+    view: BytesView,
+    skip: usize,
+    max_chars: usize,
+) -> Span<'static> {
+    let bytes = match bytes {
+        None => return null_span(),
+        Some([]) => return empty_span(),
+        Some(bytes) => bytes,
+    };
+
+    // Only what can show is encoded, one more char tells it was cut.
+    let chars = skip + max_chars + 1;
+    let head = |len: usize| &bytes[..bytes.len().min(len)];
+    let text = match (view, json) {
+        (BytesView::Text, Some(json)) => json_prefix(json, chars),
+        (BytesView::Text, None) => match std::str::from_utf8(bytes) {
+            Ok(text) => {
+                let end = text
+                    .char_indices()
+                    .nth(chars)
+                    .map_or(text.len(), |(i, _)| i);
+                text[..end].escape_debug().to_string()
+            }
+            Err(_) => hex(head(chars.div_ceil(2))),
+        },
+        (BytesView::Hex, _) => hex(head(chars.div_ceil(2))),
+        (BytesView::Base64, _) => STANDARD.encode(head(chars.div_ceil(4) * 3)),
+    };
+    let text = match text.char_indices().nth(skip) {
+        Some((index, _)) => &text[index..],
+        None if skip > 0 => "",
+        None => &text,
+    };
+    Span::raw(truncate(text, max_chars))
+}
+
+/// The compact JSON of `value`, written no further than `chars` chars.
+fn json_prefix(value: &Value, chars: usize) -> String {
+    let mut prefix = Prefix {
+        bytes: Vec::new(),
+        // A char takes up to 4 bytes.
+        max: chars.saturating_mul(4),
+    };
+    // Fails once the prefix is full.
+    let _ = serde_json::to_writer(&mut prefix, value);
+    String::from_utf8_lossy(&prefix.bytes).into_owned()
+}
+
+/// Keeps the first `max` bytes written to it and refuses the rest.
+struct Prefix {
+    bytes: Vec<u8>,
+    max: usize,
+}
+
+impl io::Write for Prefix {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let room = self.max - self.bytes.len();
+        if room == 0 && !buf.is_empty() {
+            return Err(io::ErrorKind::WriteZero.into());
+        }
+        let n = buf.len().min(room);
+        self.bytes.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Formats bytes in the classic `offset  hex  |ascii|` layout, 16 bytes a row.
+pub fn hexdump(bytes: &[u8]) -> Vec<String> {
+    bytes
+        .chunks(16)
+        .enumerate()
+        .map(|(row, chunk)| {
+            let mut line = String::with_capacity(78);
+            let _ = write!(line, "{:08x}  ", row * 16);
+            for (i, byte) in chunk.iter().enumerate() {
+                if i == 8 {
+                    line.push(' ');
+                }
+                line.push(HEX_DIGITS[(byte >> 4) as usize] as char);
+                line.push(HEX_DIGITS[(byte & 0xf) as usize] as char);
+                line.push(' ');
+            }
+            // Short rows are padded so the text column lines up.
+            let _ = write!(line, "{:1$}|", "", ASCII_COLUMN - line.len());
+            line.extend(chunk.iter().map(|b| {
+                if b.is_ascii_graphic() || *b == b' ' {
+                    *b as char
+                } else {
+                    '.'
+                }
+            }));
+            line.push('|');
+            line
+        })
+        .collect()
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    let mut text = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        text.push(HEX_DIGITS[(byte >> 4) as usize] as char);
+        text.push(HEX_DIGITS[(byte & 0xf) as usize] as char);
+    }
+    text
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((index, _)) => format!("{}…", &text[..index]),
+        None => text.to_string(),
+    }
+}
+
+fn null_span() -> Span<'static> {
+    Span::raw("<null>").style(THEME.placeholder)
+}
+
+fn empty_span() -> Span<'static> {
+    Span::raw("<empty>").style(THEME.placeholder)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hexdump_rows() {
+        let bytes: Vec<u8> = (0x3c..0x4e).collect();
+        assert_eq!(
+            hexdump(&bytes),
+            vec![
+                "00000000  3c 3d 3e 3f 40 41 42 43  44 45 46 47 48 49 4a 4b  |<=>?@ABCDEFGHIJK|",
+                "00000010  4c 4d                                             |LM|",
+            ]
+        );
+        assert_eq!(
+            hexdump(b"\x00\xffa b"),
+            vec!["00000000  00 ff 61 20 62                                    |..a b|"]
+        );
+        assert!(hexdump(&[]).is_empty());
+    }
+
+    #[test]
+    fn windows_match_the_whole_encoding() {
+        let bytes: Vec<u8> = (0..=255).cycle().take(1000).collect();
+        let text = "héllo\t\"wörld\" ".repeat(50);
+        let items: Vec<u32> = (0..200).collect();
+        let json = serde_json::json!({ "items": items, "name": "ü" });
+        let cases = [
+            (bytes.as_slice(), None, BytesView::Hex, hex(&bytes)),
+            (bytes.as_slice(), None, BytesView::Text, hex(&bytes)),
+            (
+                bytes.as_slice(),
+                None,
+                BytesView::Base64,
+                STANDARD.encode(&bytes),
+            ),
+            (
+                text.as_bytes(),
+                None,
+                BytesView::Text,
+                text.escape_debug().to_string(),
+            ),
+            (
+                b"{}".as_slice(),
+                Some(&json),
+                BytesView::Text,
+                json.to_string(),
+            ),
+        ];
+        for (bytes, json, view, whole) in cases {
+            for (skip, max_chars) in [(0, 10), (3, 7), (0, 0), (101, 40), (0, 5000), (4990, 20)] {
+                let expected = truncate(&whole.chars().skip(skip).collect::<String>(), max_chars);
+                let window = bytes_window(Some(bytes), json, view, skip, max_chars);
+                assert_eq!(window.content, expected, "{} {} {}", view, skip, max_chars);
+            }
+        }
+    }
+
+    #[test]
+    fn hex_digits() {
+        assert_eq!(hex(&[0x00, 0x0f, 0xa5, 0xff]), "000fa5ff");
+        assert_eq!(hex(&[]), "");
+    }
+}
@@ -0,0 +1,188 @@
+use std::collections::HashSet;
+
+use ratatui::{
+    style::Modifier,
+    text::{Line, Span},
+};
+use serde_json::Value;
+
+use crate::theme::THEME;
+
+/// Parses bytes as JSON if they hold an object or an array. Bare scalars are
+/// left alone, a payload of `42` or `true` is more likely plain text.
+pub fn parse_json(bytes: &[u8]) -> Option<Value> {
+    let first = bytes.iter().find(|b| !b.is_ascii_whitespace())?;
+    if *first != b'{' && *first != b'[' {
+        return None;
+    }
+    serde_json::from_slice(bytes).ok()
+}
+
+/// An object or array that can be collapsed, identified by its path.
+#[derive(Debug, Clone)]
+pub struct Fold {
+    pub path: String,
+    /// Index of the line that opens the container.
+    pub line: usize,
+}
+
+/// Pretty prints `value` with syntax highlighting. Containers whose path is
+/// in `collapsed` are shown on a single line, `selected` is highlighted.
+pub fn json_lines(
+    value: &Value,
+    root: &str,
+    collapsed: &HashSet<String>,
+    selected: Option<&str>,
+) -> (Vec<Line<'static>>, Vec<Fold>) {
+    let mut builder = Builder {
+        collapsed,
+        selected,
+        lines: Vec::new(),
+        folds: Vec::new(),
+    };
+    builder.value(value, root.to_string(), 0, None, false);
+    (builder.lines, builder.folds)
+}
+
+/// Paths of every container in `value`, used to collapse all of them.
+pub fn json_paths(value: &Value, root: &str) -> Vec<String> {
+    let mut paths = Vec::new();
+    collect_paths(value, root.to_string(), &mut paths);
+    paths
+}
+
+fn collect_paths(value: &Value, path: String, paths: &mut Vec<String>) {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (key, value) in map {
+                collect_paths(value, format!("{}/{}", path, key), paths);
+            }
+            paths.push(path);
+        }
+        Value::Array(items) if !items.is_empty() => {
+            for (index, value) in items.iter().enumerate() {
+                collect_paths(value, format!("{}/{}", path, index), paths);
+            }
+            paths.push(path);
+        }
+        _ => {}
+    }
+}
+
+struct Builder<'a> {
+    collapsed: &'a HashSet<String>,
+    selected: Option<&'a str>,
+    lines: Vec<Line<'static>>,
+    folds: Vec<Fold>,
+}
+
+impl Builder<'_> {
+    fn value(
+        &mut self,
+        value: &Value,
+        path: String,
+        indent: usize,
+        key: Option<&str>,
+        comma: bool,
+    ) {
+        match value {
+            Value::Object(map) if !map.is_empty() => {
+                let children = map
+                    .iter()
+                    .map(|(key, value)| (Some(key.as_str()), value, format!("{}/{}", path, key)))
+                    .collect();
+                self.container(path, indent, key, ('{', '}'), children, comma);
+            }
+            Value::Array(items) if !items.is_empty() => {
+                let children = items
+                    .iter()
+                    .enumerate()
+                    .map(|(index, value)| (None, value, format!("{}/{}", path, index)))
+                    .collect();
+                self.container(path, indent, key, ('[', ']'), children, comma);
+            }
+            scalar => {
+                let mut spans = prefix(" ", indent, key);
+                spans.push(scalar_span(scalar));
+                if comma {
+                    spans.push(punctuation(","));
+                }
+                self.lines.push(Line::from(spans));
+            }
+        }
+    }
+
+    fn container(
+        &mut self,
+        path: String,
+        indent: usize,
+        key: Option<&str>,
+        (open, close): (char, char),
+        children: Vec<(Option<&str>, &Value, String)>,
+        comma: bool,
+    ) {
+        let collapsed = self.collapsed.contains(&path);
+        let selected = self.selected == Some(path.as_str());
+        self.folds.push(Fold {
+            path,
+            line: self.lines.len(),
+        });
+
+        let mut spans = prefix(if collapsed { "▸" } else { "▾" }, indent, key);
+        if selected {
+            spans[0].style = spans[0].style.add_modifier(Modifier::REVERSED);
+        }
+        spans.push(punctuation(&open.to_string()));
+        if collapsed {
+            spans.push(Span::raw("…").style(THEME.placeholder));
+            spans.push(punctuation(&close.to_string()));
+            if comma {
+                spans.push(punctuation(","));
+            }
+            spans.push(Span::raw(format!("  {} items", children.len())).style(THEME.placeholder));
+            self.lines.push(Line::from(spans));
+            return;
+        }
+        self.lines.push(Line::from(spans));
+
+        let count = children.len();
+        for (index, (key, value, path)) in children.into_iter().enumerate() {
+            self.value(value, path, indent + 1, key, index + 1 < count);
+        }
+
+        let mut spans = prefix(" ", indent, None);
+        spans.push(punctuation(&close.to_string()));
+        if comma {
+            spans.push(punctuation(","));
+        }
+        self.lines.push(Line::from(spans));
+    }
+}
+
+/// The fold marker gutter, the indentation and the key of an object member.
+fn prefix(marker: &str, indent: usize, key: Option<&str>) -> Vec<Span<'static>> {
+    let mut spans = vec![
+        Span::raw(marker.to_string()).style(THEME.placeholder),
+        Span::raw(" ".repeat(indent * 2 + 1)),
+    ];
+    if let Some(key) = key {
+        spans.push(Span::raw(Value::from(key).to_string()).style(THEME.json.key));
+        spans.push(punctuation(": "));
+    }
+    spans
+}
+
+fn scalar_span(value: &Value) -> Span<'static> {
+    let style = match value {
+        Value::String(_) => THEME.json.string,
+        Value::Number(_) => THEME.json.number,
+        Value::Bool(_) | Value::Null => THEME.json.literal,
+        // Only empty containers end up here.
+        Value::Object(_) | Value::Array(_) => THEME.json.punctuation,
+    };
+    Span::raw(value.to_string()).style(style)
+}
+
+fn punctuation(text: &str) -> Span<'static> {
+    Span::raw(text.to_string()).style(THEME.json.punctuation)
+}
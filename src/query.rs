@@ -0,0 +1,718 @@
+use std::{borrow::Cow, cmp::Ordering, fmt};
+
+use color_eyre::{eyre::eyre, Result};
+use regex::Regex;
+use serde_json::Value;
+
+use crate::{kafka::KafkaMessage, timestamp::parse_timestamp};
+
+/// A compiled filter expression, for example
+/// `key == "user-42" && value.order.total > 100 && header.trace_id exists`.
+///
+/// Operands are `topic`, `partition`, `offset`, `timestamp`, `key`, `value`
+/// and `header.NAME`. The decoded key and value can be walked with
+/// `.field`, `[0]` and `["odd key"]`. Predicates are `== != < <= > >=`,
+/// `~` for a regex, `contains` and `exists`, combined with `&& || !` and
+/// parentheses. A bare string matches anywhere in the key or the value.
+#[derive(Debug, Clone)]
+pub struct Query {
+    source: String,
+    expr: Expr,
+}
+
+impl Query {
+    pub fn parse(source: &str) -> Result<Self> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.expr()?;
+        if let Some(token) = parser.tokens.get(parser.pos) {
+            return Err(eyre!("Unexpected {} at {}", token.kind, token.start + 1));
+        }
+        Ok(Self {
+            source: source.to_string(),
+            expr,
+        })
+    }
+
+    pub fn matches(&self, message: &KafkaMessage) -> bool {
+        self.expr.eval(message)
+    }
+}
+
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Exists(Operand),
+    Compare(Operand, CompareOp, Value),
+    Matches(Operand, Regex),
+    Contains(Operand, String),
+    /// A bare string, searched for in the key and the value.
+    Text(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone)]
+enum Operand {
+    Topic,
+    Partition,
+    Offset,
+    Timestamp,
+    Key(Vec<PathSegment>),
+    Value(Vec<PathSegment>),
+    Header(String),
+}
+
+#[derive(Debug, Clone)]
+enum PathSegment {
+    Field(String),
+    Index(usize),
+}
+
+impl Expr {
+    /// A missing operand fails every predicate but `!`.
+    fn eval(&self, message: &KafkaMessage) -> bool {
+        match self {
+            Expr::And(lhs, rhs) => lhs.eval(message) && rhs.eval(message),
+            Expr::Or(lhs, rhs) => lhs.eval(message) || rhs.eval(message),
+            Expr::Not(expr) => !expr.eval(message),
+            Expr::Exists(operand) => operand.resolve(message).is_some(),
+            Expr::Compare(operand, op, literal) => match operand.resolve(message) {
+                Some(value) => compare(&value, *op, literal),
+                None => false,
+            },
+            Expr::Matches(operand, regex) => match operand.resolve(message) {
+                Some(value) => regex.is_match(&text(&value)),
+                None => false,
+            },
+            Expr::Contains(operand, needle) => match operand.resolve(message) {
+                Some(value) => text(&value).contains(needle.as_str()),
+                None => false,
+            },
+            Expr::Text(needle) => [Operand::Key(Vec::new()), Operand::Value(Vec::new())]
+                .iter()
+                .filter_map(|operand| operand.resolve(message))
+                .any(|value| text(&value).contains(needle.as_str())),
+        }
+    }
+}
+
+impl Operand {
+    fn resolve<'a>(&self, message: &'a KafkaMessage) -> Option<Cow<'a, Value>> {
+        match self {
+            Operand::Topic => Some(Cow::Owned(Value::from(message.topic.as_str()))),
+            Operand::Partition => Some(Cow::Owned(Value::from(message.partition))),
+            Operand::Offset => Some(Cow::Owned(Value::from(message.offset))),
+            Operand::Timestamp => message
+                .timestamp
+                .millis()
+                .map(|millis| Cow::Owned(Value::from(millis))),
+            Operand::Key(path) => {
+                bytes_value(message.key.as_deref(), message.decoded_key.as_ref(), path)
+            }
+            Operand::Value(path) => bytes_value(
+                message.payload.as_deref(),
+                message.decoded_payload.as_ref(),
+                path,
+            ),
+            Operand::Header(name) => message
+                .headers
+                .iter()
+                .find(|h| h.key == *name)
+                .map(|h| Cow::Owned(Value::from(String::from_utf8_lossy(&h.value)))),
+        }
+    }
+}
+
+/// The decoded value at `path`, or the bytes as text when there is nothing
+/// decoded to walk.
+fn bytes_value<'a>(
+    bytes: Option<&[u8]>,
+    decoded: Option<&'a Value>,
+    path: &[PathSegment],
+) -> Option<Cow<'a, Value>> {
+    let Some(mut value) = decoded else {
+        return match path {
+            [] => bytes.map(|b| Cow::Owned(Value::from(String::from_utf8_lossy(b)))),
+            _ => None,
+        };
+    };
+    for segment in path {
+        value = match segment {
+            PathSegment::Field(name) => value.get(name)?,
+            PathSegment::Index(index) => value.get(index)?,
+        };
+    }
+    Some(Cow::Borrowed(value))
+}
+
+/// Strings without their quotes, everything else as JSON.
+fn text(value: &Value) -> Cow<'_, str> {
+    match value {
+        Value::String(s) => Cow::Borrowed(s),
+        value => Cow::Owned(value.to_string()),
+    }
+}
+
+/// Numbers compare as numbers, also when one side is a numeric string.
+fn compare(value: &Value, op: CompareOp, literal: &Value) -> bool {
+    let ordering = match (number(value), number(literal)) {
+        (Some(lhs), Some(rhs)) => lhs.partial_cmp(&rhs),
+        _ => match (value, literal) {
+            (Value::String(lhs), Value::String(rhs)) => Some(lhs.cmp(rhs)),
+            (lhs, rhs) if matches!(op, CompareOp::Eq | CompareOp::Ne) => Some(if lhs == rhs {
+                Ordering::Equal
+            } else {
+                Ordering::Less
+            }),
+            _ => None,
+        },
+    };
+    let Some(ordering) = ordering else {
+        return false;
+    };
+    match op {
+        CompareOp::Eq => ordering == Ordering::Equal,
+        CompareOp::Ne => ordering != Ordering::Equal,
+        CompareOp::Lt => ordering == Ordering::Less,
+        CompareOp::Le => ordering != Ordering::Greater,
+        CompareOp::Gt => ordering == Ordering::Greater,
+        CompareOp::Ge => ordering != Ordering::Less,
+    }
+}
+
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Ident(String),
+    Str(String),
+    Num(Value),
+    Symbol(&'static str),
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenKind::Ident(ident) => write!(f, "{}", ident),
+            TokenKind::Str(s) => write!(f, "{:?}", s),
+            TokenKind::Num(n) => write!(f, "{}", n),
+            TokenKind::Symbol(symbol) => write!(f, "{}", symbol),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    /// Char position in the source, for error messages.
+    start: usize,
+}
+
+/// Longest symbols first, so `<=` is not read as `<`.
+const SYMBOLS: [&str; 17] = [
+    "==", "!=", "<=", ">=", "=~", "!~", "&&", "||", "<", ">", "~", "!", "(", ")", ".", "[", "]",
+];
+
+fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        let kind = if c == '"' || c == '\'' {
+            let mut s = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => return Err(eyre!("Unterminated string at {}", start + 1)),
+                    Some(&q) if q == c => break,
+                    Some('\\') => {
+                        i += 1;
+                        match chars.get(i) {
+                            Some('n') => s.push('\n'),
+                            Some('t') => s.push('\t'),
+                            Some(&escaped) => s.push(escaped),
+                            None => return Err(eyre!("Unterminated string at {}", start + 1)),
+                        }
+                    }
+                    Some(&other) => s.push(other),
+                }
+                i += 1;
+            }
+            i += 1;
+            TokenKind::Str(s)
+        } else if c.is_ascii_digit()
+            || (c == '-' && chars.get(i + 1).is_some_and(char::is_ascii_digit))
+        {
+            i += 1;
+            // A dot only belongs to the number when a digit follows, so
+            // `items.0.id` is a path. After a dot the number is an index, so
+            // `items.0.1` is one as well.
+            let index = tokens
+                .last()
+                .is_some_and(|t: &Token| t.kind == TokenKind::Symbol("."));
+            while chars.get(i).is_some_and(|c| {
+                c.is_ascii_digit()
+                    || (!index && *c == '.' && chars.get(i + 1).is_some_and(char::is_ascii_digit))
+            }) {
+                i += 1;
+            }
+            let literal: String = chars[start..i].iter().collect();
+            let number = match literal.parse::<i64>() {
+                Ok(n) => Value::from(n),
+                Err(_) => literal
+                    .parse::<f64>()
+                    .ok()
+                    .and_then(|n| serde_json::Number::from_f64(n).map(Value::Number))
+                    .ok_or_else(|| eyre!("Invalid number {} at {}", literal, start + 1))?,
+            };
+            TokenKind::Num(number)
+        } else if c.is_alphabetic() || c == '_' {
+            while chars
+                .get(i)
+                .is_some_and(|c| c.is_alphanumeric() || *c == '_' || *c == '-')
+            {
+                i += 1;
+            }
+            TokenKind::Ident(chars[start..i].iter().collect())
+        } else {
+            let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+            let symbol = SYMBOLS
+                .iter()
+                .find(|s| rest.starts_with(**s))
+                .ok_or_else(|| eyre!("Unexpected {} at {}", c, start + 1))?;
+            i += symbol.chars().count();
+            TokenKind::Symbol(symbol)
+        };
+        tokens.push(Token { kind, start });
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&TokenKind> {
+        self.tokens.get(self.pos).map(|t| &t.kind)
+    }
+
+    fn next(&mut self) -> Result<TokenKind> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .ok_or_else(|| eyre!("Unexpected end of the filter"))?;
+        self.pos += 1;
+        Ok(token.kind.clone())
+    }
+
+    /// Consumes the next token if it is one of `symbols` or the keyword
+    /// spelling of one.
+    fn eat(&mut self, symbols: &[&str]) -> bool {
+        let found = match self.peek() {
+            Some(TokenKind::Symbol(s)) => symbols.contains(s),
+            Some(TokenKind::Ident(ident)) => symbols.contains(&ident.to_lowercase().as_str()),
+            _ => false,
+        };
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn error<T>(&self, expected: &str) -> Result<T> {
+        match self.tokens.get(self.pos) {
+            Some(token) => Err(eyre!(
+                "Expected {}, found {} at {}",
+                expected,
+                token.kind,
+                token.start + 1
+            )),
+            None => Err(eyre!("Expected {} at the end", expected)),
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr> {
+        let mut expr = self.and()?;
+        while self.eat(&["||", "or"]) {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr> {
+        let mut expr = self.unary()?;
+        while self.eat(&["&&", "and"]) {
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        if self.eat(&["!", "not"]) {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        if self.eat(&["("]) {
+            let expr = self.expr()?;
+            if !self.eat(&[")"]) {
+                return self.error(")");
+            }
+            return Ok(expr);
+        }
+        if let Some(TokenKind::Str(s)) = self.peek() {
+            let s = s.clone();
+            self.pos += 1;
+            return Ok(Expr::Text(s));
+        }
+        self.predicate()
+    }
+
+    fn predicate(&mut self) -> Result<Expr> {
+        let operand = self.operand()?;
+        if self.eat(&["exists"]) {
+            return Ok(Expr::Exists(operand));
+        }
+        if self.eat(&["contains"]) {
+            return Ok(Expr::Contains(operand, self.string()?));
+        }
+        if self.eat(&["~", "=~", "matches"]) {
+            return Ok(Expr::Matches(operand, self.regex()?));
+        }
+        if self.eat(&["!~"]) {
+            return Ok(Expr::Not(Box::new(Expr::Matches(operand, self.regex()?))));
+        }
+
+        let op = match self.peek() {
+            Some(TokenKind::Symbol("==")) => CompareOp::Eq,
+            Some(TokenKind::Symbol("!=")) => CompareOp::Ne,
+            Some(TokenKind::Symbol("<")) => CompareOp::Lt,
+            Some(TokenKind::Symbol("<=")) => CompareOp::Le,
+            Some(TokenKind::Symbol(">")) => CompareOp::Gt,
+            Some(TokenKind::Symbol(">=")) => CompareOp::Ge,
+            _ => return self.error("a comparison, ~, contains or exists"),
+        };
+        self.pos += 1;
+
+        let literal = match self.next()? {
+            TokenKind::Str(s) if matches!(operand, Operand::Timestamp) => {
+                Value::from(parse_timestamp(&s)?)
+            }
+            TokenKind::Str(s) => Value::from(s),
+            TokenKind::Num(n) => n,
+            TokenKind::Ident(ident) => match ident.as_str() {
+                "true" => Value::Bool(true),
+                "false" => Value::Bool(false),
+                "null" => Value::Null,
+                _ => {
+                    self.pos -= 1;
+                    return self.error("a string, a number, true, false or null");
+                }
+            },
+            TokenKind::Symbol(_) => {
+                self.pos -= 1;
+                return self.error("a string, a number, true, false or null");
+            }
+        };
+        Ok(Expr::Compare(operand, op, literal))
+    }
+
+    fn operand(&mut self) -> Result<Operand> {
+        let name = match self.peek() {
+            Some(TokenKind::Ident(name)) => name.to_lowercase(),
+            _ => return self.error("a field"),
+        };
+        self.pos += 1;
+        match name.as_str() {
+            "topic" => Ok(Operand::Topic),
+            "partition" => Ok(Operand::Partition),
+            "offset" => Ok(Operand::Offset),
+            "timestamp" | "ts" => Ok(Operand::Timestamp),
+            "key" => Ok(Operand::Key(self.path()?)),
+            "value" | "payload" => Ok(Operand::Value(self.path()?)),
+            "header" | "headers" => match self.path()?.as_slice() {
+                [PathSegment::Field(name)] => Ok(Operand::Header(name.clone())),
+                _ => Err(eyre!("Expected a header name, as in header.trace_id")),
+            },
+            _ => {
+                self.pos -= 1;
+                self.error("topic, partition, offset, timestamp, key, value or header")
+            }
+        }
+    }
+
+    fn path(&mut self) -> Result<Vec<PathSegment>> {
+        let mut path = Vec::new();
+        loop {
+            if self.eat(&["."]) {
+                match self.next()? {
+                    TokenKind::Ident(name) => path.push(PathSegment::Field(name)),
+                    TokenKind::Num(Value::Number(n)) if n.is_u64() => {
+                        path.push(PathSegment::Index(n.as_u64().unwrap_or_default() as usize))
+                    }
+                    _ => {
+                        self.pos -= 1;
+                        return self.error("a field name");
+                    }
+                }
+            } else if self.eat(&["["]) {
+                match self.next()? {
+                    TokenKind::Str(name) => path.push(PathSegment::Field(name)),
+                    TokenKind::Num(Value::Number(n)) if n.is_u64() => {
+                        path.push(PathSegment::Index(n.as_u64().unwrap_or_default() as usize))
+                    }
+                    _ => {
+                        self.pos -= 1;
+                        return self.error("an index or a quoted field name");
+                    }
+                }
+                if !self.eat(&["]"]) {
+                    return self.error("]");
+                }
+            } else {
+                return Ok(path);
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String> {
+        match self.peek() {
+            Some(TokenKind::Str(s)) => {
+                let s = s.clone();
+                self.pos += 1;
+                Ok(s)
+            }
+            _ => self.error("a string"),
+        }
+    }
+
+    fn regex(&mut self) -> Result<Regex> {
+        let pattern = self.string()?;
+        // Syntax errors span several lines, the last one says what is wrong.
+        Regex::new(&pattern).map_err(|e| {
+            let e = e.to_string();
+            let reason = e.lines().last().unwrap_or_default();
+            eyre!("Invalid regex: {}", reason.trim_start_matches("error: "))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::kafka::{KafkaHeader, KafkaTimestamp};
+
+    fn message(key: &str, value: Value) -> KafkaMessage {
+        KafkaMessage {
+            topic: "orders".to_string(),
+            partition: 2,
+            offset: 1042,
+            timestamp: KafkaTimestamp::CreateTime(1_700_000_000_000),
+            headers: vec![KafkaHeader {
+                key: "trace_id".to_string(),
+                value: b"abc-123".to_vec(),
+            }],
+            payload: Some(value.to_string().into_bytes()),
+            key: Some(key.as_bytes().to_vec()),
+            decoded_payload: Some(value),
+            decoded_key: None,
+            decode_error: None,
+        }
+    }
+
+    fn order() -> KafkaMessage {
+        message(
+            "user-42",
+            json!({
+                "order": { "total": 150.5, "status": "paid", "count": "7" },
+                "items": [{ "id": "a", "tags": ["x", "y"] }, { "id": "b" }],
+                "odd key": true,
+                "gift": null,
+            }),
+        )
+    }
+
+    fn matches(query: &str) -> bool {
+        Query::parse(query)
+            .unwrap_or_else(|e| panic!("{}: {}", query, e))
+            .matches(&order())
+    }
+
+    fn error(query: &str) -> String {
+        Query::parse(query).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn tokenizes_paths_and_numbers() {
+        let kinds = |source| {
+            tokenize(source)
+                .unwrap()
+                .into_iter()
+                .map(|t| t.kind)
+                .collect::<Vec<_>>()
+        };
+        let ident = |s: &str| TokenKind::Ident(s.to_string());
+        assert_eq!(
+            kinds("value.items.0.1"),
+            vec![
+                ident("value"),
+                TokenKind::Symbol("."),
+                ident("items"),
+                TokenKind::Symbol("."),
+                TokenKind::Num(json!(0)),
+                TokenKind::Symbol("."),
+                TokenKind::Num(json!(1)),
+            ]
+        );
+        assert_eq!(
+            kinds("x >= -1.5"),
+            vec![
+                ident("x"),
+                TokenKind::Symbol(">="),
+                TokenKind::Num(json!(-1.5))
+            ]
+        );
+        assert_eq!(
+            kinds("'it\\'s' != \"a\\nb\""),
+            vec![
+                TokenKind::Str("it's".to_string()),
+                TokenKind::Symbol("!="),
+                TokenKind::Str("a\nb".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn compares_metadata() {
+        assert!(matches("topic == \"orders\""));
+        assert!(matches("partition == 2 && offset >= 1000"));
+        assert!(matches("offset < 2000 and not partition != 2"));
+        assert!(matches("timestamp > \"2023-01-01T00:00:00Z\""));
+        assert!(!matches("ts < 1700000000000"));
+        assert!(matches("key == \"user-42\" || key == \"user-43\""));
+        assert!(matches(
+            "header.trace_id exists && header.trace_id ~ \"^abc-\\\\d+$\""
+        ));
+        assert!(!matches("header.missing exists"));
+    }
+
+    #[test]
+    fn walks_decoded_values() {
+        assert!(matches("value.order.total > 100"));
+        assert!(matches("value.order.status == 'paid'"));
+        assert!(matches("value.items[1].id == \"b\""));
+        assert!(matches("value.items.0.tags.1 == \"y\""));
+        assert!(matches("value[\"odd key\"] == true"));
+        assert!(matches("value.gift == null"));
+        assert!(matches("value.items contains \"tags\""));
+        assert!(!matches("value.items.5 exists"));
+        assert!(matches("!value.order.missing exists"));
+    }
+
+    #[test]
+    fn bare_strings_search_key_and_value() {
+        assert!(matches("\"user-4\""));
+        assert!(matches("\"paid\""));
+        assert!(!matches("\"refunded\""));
+    }
+
+    #[test]
+    fn precedence_and_parentheses() {
+        assert!(matches("partition == 1 && offset == 0 || partition == 2"));
+        assert!(!matches(
+            "partition == 1 && (offset == 0 || partition == 2)"
+        ));
+        assert!(matches("!(partition == 1)"));
+    }
+
+    #[test]
+    fn mismatched_types() {
+        // Numeric strings compare as numbers.
+        assert!(matches("value.order.count > 5"));
+        assert!(matches("value.order.count == 7"));
+        // Other values only compare for equality.
+        assert!(!matches("value.order.status > 5"));
+        assert!(!matches("value.order.status == 5"));
+        assert!(matches("value.order.status != 5"));
+        assert!(!matches("value.order < true"));
+        assert!(!matches("value.gift == false"));
+        // Missing operands fail every predicate.
+        assert!(!matches("value.nothing != 1"));
+        assert!(!matches("value.nothing contains \"\""));
+    }
+
+    #[test]
+    fn raw_values_without_a_decoder() {
+        let mut raw = order();
+        raw.decoded_payload = None;
+        let query = |source| Query::parse(source).unwrap().matches(&raw);
+        assert!(query("value contains \"paid\""));
+        assert!(!query("value.order exists"));
+        assert!(query("key == \"user-42\""));
+    }
+
+    #[test]
+    fn error_messages() {
+        assert_eq!(error(""), "Expected a field at the end");
+        assert_eq!(error("key =="), "Unexpected end of the filter");
+        assert_eq!(error("\"open"), "Unterminated string at 1");
+        assert_eq!(error("key == 1 $"), "Unexpected $ at 10");
+        assert_eq!(
+            error("size > 3"),
+            "Expected topic, partition, offset, timestamp, key, value or header, found size at 1"
+        );
+        assert_eq!(
+            error("key"),
+            "Expected a comparison, ~, contains or exists at the end"
+        );
+        assert_eq!(error("(key exists"), "Expected ) at the end");
+        assert_eq!(error("key == 1 1"), "Unexpected 1 at 10");
+        assert_eq!(
+            error("key == key"),
+            "Expected a string, a number, true, false or null, found key at 8"
+        );
+        assert_eq!(
+            error("value.\"x\" exists"),
+            "Expected a field name, found \"x\" at 7"
+        );
+        assert_eq!(
+            error("header exists"),
+            "Expected a header name, as in header.trace_id"
+        );
+        assert_eq!(error("key contains 1"), "Expected a string, found 1 at 14");
+        assert!(error("key ~ \"(\"").starts_with("Invalid regex: "));
+        assert!(error("ts > \"soon\"").starts_with("Unrecognized timestamp"));
+    }
+}
@@ -0,0 +1,199 @@
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use color_eyre::{eyre::eyre, Result};
+use futures::{stream::FuturesUnordered, StreamExt};
+use rdkafka::{
+    config::ClientConfig,
+    message::OwnedHeaders,
+    producer::{FutureProducer, FutureRecord},
+};
+
+use crate::{
+    codec::DeserializerRegistry,
+    import::MAX_FAILURES,
+    kafka::{delivered, enqueue, KafkaMessage, KafkaTopic},
+    search::{Matcher, RangeScanner, Scope, SearchProgress, SearchRange},
+};
+
+/// Added to every redriven message, `topic/partition/offset` of where it
+/// was read.
+pub const PROVENANCE_HEADER: &str = "kata-redrive-source";
+
+/// Messages scanned between checkpoints.
+const CHECKPOINT_INTERVAL: u64 = 500;
+
+/// How often a paused redrive looks whether it should go on.
+const PAUSE_POLL: Duration = Duration::from_millis(200);
+
+/// Moves a range of a topic, such as a dead letter queue, into another
+/// topic. Keys, payloads and headers are kept, the partition and timestamp
+/// are left to the producer.
+#[derive(Debug, Clone)]
+pub struct Redrive {
+    pub source: KafkaTopic,
+    pub destination: String,
+    pub range: SearchRange,
+    /// Only the messages that match are moved, all when left out.
+    pub filter: Option<(Matcher, Scope)>,
+    /// Start from the last checkpoint of the same source and destination.
+    pub resume: bool,
+}
+
+impl Redrive {
+    /// The consumer group the checkpoints are committed to.
+    pub fn group(&self) -> String {
+        format!("kata-redrive-{}-{}", self.source.name, self.destination)
+    }
+}
+
+/// Shared between the redrive task and the view.
+#[derive(Debug, Default)]
+pub struct RedriveProgress {
+    pub sent: u64,
+    /// Scanned messages the filter left out.
+    pub skipped: u64,
+    pub failed: u64,
+    /// The first `MAX_FAILURES` failures.
+    pub failures: Vec<String>,
+    /// Messages of the range moved by an earlier run.
+    pub resumed: u64,
+    /// Scanned messages covered by the last checkpoint.
+    pub checkpoint: u64,
+    /// Set while the task waits to be resumed.
+    pub paused: bool,
+    pub finished: bool,
+    pub error: Option<String>,
+}
+
+/// Moves the messages of the range, committing a checkpoint every
+/// `CHECKPOINT_INTERVAL` scanned messages and before pausing. A checkpoint
+/// is only committed once everything before it was delivered, so a redrive
+/// resumed after a failure or a cancel sends at most a batch twice.
+pub async fn run_redrive(
+    redrive: Redrive,
+    config: ClientConfig,
+    deserializers: Arc<DeserializerRegistry>,
+    producer: FutureProducer,
+    scan: Arc<Mutex<SearchProgress>>,
+    progress: Arc<Mutex<RedriveProgress>>,
+    pause: Arc<AtomicBool>,
+) -> Result<()> {
+    let mut scanner = RangeScanner::checkpointed(
+        &config,
+        &redrive.source,
+        &redrive.range,
+        &redrive.group(),
+        redrive.resume,
+        scan.clone(),
+    )
+    .await?;
+    progress.lock().unwrap().resumed = scanner.resumed();
+
+    let mut in_flight = FuturesUnordered::new();
+    let mut since_checkpoint = 0;
+    loop {
+        let paused = pause.load(Ordering::Relaxed);
+        if since_checkpoint >= CHECKPOINT_INTERVAL || (paused && since_checkpoint > 0) {
+            checkpoint(&mut in_flight, &scanner, &scan, &progress).await?;
+            since_checkpoint = 0;
+        }
+        if paused {
+            progress.lock().unwrap().paused = true;
+            while pause.load(Ordering::Relaxed) {
+                tokio::time::sleep(PAUSE_POLL).await;
+            }
+            progress.lock().unwrap().paused = false;
+        }
+
+        let Some(mut message) = scanner.next().await? else {
+            break;
+        };
+        since_checkpoint += 1;
+        if let Some((matcher, scope)) = &redrive.filter {
+            deserializers.decode_message(&mut message).await;
+            if !matcher.matches(*scope, &message) {
+                progress.lock().unwrap().skipped += 1;
+                continue;
+            }
+        }
+        in_flight.push(send(&producer, &redrive.destination, message).await);
+    }
+    checkpoint(&mut in_flight, &scanner, &scan, &progress).await
+}
+
+/// Waits for the deliveries and commits the scanned positions, unless a
+/// delivery failed.
+async fn checkpoint<F>(
+    in_flight: &mut FuturesUnordered<F>,
+    scanner: &RangeScanner,
+    scan: &Mutex<SearchProgress>,
+    progress: &Mutex<RedriveProgress>,
+) -> Result<()>
+where
+    F: Future<Output = Result<(), String>>,
+{
+    let mut failed = 0;
+    while let Some(result) = in_flight.next().await {
+        let mut progress = progress.lock().unwrap();
+        match result {
+            Ok(()) => progress.sent += 1,
+            Err(failure) => {
+                failed += 1;
+                progress.failed += 1;
+                if progress.failures.len() < MAX_FAILURES {
+                    progress.failures.push(failure);
+                }
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(eyre!(
+            "{} deliveries failed, resume to send them again from the last checkpoint",
+            failed
+        ));
+    }
+    scanner.commit().await?;
+    let scanned = scan.lock().unwrap().scanned;
+    progress.lock().unwrap().checkpoint = scanned;
+    Ok(())
+}
+
+/// Queues the message for `topic`, the returned future completes once it
+/// was delivered.
+async fn send(
+    producer: &FutureProducer,
+    topic: &str,
+    message: KafkaMessage,
+) -> impl Future<Output = Result<(), String>> {
+    let provenance = format!("{}/{}/{}", message.topic, message.partition, message.offset);
+    let headers = message
+        .headers
+        .iter()
+        .fold(OwnedHeaders::new(), |headers, header| {
+            headers.add(&header.key, header.value.as_slice())
+        })
+        .add(PROVENANCE_HEADER, &provenance);
+
+    let mut record: FutureRecord<[u8], [u8]> = FutureRecord::to(topic).headers(headers);
+    if let Some(key) = &message.key {
+        record = record.key(key.as_slice());
+    }
+    if let Some(payload) = &message.payload {
+        record = record.payload(payload.as_slice());
+    }
+    let delivery = enqueue(producer, record).await;
+    let failure = move |e| format!("{} {}", provenance, e);
+    async move {
+        match delivery {
+            Ok(delivery) => delivered(delivery).await.map_err(failure),
+            Err(e) => Err(failure(e)),
+        }
+    }
+}
@@ -0,0 +1,506 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use color_eyre::{eyre::eyre, Result};
+use rdkafka::{
+    config::ClientConfig,
+    consumer::{BaseConsumer, CommitMode, Consumer},
+    error::KafkaError,
+    topic_partition_list::{Offset, TopicPartitionList},
+};
+use regex::Regex;
+use strum::{Display, EnumIter};
+
+use crate::{
+    codec::DeserializerRegistry,
+    constant::METADATA_TIMEOUT,
+    kafka::{KafkaMessage, KafkaTopic},
+    query::Query,
+    timestamp::parse_timestamp,
+};
+
+/// Matches beyond this are counted but not kept.
+pub const MAX_MATCHES: usize = 10_000;
+
+/// Short, so a cancelled search stops quickly.
+const SEARCH_POLL_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// A scan of a range of a topic for messages that match.
+#[derive(Debug, Clone)]
+pub struct Search {
+    pub topic: KafkaTopic,
+    pub range: SearchRange,
+    pub matcher: Matcher,
+    pub scope: Scope,
+}
+
+/// Where the scan starts and ends in every partition.
+#[derive(Debug, Clone)]
+pub enum SearchRange {
+    /// Inclusive offsets, the watermarks when left out.
+    Offsets { from: Option<i64>, to: Option<i64> },
+    /// Timestamps in milliseconds, up to the high watermark when `to` is
+    /// left out.
+    Time { from: i64, to: Option<i64> },
+}
+
+impl SearchRange {
+    /// Parses offsets as typed, empty for the watermarks.
+    pub fn offsets(from: &str, to: &str) -> Result<Self> {
+        let offset = |value: &str| match value.trim() {
+            "" => Ok(None),
+            value => value
+                .parse()
+                .map(Some)
+                .map_err(|_| eyre!("Invalid offset \"{}\"", value)),
+        };
+        Ok(SearchRange::Offsets {
+            from: offset(from)?,
+            to: offset(to)?,
+        })
+    }
+
+    /// Parses times as typed, `to` empty for up to now.
+    pub fn time(from: &str, to: &str) -> Result<Self> {
+        if from.trim().is_empty() {
+            return Err(eyre!("Enter the time to start from"));
+        }
+        let to = match to.trim() {
+            "" => None,
+            to => Some(parse_timestamp(to)?),
+        };
+        Ok(SearchRange::Time {
+            from: parse_timestamp(from.trim())?,
+            to,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Matcher {
+    Substring(String),
+    Regex(Regex),
+    /// Queries name the fields themselves, the scope does not apply.
+    Query(Query),
+}
+
+impl fmt::Display for Matcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Matcher::Substring(needle) => write!(f, "{:?}", needle),
+            Matcher::Regex(regex) => write!(f, "/{}/", regex),
+            Matcher::Query(query) => write!(f, "{}", query),
+        }
+    }
+}
+
+/// The parts of a message a substring or regex is matched against.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Display, EnumIter)]
+pub enum Scope {
+    #[default]
+    All,
+    Key,
+    Value,
+    Headers,
+}
+
+impl Matcher {
+    /// Whether the message matches in `scope`, the key and payload should
+    /// be decoded first.
+    pub fn matches(&self, scope: Scope, message: &KafkaMessage) -> bool {
+        let texts = match self {
+            Matcher::Query(query) => return query.matches(message),
+            _ => texts(scope, message),
+        };
+        texts.iter().any(|text| match self {
+            Matcher::Substring(needle) => text.contains(needle.as_str()),
+            Matcher::Regex(regex) => regex.is_match(text),
+            Matcher::Query(_) => false,
+        })
+    }
+}
+
+/// The decoded key and value when there is one, their bytes as text
+/// otherwise, and headers as `name: value`.
+fn texts<'a>(scope: Scope, message: &'a KafkaMessage) -> Vec<Cow<'a, str>> {
+    let field = |bytes: Option<&'a [u8]>, decoded: Option<&serde_json::Value>| match decoded {
+        Some(serde_json::Value::String(s)) => Some(Cow::Owned(s.clone())),
+        Some(value) => Some(Cow::Owned(value.to_string())),
+        None => bytes.map(String::from_utf8_lossy),
+    };
+
+    let mut texts = Vec::new();
+    if matches!(scope, Scope::All | Scope::Key) {
+        texts.extend(field(message.key.as_deref(), message.decoded_key.as_ref()));
+    }
+    if matches!(scope, Scope::All | Scope::Value) {
+        texts.extend(field(
+            message.payload.as_deref(),
+            message.decoded_payload.as_ref(),
+        ));
+    }
+    if matches!(scope, Scope::All | Scope::Headers) {
+        texts.extend(
+            message
+                .headers
+                .iter()
+                .map(|h| Cow::Owned(format!("{}: {}", h.key, String::from_utf8_lossy(&h.value)))),
+        );
+    }
+    texts
+}
+
+/// Shared between the search task and the view.
+#[derive(Debug, Default)]
+pub struct SearchProgress {
+    pub partitions: Vec<PartitionProgress>,
+    pub scanned: u64,
+    /// Number of matches, `messages` holds the first `MAX_MATCHES`.
+    pub matched: u64,
+    pub messages: Vec<KafkaMessage>,
+    pub finished: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PartitionProgress {
+    pub id: i32,
+    pub low: i64,
+    pub high: i64,
+    pub start: i64,
+    /// Exclusive.
+    pub end: i64,
+    /// Offset of the next message to scan.
+    pub position: i64,
+}
+
+impl PartitionProgress {
+    pub fn is_done(&self) -> bool {
+        self.position >= self.end
+    }
+
+    pub fn ratio(&self) -> f64 {
+        if self.end <= self.start {
+            return 1.0;
+        }
+        ((self.position - self.start) as f64 / (self.end - self.start) as f64).clamp(0.0, 1.0)
+    }
+}
+
+/// Scans the range and reports into `progress` as it goes.
+pub async fn run_search(
+    search: Search,
+    config: ClientConfig,
+    deserializers: Arc<DeserializerRegistry>,
+    progress: Arc<Mutex<SearchProgress>>,
+) -> Result<()> {
+    let mut scanner =
+        RangeScanner::new(&config, &search.topic, &search.range, progress.clone()).await?;
+    while let Some(mut message) = scanner.next().await? {
+        deserializers.decode_message(&mut message).await;
+        if search.matcher.matches(search.scope, &message) {
+            let mut progress = progress.lock().unwrap();
+            progress.matched += 1;
+            if progress.messages.len() < MAX_MATCHES {
+                progress.messages.push(message);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Reads a range of a topic with a consumer of its own, so the message
+/// viewer is left alone, and keeps the partitions and the scanned count of
+/// `progress` up to date. The consumer calls that block run on the blocking
+/// threads, not on the runtime's workers.
+pub struct RangeScanner {
+    consumer: Arc<BaseConsumer>,
+    topic: String,
+    /// Index into the partitions of the progress by partition id.
+    index: HashMap<i32, usize>,
+    progress: Arc<Mutex<SearchProgress>>,
+    /// Messages of the range before the positions resumed from.
+    resumed: u64,
+}
+
+impl RangeScanner {
+    pub async fn new(
+        config: &ClientConfig,
+        topic: &KafkaTopic,
+        range: &SearchRange,
+        progress: Arc<Mutex<SearchProgress>>,
+    ) -> Result<Self> {
+        let consumer: BaseConsumer = config
+            .clone()
+            .set("enable.auto.commit", "false")
+            .set("enable.partition.eof", "true")
+            .create()?;
+        let (task_topic, range) = (topic.clone(), range.clone());
+        let (consumer, partitions) = blocking(move || {
+            let partitions = partition_ranges(&consumer, &task_topic, &range)?;
+            Ok((consumer, partitions))
+        })
+        .await?;
+        Self::assign(consumer, topic, partitions, progress, 0)
+    }
+
+    /// Like `new` with a consumer in `group`, so `commit` can keep the
+    /// positions as a checkpoint. With `resume` the range starts at the
+    /// positions committed last, where they are inside it.
+    pub async fn checkpointed(
+        config: &ClientConfig,
+        topic: &KafkaTopic,
+        range: &SearchRange,
+        group: &str,
+        resume: bool,
+        progress: Arc<Mutex<SearchProgress>>,
+    ) -> Result<Self> {
+        let consumer: BaseConsumer = config
+            .clone()
+            .set("group.id", group)
+            .set("enable.auto.commit", "false")
+            .set("enable.partition.eof", "true")
+            .create()?;
+        let (task_topic, range) = (topic.clone(), range.clone());
+        let (consumer, partitions, resumed) = blocking(move || {
+            let topic = task_topic;
+            let mut partitions = partition_ranges(&consumer, &topic, &range)?;
+
+            let mut resumed = 0;
+            if resume {
+                let mut list = TopicPartitionList::new();
+                for partition in &partitions {
+                    list.add_partition(&topic.name, partition.id);
+                }
+                let committed = consumer.committed_offsets(list, METADATA_TIMEOUT)?;
+                for partition in &mut partitions {
+                    let offset = committed
+                        .find_partition(&topic.name, partition.id)
+                        .map(|e| e.offset());
+                    if let Some(Offset::Offset(offset)) = offset {
+                        let start = offset.clamp(partition.start, partition.end);
+                        resumed += (start - partition.start) as u64;
+                        partition.start = start;
+                        partition.position = start;
+                    }
+                }
+            }
+            Ok((consumer, partitions, resumed))
+        })
+        .await?;
+        Self::assign(consumer, topic, partitions, progress, resumed)
+    }
+
+    fn assign(
+        consumer: BaseConsumer,
+        topic: &KafkaTopic,
+        partitions: Vec<PartitionProgress>,
+        progress: Arc<Mutex<SearchProgress>>,
+        resumed: u64,
+    ) -> Result<Self> {
+        let mut assignment = TopicPartitionList::new();
+        for partition in partitions.iter().filter(|p| !p.is_done()) {
+            assignment.add_partition_offset(
+                &topic.name,
+                partition.id,
+                Offset::Offset(partition.start),
+            )?;
+        }
+        let mut index: HashMap<i32, usize> = HashMap::new();
+        for (i, partition) in partitions.iter().enumerate() {
+            index.insert(partition.id, i);
+        }
+        progress.lock().unwrap().partitions = partitions;
+        consumer.assign(&assignment)?;
+
+        Ok(Self {
+            consumer: Arc::new(consumer),
+            topic: topic.name.clone(),
+            index,
+            progress,
+            resumed,
+        })
+    }
+
+    pub fn resumed(&self) -> u64 {
+        self.resumed
+    }
+
+    /// Commits the position of every partition to the group of a
+    /// checkpointed scanner. Everything before them is taken as done.
+    pub async fn commit(&self) -> Result<()> {
+        let mut list = TopicPartitionList::new();
+        for partition in &self.progress.lock().unwrap().partitions {
+            list.add_partition_offset(
+                &self.topic,
+                partition.id,
+                Offset::Offset(partition.position),
+            )?;
+        }
+        let consumer = self.consumer.clone();
+        blocking(move || Ok(consumer.commit(&list, CommitMode::Sync)?)).await
+    }
+
+    /// The next message of the range, `None` once every partition was read
+    /// to its end.
+    pub async fn next(&mut self) -> Result<Option<KafkaMessage>> {
+        loop {
+            tokio::task::yield_now().await;
+            if self
+                .progress
+                .lock()
+                .unwrap()
+                .partitions
+                .iter()
+                .all(|p| p.is_done())
+            {
+                return Ok(None);
+            }
+
+            // Waiting for a message blocks, it is only done when none is
+            // ready yet.
+            let message = match self.consumer.poll(Duration::ZERO) {
+                Some(message) => Some(message.map(KafkaMessage::from)),
+                None => {
+                    let consumer = self.consumer.clone();
+                    blocking(move || {
+                        Ok(consumer
+                            .poll(SEARCH_POLL_TIMEOUT)
+                            .map(|message| message.map(KafkaMessage::from)))
+                    })
+                    .await?
+                }
+            };
+            match message {
+                Some(Ok(message)) => {
+                    let Some(&i) = self.index.get(&message.partition) else {
+                        continue;
+                    };
+                    let done = {
+                        let mut progress = self.progress.lock().unwrap();
+                        let partition = &mut progress.partitions[i];
+                        if message.offset >= partition.end {
+                            None
+                        } else {
+                            partition.position = message.offset + 1;
+                            let done = partition.is_done();
+                            progress.scanned += 1;
+                            Some(done)
+                        }
+                    };
+                    match done {
+                        // Past the end of the range.
+                        None => self.finish_partition(i)?,
+                        Some(done) => {
+                            if done {
+                                self.finish_partition(i)?;
+                            }
+                            return Ok(Some(message));
+                        }
+                    }
+                }
+                // Nothing more to read before the end, e.g. when the range ends
+                // at a transaction marker or at compacted away offsets.
+                Some(Err(KafkaError::PartitionEOF(partition))) => {
+                    if let Some(&i) = self.index.get(&partition) {
+                        self.finish_partition(i)?;
+                    }
+                }
+                Some(Err(e)) => return Err(eyre!(e)),
+                None => {}
+            }
+        }
+    }
+
+    /// Marks the partition as scanned and stops fetching it.
+    fn finish_partition(&self, i: usize) -> Result<()> {
+        let mut progress = self.progress.lock().unwrap();
+        let partition = &mut progress.partitions[i];
+        partition.position = partition.position.max(partition.end);
+        let mut list = TopicPartitionList::new();
+        list.add_partition(&self.topic, partition.id);
+        self.consumer.pause(&list)?;
+        Ok(())
+    }
+}
+
+/// Runs blocking consumer calls on the blocking threads.
+async fn blocking<T, F>(f: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T> + Send + 'static,
+{
+    tokio::task::spawn_blocking(f).await?
+}
+
+/// Resolves the range into offsets with fresh watermarks.
+fn partition_ranges(
+    consumer: &BaseConsumer,
+    topic: &KafkaTopic,
+    range: &SearchRange,
+) -> Result<Vec<PartitionProgress>> {
+    let mut partitions = Vec::new();
+    for partition in &topic.partitions {
+        let (low, high) = consumer.fetch_watermarks(&topic.name, partition.id, METADATA_TIMEOUT)?;
+        let (start, end) = match range {
+            SearchRange::Offsets { from, to } => (
+                from.unwrap_or(low).clamp(low, high),
+                to.map(|to| to.saturating_add(1))
+                    .unwrap_or(high)
+                    .clamp(low, high),
+            ),
+            SearchRange::Time { .. } => (low, high),
+        };
+        partitions.push(PartitionProgress {
+            id: partition.id,
+            low,
+            high,
+            start,
+            end,
+            position: start,
+        });
+    }
+
+    if let SearchRange::Time { from, to } = range {
+        let starts = offsets_for_time(consumer, &topic.name, &partitions, *from)?;
+        let ends = match to {
+            Some(to) => offsets_for_time(consumer, &topic.name, &partitions, *to)?,
+            None => partitions.iter().map(|p| p.high).collect(),
+        };
+        for ((partition, start), end) in partitions.iter_mut().zip(starts).zip(ends) {
+            partition.start = start;
+            partition.end = end.max(start);
+            partition.position = start;
+        }
+    }
+    Ok(partitions)
+}
+
+/// The first offset at or after `millis` of every partition, the high
+/// watermark for partitions without a later message.
+fn offsets_for_time(
+    consumer: &BaseConsumer,
+    topic: &str,
+    partitions: &[PartitionProgress],
+    millis: i64,
+) -> Result<Vec<i64>> {
+    let mut list = TopicPartitionList::new();
+    for partition in partitions {
+        list.add_partition_offset(topic, partition.id, Offset::Offset(millis))?;
+    }
+    let list = consumer.offsets_for_times(list, METADATA_TIMEOUT)?;
+    Ok(partitions
+        .iter()
+        .map(
+            |p| match list.find_partition(topic, p.id).map(|e| e.offset()) {
+                Some(Offset::Offset(offset)) => offset,
+                _ => p.high,
+            },
+        )
+        .collect())
+}
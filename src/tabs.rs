@@ -1,9 +1,25 @@
 mod broker;
 mod group;
+mod input;
+mod message_detail;
+mod message_export;
+mod message_filter;
+mod message_resend;
+mod message_start;
+mod message_table;
+mod send_template;
+mod text_editor;
 mod topic;
+mod topic_create;
+mod topic_delete;
+mod topic_import;
+mod topic_load;
+mod topic_redrive;
+mod topic_search;
 mod topic_send;
 pub use broker::BrokerTab;
 pub use group::GroupTab;
+pub use text_editor::edit_externally;
 pub use topic::TopicTab;
 
 use crate::app::{App, Mode};
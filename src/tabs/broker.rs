@@ -54,10 +54,7 @@ impl BrokerTab {
             .broker_list
             .items
             .iter()
-            .enumerate()
-            .map(|(_, broker)| {
-                ListItem::new(Text::from(format!("{}:{}", broker.host, broker.port)))
-            })
+            .map(|broker| ListItem::new(Text::from(format!("{}:{}", broker.host, broker.port))))
             .collect();
 
         let list = List::new(items)
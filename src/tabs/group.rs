@@ -1,22 +1,42 @@
 use std::{sync::{Arc, Mutex}, time::Duration};
 
-use crate::{app::Mode, kafka::KafkaGroup, theme::THEME};
+use crate::{
+    app::Mode,
+    kafka::{group_offsets, GroupOffset, KafkaGroup},
+    theme::THEME,
+};
 use color_eyre::Result;
 use ratatui::{
     buffer::Buffer,
     crossterm::event::{KeyCode, KeyEvent},
     layout::{Constraint, Layout, Rect},
+    style::Modifier,
     symbols,
     text::{Line, Span, Text},
     widgets::{
-        Block, Borders, HighlightSpacing, List, ListItem, ListState, Padding, Paragraph,
-        StatefulWidget, Widget,
+        Block, Borders, Cell, HighlightSpacing, List, ListItem, ListState, Padding, Paragraph, Row,
+        StatefulWidget, Table, Widget,
     },
 };
-use rdkafka::consumer::{BaseConsumer, Consumer};
+use rdkafka::{
+    config::ClientConfig,
+    consumer::{BaseConsumer, Consumer},
+};
 
 pub struct GroupTab {
     pub group_list: GroupList,
+    client_config: ClientConfig,
+    /// Offsets of the selected group, loaded in the background.
+    offsets: Arc<Mutex<GroupOffsets>>,
+    /// First row of the offsets table on screen.
+    offsets_scroll: usize,
+}
+
+#[derive(Debug, Default)]
+struct GroupOffsets {
+    group: String,
+    /// `None` while loading.
+    offsets: Option<Result<Vec<GroupOffset>, String>>,
 }
 
 pub struct GroupList {
@@ -33,10 +53,68 @@ impl GroupList {
 }
 
 impl GroupTab {
-    pub fn new() -> Self {
+    pub fn new(client_config: ClientConfig) -> Self {
         let group_list = GroupList::new();
 
-        Self { group_list }
+        Self {
+            group_list,
+            client_config,
+            offsets: Arc::new(Mutex::new(GroupOffsets::default())),
+            offsets_scroll: 0,
+        }
+    }
+
+    fn selected_group(&self) -> Option<&KafkaGroup> {
+        self.group_list
+            .state
+            .selected()
+            .and_then(|index| self.group_list.items.get(index))
+    }
+
+    /// Loads the offsets unless they are already there for the selected
+    /// group.
+    fn group_selected(&mut self) {
+        let loaded = self.offsets.lock().unwrap().group.clone();
+        if self
+            .selected_group()
+            .is_some_and(|group| group.name != loaded)
+        {
+            self.load_offsets();
+        }
+    }
+
+    /// Loads the committed offsets of the selected group with a consumer in
+    /// that group, which only reads them and never joins it.
+    fn load_offsets(&mut self) {
+        self.offsets_scroll = 0;
+        let Some((group, topics)) = self
+            .selected_group()
+            .map(|group| (group.name.clone(), group.topics()))
+        else {
+            return;
+        };
+        *self.offsets.lock().unwrap() = GroupOffsets {
+            group: group.clone(),
+            offsets: None,
+        };
+
+        let config = self.client_config.clone();
+        let offsets = self.offsets.clone();
+        tokio::task::spawn_blocking(move || {
+            let result = config
+                .clone()
+                .set("group.id", &group)
+                .set("enable.auto.commit", "false")
+                .create::<BaseConsumer>()
+                .map_err(|e| e.into())
+                .and_then(|consumer| group_offsets(&consumer, &topics))
+                .map_err(|e| e.to_string());
+            let mut offsets = offsets.lock().unwrap();
+            // Another group may have been selected in the meantime.
+            if offsets.group == group {
+                offsets.offsets = Some(result);
+            }
+        });
     }
 
     pub fn render(&mut self, area: Rect, buf: &mut Buffer) {
@@ -60,8 +138,7 @@ impl GroupTab {
             .group_list
             .items
             .iter()
-            .enumerate()
-            .map(|(_, group)| ListItem::new(Text::from(format!("{}", group.name))))
+            .map(|group| ListItem::new(Text::from(group.name.clone())))
             .collect();
 
         let list = List::new(items)
@@ -79,8 +156,12 @@ impl GroupTab {
             None => return,
         };
 
-        let [group_detail, member_list] =
-            Layout::vertical([Constraint::Length(3), Constraint::Fill(3)]).areas(area);
+        let [group_detail, offset_table, member_list] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Fill(3),
+            Constraint::Fill(1),
+        ])
+        .areas(area);
         let block = Block::new()
             .title(Line::raw(format!("Group: {}", group.name)).centered())
             .borders(Borders::ALL)
@@ -100,6 +181,116 @@ impl GroupTab {
         Widget::render(paragraph, group_detail, buf);
 
         self.render_member_list(member_list, buf, group);
+        self.render_offsets(offset_table, buf);
+    }
+
+    /// A row for every partition, then the lag of each topic and of the
+    /// whole group.
+    fn render_offsets(&mut self, area: Rect, buf: &mut Buffer) {
+        let state = self.offsets.lock().unwrap();
+        let offsets = match &state.offsets {
+            Some(Ok(offsets)) => offsets,
+            Some(Err(e)) => {
+                let block = Block::new()
+                    .title(Line::raw("Offsets"))
+                    .borders(Borders::ALL);
+                Paragraph::new(Line::raw(format!("Failed to load the offsets: {}", e)))
+                    .style(THEME.error)
+                    .block(block)
+                    .render(area, buf);
+                return;
+            }
+            None => {
+                let block = Block::new()
+                    .title(Line::raw("Offsets"))
+                    .borders(Borders::ALL);
+                Paragraph::new(Line::raw("Loading offsets"))
+                    .style(THEME.tip)
+                    .block(block)
+                    .render(area, buf);
+                return;
+            }
+        };
+
+        let total_style = THEME.content.add_modifier(Modifier::BOLD);
+        let mut rows = Vec::new();
+        let mut total = 0;
+        for (i, offset) in offsets.iter().enumerate() {
+            rows.push(Row::new(vec![
+                Cell::from(offset.topic.clone()),
+                Cell::from(offset.partition.to_string()),
+                Cell::from(offset.committed.to_string()),
+                Cell::from(offset.high.to_string()),
+                Cell::from(offset.lag().to_string()),
+            ]));
+            total += offset.lag();
+            if offsets
+                .get(i + 1)
+                .is_none_or(|next| next.topic != offset.topic)
+            {
+                let topic_lag: i64 = offsets
+                    .iter()
+                    .filter(|o| o.topic == offset.topic)
+                    .map(|o| o.lag())
+                    .sum();
+                rows.push(
+                    Row::new(vec![
+                        Cell::from(format!("{} total", offset.topic)),
+                        Cell::from(""),
+                        Cell::from(""),
+                        Cell::from(""),
+                        Cell::from(topic_lag.to_string()),
+                    ])
+                    .style(THEME.tip),
+                );
+            }
+        }
+        rows.push(
+            Row::new(vec![
+                Cell::from("Group total"),
+                Cell::from(""),
+                Cell::from(""),
+                Cell::from(""),
+                Cell::from(total.to_string()),
+            ])
+            .style(total_style),
+        );
+
+        let block = Block::new()
+            .title(Line::raw(format!(
+                "Offsets of {} partitions, lag {} (PgUp/PgDn to scroll)",
+                offsets.len(),
+                total
+            )))
+            .borders(Borders::ALL);
+        // One line goes to the header.
+        let height = (block.inner(area).height as usize).saturating_sub(1);
+        self.offsets_scroll = self.offsets_scroll.min(rows.len().saturating_sub(height));
+        let rows: Vec<Row> = rows.into_iter().skip(self.offsets_scroll).collect();
+        drop(state);
+
+        let header = Row::new(vec![
+            "Topic",
+            "Partition",
+            "Committed",
+            "High watermark",
+            "Lag",
+        ])
+        .style(THEME.borders);
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Fill(1),
+                Constraint::Length(10),
+                Constraint::Length(14),
+                Constraint::Length(14),
+                Constraint::Length(12),
+            ],
+        )
+        .header(header)
+        .block(block)
+        .style(THEME.content);
+        Widget::render(table, area, buf);
     }
 
     fn render_member_list(&self, area: Rect, buf: &mut Buffer, group: &KafkaGroup) {
@@ -134,6 +325,8 @@ impl GroupTab {
             let kafka_group = KafkaGroup::from(group);
             self.group_list.items.push(kafka_group);
         }
+        drop(consumer);
+        self.load_offsets();
         Ok(())
     }
 }
@@ -149,8 +342,17 @@ impl GroupTab {
             KeyCode::Char('h') | KeyCode::Left => self.select_none(),
             KeyCode::Char('j') | KeyCode::Down => self.select_next(),
             KeyCode::Char('k') | KeyCode::Up => self.select_previous(),
-            _ => {}
+            KeyCode::PageDown => {
+                self.offsets_scroll = self.offsets_scroll.saturating_add(10);
+                return Ok(Mode::Tab);
+            }
+            KeyCode::PageUp => {
+                self.offsets_scroll = self.offsets_scroll.saturating_sub(10);
+                return Ok(Mode::Tab);
+            }
+            _ => return Ok(Mode::Tab),
         };
+        self.group_selected();
 
         Ok(Mode::Tab)
     }
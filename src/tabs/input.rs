@@ -0,0 +1,93 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    style::{Color, Modifier},
+    text::{Line, Span},
+};
+
+use crate::theme::THEME;
+
+/// A single line text input. The cursor is counted in chars, so multi-byte
+/// input never splits a code point.
+#[derive(Debug, Default, Clone)]
+pub struct Input {
+    value: String,
+    cursor: usize,
+}
+
+impl Input {
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    pub fn set_value(&mut self, value: &str) {
+        self.value = value.to_string();
+        self.cursor = self.value.chars().count();
+    }
+
+    /// Handles editing keys, returns false if the key was not consumed.
+    pub fn handle_key_press(&mut self, key: &KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Char(c) => self.enter_char(c),
+            KeyCode::Backspace => self.delete_char(),
+            KeyCode::Delete => self.delete_char_forward(),
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right => self.cursor = (self.cursor + 1).min(self.value.chars().count()),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.value.chars().count(),
+            _ => return false,
+        }
+        true
+    }
+
+    pub fn enter_char(&mut self, c: char) {
+        let index = self.byte_index();
+        self.value.insert(index, c);
+        self.cursor += 1;
+    }
+
+    fn delete_char(&mut self) {
+        if self.cursor == 0 {
+            return;
+        }
+        self.cursor -= 1;
+        let index = self.byte_index();
+        self.value.remove(index);
+    }
+
+    fn delete_char_forward(&mut self) {
+        if self.cursor < self.value.chars().count() {
+            let index = self.byte_index();
+            self.value.remove(index);
+        }
+    }
+
+    fn byte_index(&self) -> usize {
+        self.value
+            .char_indices()
+            .nth(self.cursor)
+            .map(|(i, _)| i)
+            .unwrap_or(self.value.len())
+    }
+
+    pub fn line(&self, focused: bool) -> Line<'_> {
+        if !focused {
+            return Line::from(Span::raw(self.value.as_str()).style(THEME.content));
+        }
+
+        let index = self.byte_index();
+        let (before, rest) = self.value.split_at(index);
+        let mut chars = rest.chars();
+        let spans = match chars.next() {
+            Some(c) => vec![
+                Span::raw(before).style(THEME.content),
+                Span::raw(c.to_string()).style(THEME.content.bg(Color::White)),
+                Span::raw(chars.as_str()).style(THEME.content),
+            ],
+            None => vec![
+                Span::raw(before).style(THEME.content),
+                Span::raw(" ").style(THEME.content.bg(Color::White)),
+            ],
+        };
+        Line::from(spans).style(THEME.content.add_modifier(Modifier::UNDERLINED))
+    }
+}
@@ -0,0 +1,405 @@
+use std::collections::HashSet;
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Margin, Rect},
+    symbols,
+    text::{Line, Span},
+    widgets::{
+        Block, Borders, Padding, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState,
+        StatefulWidget, Widget, Wrap,
+    },
+};
+
+use serde_json::Value;
+
+use super::topic::TopicPage;
+use crate::{
+    kafka::KafkaMessage,
+    payload::{
+        bytes_lines, bytes_summary,
+        json::{json_lines, json_paths, Fold},
+        BytesView,
+    },
+    theme::THEME,
+};
+
+/// Shows every field of a single message, the payload can be scrolled.
+/// JSON keys and payloads are pretty printed and their objects and arrays
+/// can be collapsed.
+pub struct MessageDetail {
+    message: KafkaMessage,
+    /// The page the detail view was opened from.
+    back: TopicPage,
+    view: BytesView,
+    collapsed: HashSet<String>,
+    /// Folds of the last render and the path of the selected one.
+    folds: Vec<Fold>,
+    selected_fold: Option<String>,
+    /// Scroll the selected fold into view on the next render.
+    follow_fold: bool,
+    /// Dropped whenever what the lines show changes.
+    rendered: Option<Rendered>,
+    /// Rows are counted in `usize`, a hex dump of a large payload has more
+    /// than `u16` can hold.
+    scroll: usize,
+    /// Scrollable height of the last render, used for paging and clamping.
+    max_scroll: usize,
+    page_height: usize,
+}
+
+impl MessageDetail {
+    pub fn new(message: KafkaMessage, back: TopicPage) -> Self {
+        Self {
+            message,
+            back,
+            view: BytesView::default(),
+            collapsed: HashSet::new(),
+            folds: Vec::new(),
+            selected_fold: None,
+            follow_fold: false,
+            rendered: None,
+            scroll: 0,
+            max_scroll: 0,
+            page_height: 0,
+        }
+    }
+
+    pub fn message(&self) -> &KafkaMessage {
+        &self.message
+    }
+
+    pub fn render(&mut self, area: Rect, buf: &mut Buffer) {
+        let message = &self.message;
+        let block = Block::new()
+            .title(
+                Line::raw(format!(
+                    "{} / {} / {} [{}]",
+                    message.topic, message.partition, message.offset, self.view
+                ))
+                .centered(),
+            )
+            .borders(Borders::ALL)
+            .border_set(symbols::border::ROUNDED)
+            .border_style(THEME.borders)
+            .padding(Padding::horizontal(1));
+
+        let inner = block.inner(area);
+        let height = inner.height as usize;
+        let rendered = match self.rendered.take() {
+            Some(rendered) if rendered.width == inner.width => rendered,
+            _ => {
+                let (lines, folds) = message_lines(
+                    &self.message,
+                    self.view,
+                    &self.collapsed,
+                    self.selected_fold.as_deref(),
+                );
+                self.folds = folds;
+                Rendered::new(lines, inner.width)
+            }
+        };
+
+        if self.follow_fold {
+            self.follow_fold = false;
+            let fold = self
+                .folds
+                .iter()
+                .find(|f| Some(&f.path) == self.selected_fold.as_ref());
+            if let Some(fold) = fold {
+                let row = rendered.rows[fold.line];
+                if row < self.scroll {
+                    self.scroll = row;
+                } else if row >= self.scroll + height {
+                    self.scroll = row + 1 - height;
+                }
+            }
+        }
+
+        self.page_height = height;
+        self.max_scroll = rendered.line_count().saturating_sub(height);
+        self.scroll = self.scroll.min(self.max_scroll);
+
+        // Only the lines on screen are handed to the paragraph.
+        let (first, skip) = rendered.line_at(self.scroll);
+        let end = rendered.line_at(self.scroll + height).0 + 1;
+        Paragraph::new(rendered.lines[first..end.min(rendered.lines.len())].to_vec())
+            .style(THEME.content)
+            .wrap(Wrap { trim: false })
+            .block(block)
+            .scroll((u16::try_from(skip).unwrap_or(u16::MAX), 0))
+            .render(area, buf);
+        self.rendered = Some(rendered);
+
+        let mut state = ScrollbarState::new(self.max_scroll).position(self.scroll);
+        Scrollbar::new(ScrollbarOrientation::VerticalRight).render(
+            area.inner(Margin {
+                vertical: 1,
+                horizontal: 0,
+            }),
+            buf,
+            &mut state,
+        );
+    }
+
+    pub fn handle_key_press(&mut self, key: &KeyEvent) -> TopicPage {
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('h') | KeyCode::Left => {
+                return self.back;
+            }
+            KeyCode::Char('j') | KeyCode::Down => self.scroll = self.scroll.saturating_add(1),
+            KeyCode::Char('k') | KeyCode::Up => self.scroll = self.scroll.saturating_sub(1),
+            KeyCode::PageDown | KeyCode::Char(' ') => {
+                self.scroll = self.scroll.saturating_add(self.page_height)
+            }
+            KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(self.page_height),
+            KeyCode::Char('g') | KeyCode::Home => self.scroll = 0,
+            KeyCode::Char('G') | KeyCode::End => self.scroll = self.max_scroll,
+            KeyCode::Char('v') => {
+                self.view = self.view.next();
+                self.rendered = None;
+            }
+            KeyCode::Char(']') => self.select_fold(1),
+            KeyCode::Char('[') => self.select_fold(-1),
+            KeyCode::Enter | KeyCode::Char('z') => self.toggle_fold(),
+            KeyCode::Char('-') => self.collapse_all(),
+            KeyCode::Char('+') | KeyCode::Char('=') => {
+                self.collapsed.clear();
+                self.rendered = None;
+            }
+            _ => {}
+        }
+        self.scroll = self.scroll.min(self.max_scroll);
+        TopicPage::MessageDetail
+    }
+
+    fn select_fold(&mut self, step: isize) {
+        if self.folds.is_empty() {
+            return;
+        }
+        let index = match self
+            .folds
+            .iter()
+            .position(|f| Some(&f.path) == self.selected_fold.as_ref())
+        {
+            Some(index) => (index as isize + step).rem_euclid(self.folds.len() as isize) as usize,
+            None => 0,
+        };
+        self.selected_fold = Some(self.folds[index].path.clone());
+        self.follow_fold = true;
+        self.rendered = None;
+    }
+
+    fn toggle_fold(&mut self) {
+        match self.selected_fold.clone() {
+            Some(path) => {
+                if !self.collapsed.remove(&path) {
+                    self.collapsed.insert(path);
+                }
+                self.follow_fold = true;
+                self.rendered = None;
+            }
+            None => self.select_fold(1),
+        }
+    }
+
+    /// Collapses everything below the top level of the key and the payload.
+    fn collapse_all(&mut self) {
+        let roots = [
+            ("key", self.message.decoded_key.as_ref()),
+            ("payload", self.message.decoded_payload.as_ref()),
+        ];
+        for (root, value) in roots {
+            if let Some(value) = value {
+                self.collapsed
+                    .extend(json_paths(value, root).into_iter().filter(|p| p != root));
+            }
+        }
+        self.rendered = None;
+    }
+}
+
+/// The lines of the message for a width, with the row each one starts at
+/// once wrapped.
+struct Rendered {
+    width: u16,
+    lines: Vec<Line<'static>>,
+    /// The first row of every line, then the number of rows.
+    rows: Vec<usize>,
+}
+
+impl Rendered {
+    fn new(lines: Vec<Line<'static>>, width: u16) -> Self {
+        let mut rows = Vec::with_capacity(lines.len() + 1);
+        let mut row = 0;
+        for line in &lines {
+            rows.push(row);
+            row += Paragraph::new(line.clone())
+                .wrap(Wrap { trim: false })
+                .line_count(width);
+        }
+        rows.push(row);
+        Self { width, lines, rows }
+    }
+
+    fn line_count(&self) -> usize {
+        self.rows.last().copied().unwrap_or_default()
+    }
+
+    /// The line shown on `row` and how many of its rows are above it.
+    fn line_at(&self, row: usize) -> (usize, usize) {
+        let line = self.rows[..self.lines.len()]
+            .partition_point(|start| *start <= row)
+            .saturating_sub(1);
+        (line, row.saturating_sub(self.rows[line]))
+    }
+}
+
+fn message_lines(
+    message: &KafkaMessage,
+    view: BytesView,
+    collapsed: &HashSet<String>,
+    selected: Option<&str>,
+) -> (Vec<Line<'static>>, Vec<Fold>) {
+    let field = |name: &str, value: String| {
+        Line::from(vec![
+            Span::raw(format!("{}: ", name)).style(THEME.tip),
+            Span::raw(value),
+        ])
+    };
+    let size = |bytes: &Option<Vec<u8>>| match bytes {
+        Some(bytes) => format!("{} bytes", bytes.len()),
+        None => "null".to_string(),
+    };
+
+    let mut lines = vec![
+        field("Topic", message.topic.clone()),
+        field("Partition", message.partition.to_string()),
+        field("Offset", message.offset.to_string()),
+        field("Timestamp", message.timestamp.to_string()),
+        field("Headers", message.headers.len().to_string()),
+    ];
+    if let Some(error) = &message.decode_error {
+        lines.insert(
+            4,
+            Line::from(vec![
+                Span::raw("Decode error: ").style(THEME.tip),
+                Span::raw(error.clone()).style(THEME.error),
+            ]),
+        );
+    }
+    for header in &message.headers {
+        lines.push(Line::from(vec![
+            Span::raw(format!("  {}: ", header.key)),
+            bytes_summary(Some(&header.value), None, view, usize::MAX),
+        ]));
+    }
+    let mut folds = Vec::new();
+
+    let mut push_field =
+        |lines: &mut Vec<Line<'static>>, root: &str, bytes: Option<&[u8]>, json: Option<&Value>| {
+            match (view, json) {
+                (BytesView::Text, Some(json)) => {
+                    let (json_lines, json_folds) = json_lines(json, root, collapsed, selected);
+                    let offset = lines.len();
+                    folds.extend(json_folds.into_iter().map(|fold| Fold {
+                        line: fold.line + offset,
+                        ..fold
+                    }));
+                    lines.extend(json_lines);
+                }
+                _ => lines.extend(bytes_lines(bytes, view)),
+            }
+        };
+
+    lines.push(Line::from(""));
+    lines.push(field("Key", size(&message.key)));
+    push_field(
+        &mut lines,
+        "key",
+        message.key.as_deref(),
+        message.decoded_key.as_ref(),
+    );
+
+    lines.push(Line::from(""));
+    let payload = match message.payload {
+        Some(_) => size(&message.payload),
+        None => "null (tombstone)".to_string(),
+    };
+    lines.push(field("Payload", payload));
+    push_field(
+        &mut lines,
+        "payload",
+        message.payload.as_deref(),
+        message.decoded_payload.as_ref(),
+    );
+    (lines, folds)
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::{KeyEventKind, KeyEventState, KeyModifiers};
+
+    use crate::kafka::KafkaTimestamp;
+
+    use super::*;
+
+    fn press(code: KeyCode) -> KeyEvent {
+        KeyEvent {
+            code,
+            modifiers: KeyModifiers::NONE,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }
+    }
+
+    #[test]
+    fn scrolls_past_u16_rows() {
+        let message = KafkaMessage {
+            topic: "blobs".to_string(),
+            partition: 0,
+            offset: 0,
+            timestamp: KafkaTimestamp::NotAvailable,
+            headers: Vec::new(),
+            payload: Some(vec![0xff; 1 << 20]),
+            key: None,
+            decoded_payload: None,
+            decoded_key: None,
+            decode_error: None,
+        };
+        let mut detail = MessageDetail::new(message, TopicPage::Messages);
+        let area = Rect::new(0, 0, 120, 20);
+        let mut buf = Buffer::empty(area);
+        detail.render(area, &mut buf);
+        let rows = detail.rendered.as_ref().unwrap().line_count();
+        assert!(rows > u16::MAX as usize, "{}", rows);
+        assert_eq!(detail.max_scroll, rows - 18);
+
+        detail.handle_key_press(&press(KeyCode::End));
+        detail.render(area, &mut buf);
+        assert_eq!(detail.scroll, detail.max_scroll);
+        detail.handle_key_press(&press(KeyCode::Char('j')));
+        assert_eq!(detail.scroll, detail.max_scroll);
+        detail.handle_key_press(&press(KeyCode::PageUp));
+        assert_eq!(detail.scroll, detail.max_scroll - 18);
+    }
+
+    #[test]
+    fn finds_the_line_of_a_wrapped_row() {
+        let lines = vec![
+            Line::raw("short"),
+            Line::raw("a line that wraps over three rows"),
+            Line::raw(""),
+            Line::raw("end"),
+        ];
+        let rendered = Rendered::new(lines, 12);
+        assert_eq!(rendered.rows, vec![0, 1, 4, 5, 6]);
+        assert_eq!(rendered.line_count(), 6);
+        assert_eq!(rendered.line_at(0), (0, 0));
+        assert_eq!(rendered.line_at(3), (1, 2));
+        assert_eq!(rendered.line_at(4), (2, 0));
+        assert_eq!(rendered.line_at(5), (3, 0));
+        assert_eq!(rendered.line_at(9), (3, 4));
+    }
+}
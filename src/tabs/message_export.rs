@@ -0,0 +1,273 @@
+use color_eyre::{eyre::eyre, Result};
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    symbols,
+    text::Line,
+    widgets::{Block, Borders, Clear, Paragraph, Widget, Wrap},
+};
+
+use super::{
+    input::Input,
+    topic::{center, PopupAction},
+};
+use crate::{
+    export::{Export, ExportFormat, ExportSource},
+    kafka::KafkaTopic,
+    search::SearchRange,
+    theme::THEME,
+};
+
+/// Asks what to export, in which format and where to.
+pub struct ExportForm {
+    topic: KafkaTopic,
+    /// Whether there are received messages to export.
+    has_shown: bool,
+    field: ExportField,
+    source: SourceKind,
+    from: Input,
+    to: Input,
+    format: ExportFormat,
+    path: Input,
+    error: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum ExportField {
+    #[default]
+    Source,
+    From,
+    To,
+    Format,
+    Path,
+}
+
+impl ExportField {
+    const ALL: [ExportField; 5] = [
+        ExportField::Source,
+        ExportField::From,
+        ExportField::To,
+        ExportField::Format,
+        ExportField::Path,
+    ];
+
+    fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|f| *f == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    fn prev(self) -> Self {
+        let index = Self::ALL.iter().position(|f| *f == self).unwrap_or(0);
+        Self::ALL[(index + Self::ALL.len() - 1) % Self::ALL.len()]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SourceKind {
+    Shown,
+    Offsets,
+    Time,
+}
+
+impl SourceKind {
+    fn title(self) -> &'static str {
+        match self {
+            SourceKind::Shown => "Received messages, as filtered",
+            SourceKind::Offsets => "Offset range",
+            SourceKind::Time => "Time range",
+        }
+    }
+}
+
+impl ExportForm {
+    pub fn new(topic: KafkaTopic, has_shown: bool) -> Self {
+        let format = ExportFormat::default();
+        let mut path = Input::default();
+        path.set_value(&format.default_path(&topic.name).to_string_lossy());
+        Self {
+            topic,
+            has_shown,
+            field: ExportField::default(),
+            source: if has_shown {
+                SourceKind::Shown
+            } else {
+                SourceKind::Offsets
+            },
+            from: Input::default(),
+            to: Input::default(),
+            format,
+            path,
+            error: None,
+        }
+    }
+
+    pub fn render(&self, area: Rect, buf: &mut Buffer) {
+        let area = center(area, Constraint::Length(64), Constraint::Length(19));
+        Clear.render(area, buf);
+
+        let block = Block::new()
+            .title(Line::raw(format!("Export {}", self.topic.name)).centered())
+            .borders(Borders::ALL)
+            .border_set(symbols::border::ROUNDED)
+            .border_style(THEME.borders)
+            .style(THEME.root);
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        let [source, from, to, format, path, status] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Fill(1),
+        ])
+        .areas(inner);
+
+        let render_field = |line: Line, title: &str, area: Rect, buf: &mut Buffer| {
+            let block = Block::new()
+                .title(Line::raw(title.to_string()))
+                .border_set(symbols::border::ROUNDED)
+                .border_style(THEME.borders)
+                .borders(Borders::ALL);
+            Paragraph::new(line).block(block).render(area, buf);
+        };
+        let choice = |value: &str, field: ExportField| {
+            let line = Line::raw(format!("‹ {} ›", value));
+            if field == self.field {
+                line.style(THEME.tabs_selected)
+            } else {
+                line
+            }
+        };
+
+        render_field(
+            choice(self.source.title(), ExportField::Source),
+            "Messages",
+            source,
+            buf,
+        );
+        let (from_title, to_title) = match self.source {
+            SourceKind::Shown => ("From (not used)", "To (not used)"),
+            SourceKind::Offsets => (
+                "From offset (empty: low watermark)",
+                "To offset, inclusive (empty: high watermark)",
+            ),
+            SourceKind::Time => ("From (10:42 yesterday, -1h, ...)", "To (empty: now)"),
+        };
+        render_field(
+            self.from.line(self.field == ExportField::From),
+            from_title,
+            from,
+            buf,
+        );
+        render_field(
+            self.to.line(self.field == ExportField::To),
+            to_title,
+            to,
+            buf,
+        );
+        render_field(
+            choice(&self.format.to_string(), ExportField::Format),
+            "Format",
+            format,
+            buf,
+        );
+        let path_title = match self.format {
+            ExportFormat::Raw => "Directory",
+            _ => "File",
+        };
+        render_field(
+            self.path.line(self.field == ExportField::Path),
+            path_title,
+            path,
+            buf,
+        );
+
+        let status_line = match &self.error {
+            Some(err) => Line::raw(err.as_str()).style(THEME.error),
+            None => Line::raw("Tab: next field  ←/→: choose  Enter: export  Esc: cancel")
+                .style(THEME.tip),
+        };
+        Paragraph::new(status_line)
+            .wrap(Wrap { trim: true })
+            .render(status, buf);
+    }
+
+    pub fn handle_key_press(&mut self, key: &KeyEvent) -> PopupAction {
+        match key.code {
+            KeyCode::Esc => return PopupAction::Close,
+            KeyCode::Tab | KeyCode::Down => self.field = self.field.next(),
+            KeyCode::BackTab | KeyCode::Up => self.field = self.field.prev(),
+            KeyCode::Enter => match self.export() {
+                Ok(export) => return PopupAction::Export(export),
+                Err(e) => self.error = Some(e.to_string()),
+            },
+            KeyCode::Left | KeyCode::Right | KeyCode::Char(' ')
+                if matches!(self.field, ExportField::Source | ExportField::Format) =>
+            {
+                self.error = None;
+                match self.field {
+                    ExportField::Source => self.next_source(),
+                    _ => self.next_format(),
+                }
+            }
+            _ => {
+                let input = match self.field {
+                    ExportField::From => &mut self.from,
+                    ExportField::To => &mut self.to,
+                    ExportField::Path => &mut self.path,
+                    _ => return PopupAction::None,
+                };
+                if input.handle_key_press(key) {
+                    self.error = None;
+                }
+            }
+        }
+        PopupAction::None
+    }
+
+    fn next_source(&mut self) {
+        self.source = match self.source {
+            SourceKind::Shown => SourceKind::Offsets,
+            SourceKind::Offsets => SourceKind::Time,
+            SourceKind::Time if self.has_shown => SourceKind::Shown,
+            SourceKind::Time => SourceKind::Offsets,
+        };
+        self.from.set_value("");
+        self.to.set_value("");
+    }
+
+    /// A path left at its default follows the format.
+    fn next_format(&mut self) {
+        let default = self.format.default_path(&self.topic.name);
+        self.format = self.format.next();
+        if self.path.value() == default.to_string_lossy() {
+            let path = self.format.default_path(&self.topic.name);
+            self.path.set_value(&path.to_string_lossy());
+        }
+    }
+
+    fn export(&self) -> Result<Export> {
+        let source = match self.source {
+            SourceKind::Shown => ExportSource::Shown,
+            SourceKind::Offsets => {
+                ExportSource::Range(SearchRange::offsets(self.from.value(), self.to.value())?)
+            }
+            SourceKind::Time => {
+                ExportSource::Range(SearchRange::time(self.from.value(), self.to.value())?)
+            }
+        };
+        let path = self.path.value().trim();
+        if path.is_empty() {
+            return Err(eyre!("Enter where to export to"));
+        }
+        Ok(Export {
+            topic: self.topic.clone(),
+            source,
+            format: self.format,
+            path: path.into(),
+        })
+    }
+}
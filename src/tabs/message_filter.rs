@@ -0,0 +1,243 @@
+use std::collections::VecDeque;
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    symbols,
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, Widget},
+};
+
+use super::input::Input;
+use crate::{message_buffer::MessageBuffer, query::Query, theme::THEME};
+
+/// The filter bar of the received messages. Each message is checked once,
+/// when it is first drawn, so a filter costs nothing while nothing arrives.
+#[derive(Default)]
+pub struct MessageFilter {
+    query: Option<Query>,
+    input: Input,
+    editing: bool,
+    error: Option<String>,
+    /// Sequence numbers of the matching messages before `checked`.
+    matches: VecDeque<u64>,
+    checked: u64,
+}
+
+impl MessageFilter {
+    /// Shown while there is a filter or one is being typed.
+    pub fn is_visible(&self) -> bool {
+        self.editing || self.query.is_some()
+    }
+
+    pub fn is_editing(&self) -> bool {
+        self.editing
+    }
+
+    pub fn start_editing(&mut self) {
+        self.editing = true;
+    }
+
+    /// Checks everything again, for when the messages were replaced or
+    /// decoded anew.
+    pub fn reset(&mut self) {
+        self.matches.clear();
+        self.checked = 0;
+    }
+
+    /// Checks the messages received since the last update and forgets the
+    /// ones that were dropped from the buffer.
+    pub fn update(&mut self, messages: &MessageBuffer) {
+        if messages.end() < self.checked {
+            self.reset();
+        }
+        let dropped = self.matches.partition_point(|&seq| seq < messages.start());
+        self.matches.drain(..dropped);
+        let from = self.checked.max(messages.start());
+        if let Some(query) = &self.query {
+            let new = (from..messages.end())
+                .filter(|&seq| messages.get(seq).is_some_and(|m| query.matches(m)));
+            self.matches.extend(new);
+        }
+        self.checked = messages.end();
+    }
+
+    /// Number of messages shown.
+    pub fn count(&self, messages: &MessageBuffer) -> usize {
+        match self.query {
+            Some(_) => self.matches.len(),
+            None => messages.len(),
+        }
+    }
+
+    /// Sequence number of the `row`th message shown.
+    pub fn seq(&self, row: usize, messages: &MessageBuffer) -> Option<u64> {
+        match self.query {
+            Some(_) => self.matches.get(row).copied(),
+            None => Some(messages.start() + row as u64),
+        }
+    }
+
+    /// Row of the message `seq`, or of the next one shown after it.
+    pub fn row(&self, seq: u64, messages: &MessageBuffer) -> usize {
+        match self.query {
+            Some(_) => self.matches.partition_point(|&s| s < seq),
+            None => seq.saturating_sub(messages.start()) as usize,
+        }
+    }
+
+    /// Returns true when the filter changed.
+    pub fn handle_key_press(&mut self, key: &KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Esc => {
+                self.editing = false;
+                self.error = None;
+                let source = self
+                    .query
+                    .as_ref()
+                    .map(Query::to_string)
+                    .unwrap_or_default();
+                self.input.set_value(&source);
+            }
+            KeyCode::Enter => {
+                let source = self.input.value().trim();
+                if source.is_empty() {
+                    self.query = None;
+                } else {
+                    match Query::parse(source) {
+                        Ok(query) => self.query = Some(query),
+                        Err(e) => {
+                            self.error = Some(e.to_string());
+                            return false;
+                        }
+                    }
+                }
+                self.editing = false;
+                self.error = None;
+                self.reset();
+                return true;
+            }
+            _ => {
+                if self.input.handle_key_press(key) {
+                    self.error = None;
+                }
+            }
+        }
+        false
+    }
+
+    pub fn render(&self, area: Rect, buf: &mut Buffer) {
+        let title = match &self.error {
+            Some(error) => Line::from(Span::raw(error.as_str()).style(THEME.error)),
+            None if self.editing => Line::raw(
+                "Filter: key == \"user-42\" && value.total > 100 && header.trace_id exists",
+            ),
+            None => Line::raw("Filter (/ to edit)"),
+        };
+        let block = Block::new()
+            .title(title)
+            .borders(Borders::ALL)
+            .border_set(symbols::border::ROUNDED)
+            .border_style(THEME.borders);
+        Paragraph::new(self.input.line(self.editing))
+            .block(block)
+            .render(area, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kafka::{KafkaMessage, KafkaTimestamp};
+
+    /// Even offsets match the filter.
+    fn message(offset: i64) -> KafkaMessage {
+        let key = if offset % 2 == 0 { "hit" } else { "miss" };
+        KafkaMessage {
+            topic: "orders".to_string(),
+            partition: 0,
+            offset,
+            timestamp: KafkaTimestamp::NotAvailable,
+            headers: Vec::new(),
+            payload: None,
+            key: Some(key.as_bytes().to_vec()),
+            decoded_payload: None,
+            decoded_key: None,
+            decode_error: None,
+        }
+    }
+
+    fn filter() -> MessageFilter {
+        MessageFilter {
+            query: Some(Query::parse("key == \"hit\"").unwrap()),
+            ..MessageFilter::default()
+        }
+    }
+
+    fn shown(filter: &MessageFilter, messages: &MessageBuffer) -> Vec<i64> {
+        (0..filter.count(messages))
+            .map(|row| {
+                let seq = filter.seq(row, messages).unwrap();
+                messages.get(seq).unwrap().offset
+            })
+            .collect()
+    }
+
+    #[test]
+    fn forgets_dropped_matches() {
+        let mut messages = MessageBuffer::new(4);
+        let mut filter = filter();
+        for offset in 0..4 {
+            messages.push(message(offset));
+        }
+        filter.update(&messages);
+        assert_eq!(shown(&filter, &messages), [0, 2]);
+
+        // Drops the checked offsets 0 to 2.
+        for offset in 4..7 {
+            messages.push(message(offset));
+        }
+        filter.update(&messages);
+        assert_eq!(shown(&filter, &messages), [4, 6]);
+        assert_eq!(filter.row(5, &messages), 1);
+        assert_eq!(filter.row(0, &messages), 0);
+
+        // Drops everything checked and offsets 7 and 8 before they are.
+        for offset in 7..13 {
+            messages.push(message(offset));
+        }
+        filter.update(&messages);
+        assert_eq!(shown(&filter, &messages), [10, 12]);
+    }
+
+    #[test]
+    fn starts_over_after_a_clear() {
+        let mut messages = MessageBuffer::new(4);
+        let mut filter = filter();
+        for offset in 0..3 {
+            messages.push(message(offset));
+        }
+        filter.update(&messages);
+        messages.clear();
+        filter.update(&messages);
+        assert_eq!(filter.count(&messages), 0);
+
+        messages.push(message(8));
+        messages.push(message(9));
+        filter.update(&messages);
+        assert_eq!(shown(&filter, &messages), [8]);
+    }
+
+    #[test]
+    fn shows_everything_without_a_query() {
+        let mut messages = MessageBuffer::new(2);
+        let mut filter = MessageFilter::default();
+        for offset in 0..5 {
+            messages.push(message(offset));
+        }
+        filter.update(&messages);
+        assert_eq!(shown(&filter, &messages), [3, 4]);
+        assert_eq!(filter.row(4, &messages), 1);
+    }
+}
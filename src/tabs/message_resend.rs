@@ -0,0 +1,153 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    symbols,
+    text::Line,
+    widgets::{Block, Borders, Clear, Paragraph, Widget, Wrap},
+};
+
+use super::{
+    input::Input,
+    topic::{center, PopupAction},
+};
+use crate::theme::THEME;
+
+/// Topics listed below the input, the list scrolls past them.
+const VISIBLE: usize = 8;
+
+/// Asks which topic a consumed message is sent to again, the topics
+/// containing what is typed are offered.
+pub struct ResendForm {
+    topics: Vec<String>,
+    topic: Input,
+    /// Index into the matching topics.
+    selected: Option<usize>,
+    error: Option<String>,
+}
+
+impl ResendForm {
+    pub fn new(source: &str, topics: Vec<String>) -> Self {
+        let mut topic = Input::default();
+        topic.set_value(source);
+        Self {
+            topics,
+            topic,
+            selected: None,
+            error: None,
+        }
+    }
+
+    fn matches(&self) -> Vec<&String> {
+        let typed = self.topic.value().trim();
+        self.topics
+            .iter()
+            .filter(|topic| topic.contains(typed))
+            .collect()
+    }
+
+    pub fn render(&self, area: Rect, buf: &mut Buffer) {
+        let area = center(
+            area,
+            Constraint::Length(64),
+            Constraint::Length(VISIBLE as u16 + 8),
+        );
+        Clear.render(area, buf);
+
+        let block = Block::new()
+            .title(Line::raw("Re-produce message").centered())
+            .borders(Borders::ALL)
+            .border_set(symbols::border::ROUNDED)
+            .border_style(THEME.borders)
+            .style(THEME.root);
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        let [input, list, status] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Length(VISIBLE as u16),
+            Constraint::Fill(1),
+        ])
+        .areas(inner);
+
+        let input_block = Block::new()
+            .title(Line::raw("Into topic"))
+            .border_set(symbols::border::ROUNDED)
+            .border_style(THEME.borders)
+            .borders(Borders::ALL);
+        Paragraph::new(self.topic.line(self.selected.is_none()))
+            .block(input_block)
+            .render(input, buf);
+
+        let matches = self.matches();
+        let first = self
+            .selected
+            .map_or(0, |selected| (selected + 1).saturating_sub(VISIBLE));
+        let lines: Vec<Line> = matches
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(VISIBLE)
+            .map(|(i, topic)| {
+                if Some(i) == self.selected {
+                    Line::raw(format!("> {}", topic)).style(THEME.tabs_selected)
+                } else {
+                    Line::raw(format!("  {}", topic)).style(THEME.content)
+                }
+            })
+            .collect();
+        Paragraph::new(lines).render(list, buf);
+
+        let status_line = match &self.error {
+            Some(err) => Line::raw(err.as_str()).style(THEME.error),
+            None => {
+                Line::raw("↑/↓: choose a topic  Enter: edit and send  Esc: cancel").style(THEME.tip)
+            }
+        };
+        Paragraph::new(status_line)
+            .wrap(Wrap { trim: true })
+            .render(status, buf);
+    }
+
+    pub fn handle_key_press(&mut self, key: &KeyEvent) -> PopupAction {
+        match key.code {
+            KeyCode::Esc => return PopupAction::Close,
+            KeyCode::Down => {
+                let count = self.matches().len();
+                if count > 0 {
+                    self.selected = Some(self.selected.map_or(0, |i| (i + 1).min(count - 1)));
+                }
+            }
+            KeyCode::Up => {
+                self.selected = match self.selected {
+                    Some(0) | None => None,
+                    Some(i) => Some(i - 1),
+                }
+            }
+            KeyCode::Enter => {
+                let topic = match self.selected {
+                    Some(i) => self.matches().get(i).map(|topic| topic.to_string()),
+                    None => Some(self.topic.value().trim().to_string()),
+                };
+                match topic {
+                    Some(topic) if self.topics.contains(&topic) => {
+                        return PopupAction::Resend(topic)
+                    }
+                    _ => {
+                        self.error = Some(format!(
+                            "No topic \"{}\", choose one from the list",
+                            self.topic.value().trim()
+                        ))
+                    }
+                }
+            }
+            _ => {
+                if self.topic.handle_key_press(key) {
+                    self.selected = None;
+                    self.error = None;
+                }
+            }
+        }
+        PopupAction::None
+    }
+}
@@ -0,0 +1,236 @@
+use color_eyre::{eyre::eyre, Result};
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    symbols,
+    text::Line,
+    widgets::{Block, Borders, Clear, Paragraph, Widget, Wrap},
+};
+
+use super::{
+    input::Input,
+    topic::{center, PopupAction},
+};
+use crate::{
+    kafka::{KafkaTopic, StartPosition},
+    theme::THEME,
+    timestamp::{format_timestamp, parse_timestamp},
+};
+
+/// Lets the user choose where the message viewer starts reading.
+pub struct MessageStartPicker {
+    topic: KafkaTopic,
+    kind: StartKind,
+    value: Input,
+    error: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum StartKind {
+    #[default]
+    Beginning,
+    End,
+    Offset,
+    LastN,
+    Timestamp,
+}
+
+impl StartKind {
+    const ALL: [StartKind; 5] = [
+        StartKind::Beginning,
+        StartKind::End,
+        StartKind::Offset,
+        StartKind::LastN,
+        StartKind::Timestamp,
+    ];
+
+    fn title(self) -> &'static str {
+        match self {
+            StartKind::Beginning => "From the beginning",
+            StartKind::End => "From the end (tail)",
+            StartKind::Offset => "From offset",
+            StartKind::LastN => "Last N messages per partition",
+            StartKind::Timestamp => "From timestamp",
+        }
+    }
+
+    fn hint(self) -> &'static str {
+        match self {
+            StartKind::Beginning | StartKind::End => "",
+            StartKind::Offset => "1200 for all partitions, or 0:1200, 3:50",
+            StartKind::LastN => "100",
+            StartKind::Timestamp => "10:42 yesterday, 2024-05-01 10:42, -15m, epoch ms",
+        }
+    }
+
+    fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|k| *k == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    fn prev(self) -> Self {
+        let index = Self::ALL.iter().position(|k| *k == self).unwrap_or(0);
+        Self::ALL[(index + Self::ALL.len() - 1) % Self::ALL.len()]
+    }
+}
+
+impl MessageStartPicker {
+    pub fn new(topic: KafkaTopic) -> Self {
+        Self {
+            topic,
+            kind: StartKind::default(),
+            value: Input::default(),
+            error: None,
+        }
+    }
+
+    pub fn render(&self, area: Rect, buf: &mut Buffer) {
+        let watermarks = self.topic.partitions.len().min(6) as u16;
+        let area = center(
+            area,
+            Constraint::Length(64),
+            Constraint::Length(StartKind::ALL.len() as u16 + watermarks + 8),
+        );
+        Clear.render(area, buf);
+
+        let block = Block::new()
+            .title(Line::raw(format!("Read {} from", self.topic.name)).centered())
+            .borders(Borders::ALL)
+            .border_set(symbols::border::ROUNDED)
+            .border_style(THEME.borders)
+            .style(THEME.root);
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        let [kinds, value, partitions, status] = Layout::vertical([
+            Constraint::Length(StartKind::ALL.len() as u16),
+            Constraint::Length(3),
+            Constraint::Length(watermarks),
+            Constraint::Fill(1),
+        ])
+        .areas(inner);
+
+        let lines: Vec<Line> = StartKind::ALL
+            .iter()
+            .map(|kind| {
+                if *kind == self.kind {
+                    Line::raw(format!("> {}", kind.title())).style(THEME.tabs_selected)
+                } else {
+                    Line::raw(format!("  {}", kind.title())).style(THEME.content)
+                }
+            })
+            .collect();
+        Paragraph::new(lines).render(kinds, buf);
+
+        let block = Block::new()
+            .title(Line::raw(self.kind.hint()))
+            .border_set(symbols::border::ROUNDED)
+            .border_style(THEME.borders)
+            .borders(Borders::ALL);
+        let focused = !matches!(self.kind, StartKind::Beginning | StartKind::End);
+        Paragraph::new(self.value.line(focused))
+            .block(block)
+            .render(value, buf);
+
+        let mut lines: Vec<Line> = self
+            .topic
+            .partitions
+            .iter()
+            .map(|p| {
+                Line::raw(format!(
+                    "  Partition {}: offsets {}..{}",
+                    p.id, p.low, p.high
+                ))
+                .style(THEME.content)
+            })
+            .collect();
+        if lines.len() > watermarks as usize {
+            lines.truncate(watermarks as usize - 1);
+            lines.push(Line::raw("  ...").style(THEME.content));
+        }
+        Paragraph::new(lines).render(partitions, buf);
+
+        let status_line = match (&self.error, self.kind) {
+            (Some(err), _) => Line::raw(err.as_str()).style(THEME.error),
+            (None, StartKind::Timestamp) => match parse_timestamp(self.value.value()) {
+                Ok(millis) => {
+                    Line::raw(format!("Starts at {}", format_timestamp(millis))).style(THEME.tip)
+                }
+                Err(_) => Line::raw("Enter a timestamp").style(THEME.tip),
+            },
+            (None, _) => Line::raw("↑/↓: choose  Enter: read  Esc: cancel").style(THEME.tip),
+        };
+        Paragraph::new(status_line)
+            .wrap(Wrap { trim: true })
+            .render(status, buf);
+    }
+
+    pub fn handle_key_press(&mut self, key: &KeyEvent) -> PopupAction {
+        match key.code {
+            KeyCode::Esc => return PopupAction::Close,
+            KeyCode::Down | KeyCode::Tab => self.set_kind(self.kind.next()),
+            KeyCode::Up | KeyCode::BackTab => self.set_kind(self.kind.prev()),
+            KeyCode::Enter => match self.position() {
+                Ok(position) => return PopupAction::Receive(position),
+                Err(e) => self.error = Some(e.to_string()),
+            },
+            _ => {
+                if !matches!(self.kind, StartKind::Beginning | StartKind::End) {
+                    self.value.handle_key_press(key);
+                    self.error = None;
+                }
+            }
+        }
+        PopupAction::None
+    }
+
+    fn set_kind(&mut self, kind: StartKind) {
+        self.kind = kind;
+        self.value.set_value("");
+        self.error = None;
+    }
+
+    fn position(&self) -> Result<StartPosition> {
+        let value = self.value.value().trim();
+        match self.kind {
+            StartKind::Beginning => Ok(StartPosition::Beginning),
+            StartKind::End => Ok(StartPosition::End),
+            StartKind::LastN => value
+                .parse()
+                .ok()
+                .filter(|n| *n > 0)
+                .map(StartPosition::LastN)
+                .ok_or_else(|| eyre!("Expected a positive number of messages")),
+            StartKind::Timestamp => parse_timestamp(value).map(StartPosition::Timestamp),
+            StartKind::Offset => self.parse_offsets(value).map(StartPosition::Offsets),
+        }
+    }
+
+    fn parse_offsets(&self, value: &str) -> Result<Vec<(i32, i64)>> {
+        if let Ok(offset) = value.parse::<i64>() {
+            return Ok(self
+                .topic
+                .partitions
+                .iter()
+                .map(|p| (p.id, offset))
+                .collect());
+        }
+
+        let mut offsets = Vec::new();
+        for entry in value.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (partition, offset) = entry
+                .split_once(':')
+                .and_then(|(p, o)| Some((p.trim().parse().ok()?, o.trim().parse().ok()?)))
+                .ok_or_else(|| eyre!("Invalid entry \"{}\", expected partition:offset", entry))?;
+            if !self.topic.partitions.iter().any(|p| p.id == partition) {
+                return Err(eyre!("Partition {} does not exist", partition));
+            }
+            offsets.push((partition, offset));
+        }
+        if offsets.is_empty() {
+            return Err(eyre!("Enter an offset"));
+        }
+        Ok(offsets)
+    }
+}
@@ -0,0 +1,290 @@
+use std::collections::HashSet;
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Rect},
+    text::{Line, Span},
+    widgets::{Block, Cell, HighlightSpacing, Row, StatefulWidget, Table, TableState},
+};
+use strum::{Display, EnumIter, IntoEnumIterator};
+
+use super::message_filter::MessageFilter;
+use crate::{
+    kafka::KafkaMessage,
+    message_buffer::MessageBuffer,
+    payload::{bytes_window, BytesView},
+    theme::THEME,
+    timestamp::format_timestamp,
+};
+
+/// Characters a key press scrolls the key and value columns sideways.
+const SCROLL_STEP: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumIter)]
+pub enum Column {
+    Partition,
+    Offset,
+    Timestamp,
+    Key,
+    Value,
+    Size,
+}
+
+impl Column {
+    fn width(self) -> Constraint {
+        match self {
+            Column::Partition => Constraint::Length(11),
+            Column::Offset => Constraint::Length(12),
+            Column::Timestamp => Constraint::Length(23),
+            Column::Key => Constraint::Fill(1),
+            Column::Value => Constraint::Fill(3),
+            Column::Size => Constraint::Length(10),
+        }
+    }
+}
+
+/// The received messages as a table. It follows the newest message until
+/// the user moves up, and formats only the rows on screen.
+pub struct MessageTable {
+    /// Sequence number of the selected message, so the selection stays on
+    /// it while older messages are dropped.
+    selected: Option<u64>,
+    follow: bool,
+    /// First row on screen.
+    offset: usize,
+    /// Rows on screen at the last render, a page up or down.
+    page: usize,
+    /// Characters left out at the start of keys and values.
+    scroll: usize,
+    hidden: HashSet<Column>,
+}
+
+impl Default for MessageTable {
+    fn default() -> Self {
+        Self {
+            selected: None,
+            follow: true,
+            offset: 0,
+            page: 1,
+            scroll: 0,
+            hidden: HashSet::new(),
+        }
+    }
+}
+
+impl MessageTable {
+    pub fn is_following(&self) -> bool {
+        self.follow
+    }
+
+    /// Back to following the newest message, for when the rows changed.
+    pub fn reset(&mut self) {
+        self.selected = None;
+        self.follow = true;
+        self.offset = 0;
+    }
+
+    /// Sequence number of the selected message.
+    pub fn selected(&self, messages: &MessageBuffer, filter: &MessageFilter) -> Option<u64> {
+        let row = self.row(messages, filter)?;
+        filter.seq(row, messages)
+    }
+
+    fn row(&self, messages: &MessageBuffer, filter: &MessageFilter) -> Option<usize> {
+        let last = filter.count(messages).checked_sub(1)?;
+        match self.selected {
+            _ if self.follow => Some(last),
+            Some(seq) => Some(filter.row(seq, messages).min(last)),
+            None => None,
+        }
+    }
+
+    fn select(&mut self, row: usize, messages: &MessageBuffer, filter: &MessageFilter) {
+        let Some(last) = filter.count(messages).checked_sub(1) else {
+            return;
+        };
+        self.follow = row >= last;
+        self.selected = filter.seq(row.min(last), messages);
+    }
+
+    /// Returns false for keys the table does not use.
+    pub fn handle_key_press(
+        &mut self,
+        key: &KeyEvent,
+        messages: &MessageBuffer,
+        filter: &MessageFilter,
+    ) -> bool {
+        let row = self.row(messages, filter);
+        match key.code {
+            KeyCode::Char('j') | KeyCode::Down => {
+                self.select(row.map_or(0, |row| row + 1), messages, filter)
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.select(row.unwrap_or(0).saturating_sub(1), messages, filter)
+            }
+            KeyCode::PageDown => self.select(row.unwrap_or(0) + self.page, messages, filter),
+            KeyCode::PageUp => {
+                self.select(row.unwrap_or(0).saturating_sub(self.page), messages, filter)
+            }
+            KeyCode::Char('g') | KeyCode::Home => self.select(0, messages, filter),
+            KeyCode::Char('G') | KeyCode::End => self.follow = true,
+            KeyCode::Char('h') | KeyCode::Left => {
+                self.scroll = self.scroll.saturating_sub(SCROLL_STEP)
+            }
+            KeyCode::Char('l') | KeyCode::Right => self.scroll += SCROLL_STEP,
+            KeyCode::Char(c @ '1'..='6') => {
+                let index = c as usize - '1' as usize;
+                if let Some(column) = Column::iter().nth(index) {
+                    if !self.hidden.remove(&column) {
+                        self.hidden.insert(column);
+                    }
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    pub fn render(
+        &mut self,
+        area: Rect,
+        buf: &mut Buffer,
+        block: Block,
+        messages: &MessageBuffer,
+        filter: &MessageFilter,
+        view: BytesView,
+    ) {
+        let columns: Vec<Column> = Column::iter()
+            .filter(|c| !self.hidden.contains(c))
+            .collect();
+        // One line goes to the header.
+        let height = (block.inner(area).height as usize).saturating_sub(1);
+        self.page = height.max(1);
+
+        let count = filter.count(messages);
+        let row = self.row(messages, filter);
+        self.offset = visible_offset(self.offset, row, height).min(count.saturating_sub(height));
+        let width = area.width as usize;
+
+        let rows: Vec<Row> = (self.offset..count.min(self.offset + height))
+            .filter_map(|row| filter.seq(row, messages))
+            .filter_map(|seq| messages.get(seq))
+            .map(|m| Row::new(columns.iter().map(|c| self.cell(*c, m, view, width))))
+            .collect();
+        // Numbered by the key that toggles them.
+        let header = Row::new(columns.iter().map(|c| {
+            Cell::from(Line::from(vec![
+                Span::styled(format!("{}", *c as usize + 1), THEME.tip),
+                Span::raw(format!(" {}", c)),
+            ]))
+        }))
+        .style(THEME.borders);
+
+        let table = Table::new(rows, columns.iter().map(|c| c.width()))
+            .header(header)
+            .block(block)
+            .row_highlight_style(THEME.tabs_selected)
+            .highlight_symbol(">")
+            .highlight_spacing(HighlightSpacing::Always);
+        let mut state = TableState::default().with_selected(row.map(|row| row - self.offset));
+        StatefulWidget::render(table, area, buf, &mut state);
+    }
+
+    fn cell(
+        &self,
+        column: Column,
+        message: &KafkaMessage,
+        view: BytesView,
+        width: usize,
+    ) -> Cell<'static> {
+        match column {
+            Column::Partition => Cell::from(message.partition.to_string()),
+            Column::Offset => Cell::from(message.offset.to_string()),
+            Column::Timestamp => Cell::from(
+                message
+                    .timestamp
+                    .millis()
+                    .map(format_timestamp)
+                    .unwrap_or_else(|| "-".to_string()),
+            ),
+            Column::Key => Cell::from(bytes_window(
+                message.key.as_deref(),
+                message.decoded_key.as_ref(),
+                view,
+                self.scroll,
+                width,
+            )),
+            Column::Value => Cell::from(bytes_window(
+                message.payload.as_deref(),
+                message.decoded_payload.as_ref(),
+                view,
+                self.scroll,
+                width,
+            )),
+            Column::Size => {
+                let size = message.key.as_ref().map_or(0, Vec::len)
+                    + message.payload.as_ref().map_or(0, Vec::len);
+                Cell::from(format_size(size))
+            }
+        }
+    }
+}
+
+/// Moves the first row on screen of `height` rows just far enough to keep
+/// the selected row in view.
+pub fn visible_offset(offset: usize, selected: Option<usize>, height: usize) -> usize {
+    match selected {
+        Some(selected) if selected < offset => selected,
+        Some(selected) if selected >= offset + height => selected + 1 - height.max(1),
+        _ => offset,
+    }
+}
+
+fn format_size(bytes: usize) -> String {
+    const UNITS: [&str; 3] = ["KiB", "MiB", "GiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scrolls_to_the_selection() {
+        // Inside the window of rows 10 to 14.
+        assert_eq!(visible_offset(10, Some(10), 5), 10);
+        assert_eq!(visible_offset(10, Some(14), 5), 10);
+        assert_eq!(visible_offset(10, None, 5), 10);
+        // Above, the selection becomes the first row.
+        assert_eq!(visible_offset(10, Some(3), 5), 3);
+        // Below, it becomes the last row.
+        assert_eq!(visible_offset(10, Some(15), 5), 11);
+        assert_eq!(visible_offset(10, Some(40), 5), 36);
+        // Without room the selection is the only row.
+        assert_eq!(visible_offset(10, Some(12), 0), 12);
+        assert_eq!(visible_offset(10, Some(3), 0), 3);
+        assert_eq!(visible_offset(10, None, 0), 10);
+    }
+
+    #[test]
+    fn formats_sizes() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1024), "1.0 KiB");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(1024 * 1024), "1.0 MiB");
+        assert_eq!(format_size(3 * 1024 * 1024 * 1024), "3.0 GiB");
+        // GiB is the largest unit.
+        assert_eq!(format_size(2048 * 1024 * 1024 * 1024), "2048.0 GiB");
+    }
+}
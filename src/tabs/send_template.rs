@@ -0,0 +1,154 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    symbols,
+    text::Line,
+    widgets::{Block, Borders, Clear, Paragraph, Widget},
+};
+
+use super::{input::Input, topic::center};
+use crate::{template::Template, theme::THEME};
+
+/// Templates shown at once, the list scrolls past them.
+const VISIBLE: usize = 12;
+
+pub enum TemplatePopup {
+    Pick(TemplatePicker),
+    Save(Input),
+}
+
+pub enum TemplateAction {
+    None,
+    Close,
+    Load(Template),
+    Delete(String),
+    Save(String),
+}
+
+impl TemplatePopup {
+    pub fn render(&self, area: Rect, buf: &mut Buffer, topic: &str) {
+        match self {
+            TemplatePopup::Pick(picker) => picker.render(area, buf, topic),
+            TemplatePopup::Save(name) => render_save(name, area, buf, topic),
+        }
+    }
+
+    pub fn handle_key_press(&mut self, key: &KeyEvent) -> TemplateAction {
+        match self {
+            TemplatePopup::Pick(picker) => picker.handle_key_press(key),
+            TemplatePopup::Save(name) => match key.code {
+                KeyCode::Esc => TemplateAction::Close,
+                KeyCode::Enter if !name.value().trim().is_empty() => {
+                    TemplateAction::Save(name.value().trim().to_string())
+                }
+                _ => {
+                    name.handle_key_press(key);
+                    TemplateAction::None
+                }
+            },
+        }
+    }
+}
+
+/// Lists the templates of a topic to load one into the send form.
+pub struct TemplatePicker {
+    templates: Vec<Template>,
+    selected: usize,
+}
+
+impl TemplatePicker {
+    pub fn new(templates: Vec<Template>) -> Self {
+        Self {
+            templates,
+            selected: 0,
+        }
+    }
+
+    fn render(&self, area: Rect, buf: &mut Buffer, topic: &str) {
+        let listed = self.templates.len().clamp(1, VISIBLE) as u16;
+        let area = center(area, Constraint::Length(64), Constraint::Length(listed + 4));
+        Clear.render(area, buf);
+
+        let block = Block::new()
+            .title(Line::raw(format!("Templates of {}", topic)).centered())
+            .borders(Borders::ALL)
+            .border_set(symbols::border::ROUNDED)
+            .border_style(THEME.borders)
+            .style(THEME.root);
+        let inner = block.inner(area);
+        block.render(area, buf);
+        let [list, status] =
+            Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(inner);
+
+        let first = (self.selected + 1).saturating_sub(VISIBLE);
+        let lines: Vec<Line> = if self.templates.is_empty() {
+            vec![Line::raw("No templates yet, Ctrl+W in the form saves one").style(THEME.tip)]
+        } else {
+            self.templates
+                .iter()
+                .enumerate()
+                .skip(first)
+                .take(VISIBLE)
+                .map(|(i, template)| {
+                    if i == self.selected {
+                        Line::raw(format!("> {}", template.name)).style(THEME.tabs_selected)
+                    } else {
+                        Line::raw(format!("  {}", template.name)).style(THEME.content)
+                    }
+                })
+                .collect()
+        };
+        Paragraph::new(lines).render(list, buf);
+        Paragraph::new(Line::raw("Enter: load  d: delete  Esc: cancel").style(THEME.tip))
+            .render(status, buf);
+    }
+
+    fn handle_key_press(&mut self, key: &KeyEvent) -> TemplateAction {
+        match key.code {
+            KeyCode::Esc => return TemplateAction::Close,
+            KeyCode::Char('j') | KeyCode::Down => {
+                self.selected = (self.selected + 1).min(self.templates.len().saturating_sub(1))
+            }
+            KeyCode::Char('k') | KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Enter => {
+                if let Some(template) = self.templates.get(self.selected) {
+                    return TemplateAction::Load(template.clone());
+                }
+            }
+            KeyCode::Char('d') if self.selected < self.templates.len() => {
+                let template = self.templates.remove(self.selected);
+                self.selected = self.selected.min(self.templates.len().saturating_sub(1));
+                return TemplateAction::Delete(template.name);
+            }
+            _ => {}
+        }
+        TemplateAction::None
+    }
+}
+
+fn render_save(name: &Input, area: Rect, buf: &mut Buffer, topic: &str) {
+    let area = center(area, Constraint::Length(64), Constraint::Length(6));
+    Clear.render(area, buf);
+
+    let block = Block::new()
+        .title(Line::raw(format!("Save a template of {}", topic)).centered())
+        .borders(Borders::ALL)
+        .border_set(symbols::border::ROUNDED)
+        .border_style(THEME.borders)
+        .style(THEME.root);
+    let inner = block.inner(area);
+    block.render(area, buf);
+    let [input, status] =
+        Layout::vertical([Constraint::Length(3), Constraint::Length(1)]).areas(inner);
+
+    let input_block = Block::new()
+        .title(Line::raw("Name, an existing one is replaced"))
+        .border_set(symbols::border::ROUNDED)
+        .border_style(THEME.borders)
+        .borders(Borders::ALL);
+    Paragraph::new(name.line(true))
+        .block(input_block)
+        .render(input, buf);
+    Paragraph::new(Line::raw("Enter: save  Esc: cancel").style(THEME.tip)).render(status, buf);
+}
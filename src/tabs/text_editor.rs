@@ -0,0 +1,460 @@
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::{
+    env,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+    process::Command,
+};
+
+use color_eyre::{
+    eyre::{eyre, Context},
+    Result,
+};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier},
+    text::{Line, Span},
+    widgets::{Block, Paragraph, Widget},
+};
+
+use crate::theme::THEME;
+
+/// Edits kept for undo.
+const MAX_UNDO: usize = 100;
+
+/// A multi-line text input. Like `Input` the cursor is counted in chars, so
+/// multi-byte input never splits a code point.
+#[derive(Debug, Clone)]
+pub struct TextEditor {
+    /// Never empty, an empty text is a single empty line.
+    lines: Vec<String>,
+    row: usize,
+    col: usize,
+    /// First line and column on screen.
+    scroll: (usize, usize),
+    undo: Vec<Snapshot>,
+    /// Set while typing, a run of typed chars is undone at once.
+    typing: bool,
+}
+
+#[derive(Debug, Clone)]
+struct Snapshot {
+    lines: Vec<String>,
+    row: usize,
+    col: usize,
+}
+
+impl Default for TextEditor {
+    fn default() -> Self {
+        Self {
+            lines: vec![String::new()],
+            row: 0,
+            col: 0,
+            scroll: (0, 0),
+            undo: Vec::new(),
+            typing: false,
+        }
+    }
+}
+
+impl TextEditor {
+    pub fn text(&self) -> String {
+        self.lines.join("\n")
+    }
+
+    /// Replaces the text, which can be undone, and moves to its end.
+    pub fn set_text(&mut self, text: &str) {
+        self.save();
+        self.typing = false;
+        self.lines = text
+            .replace("\r\n", "\n")
+            .split('\n')
+            .map(String::from)
+            .collect();
+        self.row = self.lines.len() - 1;
+        self.col = self.line_len();
+    }
+
+    /// Empties the editor and forgets its history.
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Inserts text at the cursor, as one edit.
+    pub fn insert_str(&mut self, text: &str) {
+        self.save();
+        self.typing = false;
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        for c in text.chars() {
+            match c {
+                '\n' => self.split_line(),
+                c => self.insert_char(c),
+            }
+        }
+    }
+
+    /// Handles editing keys, returns false if the key was not consumed.
+    pub fn handle_key_press(&mut self, key: &KeyEvent) -> bool {
+        if key.modifiers.contains(KeyModifiers::CONTROL) {
+            if key.code != KeyCode::Char('z') {
+                return false;
+            }
+            self.undo();
+            return true;
+        }
+
+        let typing = matches!(key.code, KeyCode::Char(c) if !c.is_whitespace());
+        if matches!(
+            key.code,
+            KeyCode::Char(_) | KeyCode::Enter | KeyCode::Backspace | KeyCode::Delete
+        ) && !(typing && self.typing)
+        {
+            self.save();
+        }
+        self.typing = typing;
+
+        match key.code {
+            KeyCode::Char(c) => self.insert_char(c),
+            KeyCode::Enter => self.split_line(),
+            KeyCode::Backspace => self.delete_char(),
+            KeyCode::Delete => self.delete_char_forward(),
+            KeyCode::Left => self.move_left(),
+            KeyCode::Right => self.move_right(),
+            KeyCode::Up if self.row > 0 => {
+                self.row -= 1;
+                self.col = self.col.min(self.line_len());
+            }
+            KeyCode::Down if self.row + 1 < self.lines.len() => {
+                self.row += 1;
+                self.col = self.col.min(self.line_len());
+            }
+            KeyCode::Up | KeyCode::Down => {}
+            KeyCode::Home => self.col = 0,
+            KeyCode::End => self.col = self.line_len(),
+            _ => return false,
+        }
+        true
+    }
+
+    fn save(&mut self) {
+        if self.undo.len() == MAX_UNDO {
+            self.undo.remove(0);
+        }
+        self.undo.push(Snapshot {
+            lines: self.lines.clone(),
+            row: self.row,
+            col: self.col,
+        });
+    }
+
+    fn undo(&mut self) {
+        if let Some(snapshot) = self.undo.pop() {
+            self.lines = snapshot.lines;
+            self.row = snapshot.row;
+            self.col = snapshot.col;
+        }
+        self.typing = false;
+    }
+
+    fn line_len(&self) -> usize {
+        self.lines[self.row].chars().count()
+    }
+
+    fn byte_index(&self) -> usize {
+        let line = &self.lines[self.row];
+        line.char_indices()
+            .nth(self.col)
+            .map(|(i, _)| i)
+            .unwrap_or(line.len())
+    }
+
+    fn insert_char(&mut self, c: char) {
+        let index = self.byte_index();
+        self.lines[self.row].insert(index, c);
+        self.col += 1;
+    }
+
+    fn split_line(&mut self) {
+        let index = self.byte_index();
+        let rest = self.lines[self.row].split_off(index);
+        self.lines.insert(self.row + 1, rest);
+        self.row += 1;
+        self.col = 0;
+    }
+
+    fn delete_char(&mut self) {
+        if self.col > 0 {
+            self.col -= 1;
+            let index = self.byte_index();
+            self.lines[self.row].remove(index);
+        } else if self.row > 0 {
+            let line = self.lines.remove(self.row);
+            self.row -= 1;
+            self.col = self.line_len();
+            self.lines[self.row].push_str(&line);
+        }
+    }
+
+    fn delete_char_forward(&mut self) {
+        if self.col < self.line_len() {
+            let index = self.byte_index();
+            self.lines[self.row].remove(index);
+        } else if self.row + 1 < self.lines.len() {
+            let line = self.lines.remove(self.row + 1);
+            self.lines[self.row].push_str(&line);
+        }
+    }
+
+    fn move_left(&mut self) {
+        if self.col > 0 {
+            self.col -= 1;
+        } else if self.row > 0 {
+            self.row -= 1;
+            self.col = self.line_len();
+        }
+    }
+
+    fn move_right(&mut self) {
+        if self.col < self.line_len() {
+            self.col += 1;
+        } else if self.row + 1 < self.lines.len() {
+            self.row += 1;
+            self.col = 0;
+        }
+    }
+
+    /// Scrolls just far enough to keep the cursor on screen.
+    pub fn render(&mut self, area: Rect, buf: &mut Buffer, block: Block, focused: bool) {
+        let inner = block.inner(area);
+        let (height, width) = (inner.height.max(1) as usize, inner.width.max(1) as usize);
+        let (top, left) = &mut self.scroll;
+        *top = (*top).clamp((self.row + 1).saturating_sub(height), self.row);
+        *left = (*left).clamp((self.col + 1).saturating_sub(width), self.col);
+
+        let lines: Vec<Line> = self
+            .lines
+            .iter()
+            .enumerate()
+            .skip(self.scroll.0)
+            .take(height)
+            .map(|(row, line)| {
+                let line: String = line.chars().skip(self.scroll.1).collect();
+                if !focused || row != self.row {
+                    return Line::from(Span::raw(line).style(THEME.content));
+                }
+                let col = self.col - self.scroll.1;
+                let before: String = line.chars().take(col).collect();
+                let mut rest = line.chars().skip(col);
+                let cursor = rest.next().map(String::from).unwrap_or(" ".to_string());
+                Line::from(vec![
+                    Span::raw(before).style(THEME.content),
+                    Span::raw(cursor).style(THEME.content.bg(Color::White)),
+                    Span::raw(rest.collect::<String>()).style(THEME.content),
+                ])
+                .style(THEME.content.add_modifier(Modifier::UNDERLINED))
+            })
+            .collect();
+        Paragraph::new(lines).block(block).render(area, buf);
+    }
+}
+
+/// Opens `text` in `$VISUAL` or `$EDITOR`, vi when neither is set, and
+/// returns what was saved. The terminal has to be handed over first.
+pub fn edit_externally(text: &str) -> Result<String> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or("vi".to_string());
+    // Editors are often given with arguments, as in `code --wait`.
+    let mut words = editor.split_whitespace();
+    let program = words.next().ok_or_else(|| eyre!("$EDITOR is empty"))?;
+
+    let (path, mut file) = create_temp_file().wrap_err("Failed to create a temporary file")?;
+    let written = file.write_all(text.as_bytes());
+    drop(file);
+    if let Err(e) = written {
+        let _ = fs::remove_file(&path);
+        return Err(e).wrap_err("Failed to write the temporary file");
+    }
+    let status = Command::new(program)
+        .args(words)
+        .arg(&path)
+        .status()
+        .wrap_err_with(|| format!("Failed to start {}", editor));
+    let edited = fs::read_to_string(&path).wrap_err("Failed to read the temporary file");
+    let _ = fs::remove_file(&path);
+
+    let status = status?;
+    if !status.success() {
+        return Err(eyre!("{} exited with {}", editor, status));
+    }
+    Ok(trim_added_newline(text, edited?))
+}
+
+/// Most editors end the file with a newline the text did not have, it is
+/// taken off again.
+fn trim_added_newline(text: &str, mut edited: String) -> String {
+    if !text.ends_with('\n') && edited.ends_with('\n') {
+        edited.pop();
+        if edited.ends_with('\r') {
+            edited.pop();
+        }
+    }
+    edited
+}
+
+/// Creates a file in the temporary directory that only the user can read,
+/// under a new random name so no one can have prepared it or a link there.
+fn create_temp_file() -> io::Result<(PathBuf, File)> {
+    loop {
+        let name = format!("kata-{:016x}.txt", rand::random::<u64>());
+        let path = env::temp_dir().join(name);
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+        match options.open(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(editor: &mut TextEditor, code: KeyCode) {
+        editor.handle_key_press(&KeyEvent::new(code, KeyModifiers::NONE));
+    }
+
+    fn type_str(editor: &mut TextEditor, text: &str) {
+        text.chars().for_each(|c| press(editor, KeyCode::Char(c)));
+    }
+
+    fn undo(editor: &mut TextEditor) {
+        editor.handle_key_press(&KeyEvent::new(KeyCode::Char('z'), KeyModifiers::CONTROL));
+    }
+
+    fn cursor(editor: &TextEditor) -> (usize, usize) {
+        (editor.row, editor.col)
+    }
+
+    #[test]
+    fn edits_multi_byte_chars() {
+        let mut editor = TextEditor::default();
+        type_str(&mut editor, "café🦀");
+        assert_eq!(cursor(&editor), (0, 5));
+        press(&mut editor, KeyCode::Left);
+        press(&mut editor, KeyCode::Left);
+        type_str(&mut editor, "x");
+        assert_eq!(editor.text(), "cafxé🦀");
+        assert_eq!(cursor(&editor), (0, 4));
+        press(&mut editor, KeyCode::Backspace);
+        assert_eq!(editor.text(), "café🦀");
+        press(&mut editor, KeyCode::Delete);
+        assert_eq!(editor.text(), "caf🦀");
+        press(&mut editor, KeyCode::Right);
+        assert_eq!(cursor(&editor), (0, 4));
+        press(&mut editor, KeyCode::Delete);
+        assert_eq!(editor.text(), "caf🦀");
+        press(&mut editor, KeyCode::Backspace);
+        press(&mut editor, KeyCode::Home);
+        press(&mut editor, KeyCode::Delete);
+        assert_eq!(editor.text(), "af");
+        press(&mut editor, KeyCode::End);
+        assert_eq!(cursor(&editor), (0, 2));
+    }
+
+    #[test]
+    fn splits_and_joins_lines() {
+        let mut editor = TextEditor::default();
+        type_str(&mut editor, "ab€cd");
+        press(&mut editor, KeyCode::Left);
+        press(&mut editor, KeyCode::Left);
+        press(&mut editor, KeyCode::Enter);
+        assert_eq!(editor.lines, ["ab€", "cd"]);
+        assert_eq!(cursor(&editor), (1, 0));
+        press(&mut editor, KeyCode::Backspace);
+        assert_eq!(editor.text(), "ab€cd");
+        assert_eq!(cursor(&editor), (0, 3));
+
+        press(&mut editor, KeyCode::Enter);
+        press(&mut editor, KeyCode::Left);
+        assert_eq!(cursor(&editor), (0, 3));
+        press(&mut editor, KeyCode::Right);
+        assert_eq!(cursor(&editor), (1, 0));
+        press(&mut editor, KeyCode::Up);
+        press(&mut editor, KeyCode::End);
+        press(&mut editor, KeyCode::Delete);
+        assert_eq!(editor.text(), "ab€cd");
+
+        editor.set_text("long line\nab");
+        assert_eq!(cursor(&editor), (1, 2));
+        press(&mut editor, KeyCode::Up);
+        press(&mut editor, KeyCode::End);
+        press(&mut editor, KeyCode::Down);
+        assert_eq!(cursor(&editor), (1, 2));
+    }
+
+    #[test]
+    fn inserts_any_line_ending() {
+        let mut editor = TextEditor::default();
+        editor.insert_str("a\r\nb\rc\n");
+        assert_eq!(editor.text(), "a\nb\nc\n");
+        assert_eq!(cursor(&editor), (3, 0));
+        undo(&mut editor);
+        assert_eq!(editor.text(), "");
+    }
+
+    #[test]
+    fn undoes_typed_runs_at_once() {
+        let mut editor = TextEditor::default();
+        type_str(&mut editor, "abc");
+        press(&mut editor, KeyCode::Enter);
+        type_str(&mut editor, "de");
+        press(&mut editor, KeyCode::Backspace);
+        press(&mut editor, KeyCode::Backspace);
+        type_str(&mut editor, "f g");
+
+        let mut undone = Vec::new();
+        for _ in 0..8 {
+            undo(&mut editor);
+            undone.push(editor.text());
+        }
+        assert_eq!(
+            undone,
+            ["abc\nf ", "abc\nf", "abc\n", "abc\nd", "abc\nde", "abc\n", "abc", ""]
+        );
+        undo(&mut editor);
+        assert_eq!(editor.text(), "");
+    }
+
+    #[test]
+    fn keeps_the_last_edits() {
+        let mut editor = TextEditor::default();
+        for _ in 0..MAX_UNDO + 50 {
+            press(&mut editor, KeyCode::Enter);
+        }
+        assert_eq!(editor.undo.len(), MAX_UNDO);
+        for _ in 0..MAX_UNDO + 10 {
+            undo(&mut editor);
+        }
+        assert_eq!(editor.text(), "\n".repeat(50));
+    }
+
+    #[test]
+    fn trims_the_newline_an_editor_added() {
+        let trim = |text: &str, edited: &str| trim_added_newline(text, edited.to_string());
+        assert_eq!(trim("a", "a\n"), "a");
+        assert_eq!(trim("a", "b\r\n"), "b");
+        assert_eq!(trim("a", "a\n\n"), "a\n");
+        assert_eq!(trim("a", "a"), "a");
+        assert_eq!(trim("a\n", "a\n"), "a\n");
+        assert_eq!(trim("", "\n"), "");
+    }
+}
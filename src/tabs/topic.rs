@@ -1,19 +1,46 @@
 use std::{
-    sync::{Arc, Mutex},
+    collections::HashSet,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
     time::{Duration, SystemTime},
 };
 
 use crate::{
     app::Mode,
+    codec::{DeserializerRegistry, Field},
     constant::POLL_TIMEOUT,
-    kafka::{KafkaMessage, KafkaTopic},
-    tabs::topic_send::TopicSendForm,
+    export::{export_range, Export, ExportSource, Exporter},
+    import::Import,
+    kafka::{KafkaMessage, KafkaTopic, StartPosition},
+    load::LoadTest,
+    message_buffer::MessageBuffer,
+    payload::BytesView,
+    redrive::Redrive,
+    search::{Search, SearchProgress},
+    tabs::{
+        message_detail::MessageDetail,
+        message_export::ExportForm,
+        message_filter::MessageFilter,
+        message_resend::ResendForm,
+        message_start::MessageStartPicker,
+        message_table::MessageTable,
+        topic_create::TopicCreateForm,
+        topic_delete::TopicDeleteConfirm,
+        topic_import::{ImportForm, ImportView},
+        topic_load::{LoadForm, LoadView},
+        topic_redrive::{RedriveForm, RedriveView},
+        topic_search::{SearchForm, SearchView},
+        topic_send::TopicSendForm,
+    },
+    template::TemplateStore,
     theme::THEME,
 };
 use color_eyre::{eyre::eyre, Result};
 use ratatui::{
     buffer::Buffer,
-    crossterm::event::{KeyCode, KeyEvent},
+    crossterm::event::{KeyCode, KeyEvent, KeyModifiers},
     layout::{Constraint, Flex, Layout, Rect},
     symbols,
     text::{Line, Span, Text},
@@ -23,8 +50,9 @@ use ratatui::{
     },
 };
 use rdkafka::{
-    admin::{AdminClient, AdminOptions, NewTopic},
+    admin::AdminClient,
     client::DefaultClientContext,
+    config::ClientConfig,
     consumer::{BaseConsumer, Consumer},
     producer::FutureProducer,
 };
@@ -35,24 +63,71 @@ pub struct TopicTab {
     pub topic_page: TopicPage,
 
     send_form: TopicSendForm,
-    messages: Arc<Mutex<Vec<KafkaMessage>>>,
+    messages: Arc<Mutex<MessageBuffer>>,
+    message_table: MessageTable,
+    filter: MessageFilter,
+    /// Set once the messages were decoded again after a format change, the
+    /// filter has to check them anew.
+    redecoded: Arc<AtomicBool>,
+    bytes_view: BytesView,
+    message_detail: Option<MessageDetail>,
+    /// The topic and partitions the consumer is assigned to, `messages`
+    /// belong to it.
+    recv_topic: Option<KafkaTopic>,
+    recv_position: StartPosition,
+    popup: Option<TopicPopup>,
 
-    err: Arc<Mutex<Option<String>>>,
-    err_time: Arc<Mutex<Option<SystemTime>>>,
+    notice: Arc<Mutex<Option<Notice>>>,
+    notice_time: Arc<Mutex<Option<SystemTime>>>,
 
     receive_handle: Option<JoinHandle<()>>,
+    deserializers: Arc<DeserializerRegistry>,
+    /// Configuration for the consumers of background jobs.
+    client_config: ClientConfig,
+    search: Option<SearchView>,
+    import: Option<ImportView>,
+    load: Option<LoadView>,
+    redrive: Option<RedriveView>,
+    /// A range export running in the background.
+    export: Option<RangeExport>,
+    templates: TemplateStore,
+}
+
+/// Stops its task when dropped.
+struct RangeExport {
+    path: String,
+    progress: Arc<Mutex<SearchProgress>>,
+    handle: JoinHandle<()>,
+}
+
+impl Drop for RangeExport {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
 }
 
 pub struct TopicList {
     pub items: Vec<KafkaTopic>,
     pub state: ListState,
+    /// Names of the topics marked for a bulk action.
+    pub marked: HashSet<String>,
 }
 
 impl TopicList {
     fn new() -> Self {
         let items = Vec::new();
         let state = ListState::default();
-        Self { items, state }
+        Self {
+            items,
+            state,
+            marked: HashSet::new(),
+        }
+    }
+
+    fn selected(&self) -> Option<&KafkaTopic> {
+        self.state
+            .selected()
+            .and_then(|index| self.items.get(index))
     }
 }
 
@@ -63,39 +138,125 @@ pub enum TopicPage {
     Info,
     Messages,
     MessagesRecv,
+    MessageDetail,
+    Search,
+    Import,
+    Load,
+    Redrive,
     Send,
     SendEdit,
 }
 
+/// A modal dialog drawn over the topic tab, it takes all key presses while open.
+pub enum TopicPopup {
+    Create(TopicCreateForm),
+    Delete(TopicDeleteConfirm),
+    Start(MessageStartPicker),
+    Search(SearchForm),
+    Export(ExportForm),
+    Import(ImportForm),
+    Load(LoadForm),
+    Resend(ResendForm),
+    Redrive(RedriveForm),
+}
+
+/// What the topic tab should do after a popup handled a key press.
+pub enum PopupAction {
+    None,
+    Close,
+    /// Close the popup, show the message and refresh the metadata.
+    Refresh(String),
+    /// Close the popup and start receiving messages of the selected topic.
+    Receive(StartPosition),
+    /// Close the popup and start scanning the topic.
+    Search(Search),
+    /// Close the popup and write the messages out.
+    Export(Export),
+    /// Close the popup and replay the file.
+    Import(Import),
+    /// Close the popup and start the load test.
+    Load(LoadTest),
+    /// Close the popup and open the send form of the topic with the message
+    /// of the detail view.
+    Resend(String),
+    /// Close the popup and start moving the messages.
+    Redrive(Redrive),
+}
+
 impl TopicTab {
-    pub fn new() -> Self {
+    pub fn new(
+        deserializers: Arc<DeserializerRegistry>,
+        client_config: ClientConfig,
+        max_messages: usize,
+        templates: TemplateStore,
+    ) -> Self {
         let topic_list = TopicList::new();
         let topic_page = TopicPage::default();
-        let send_form = TopicSendForm::new("");
+        let send_form = TopicSendForm::new("", deserializers.clone(), templates.clone());
         Self {
             topic_list,
             topic_page,
             send_form,
-            messages: Arc::new(Mutex::new(Vec::new())),
+            messages: Arc::new(Mutex::new(MessageBuffer::new(max_messages))),
+            message_table: MessageTable::default(),
+            filter: MessageFilter::default(),
+            redecoded: Arc::new(AtomicBool::new(false)),
+            bytes_view: BytesView::default(),
+            message_detail: None,
+            recv_topic: None,
+            recv_position: StartPosition::Beginning,
+            popup: None,
 
-            err: Arc::new(Mutex::new(None)),
-            err_time: Arc::new(Mutex::new(None)),
+            notice: Arc::new(Mutex::new(None)),
+            notice_time: Arc::new(Mutex::new(None)),
             receive_handle: None,
+            deserializers,
+            client_config,
+            search: None,
+            import: None,
+            load: None,
+            redrive: None,
+            export: None,
+            templates,
         }
     }
 
     pub fn set_error(&mut self, error: String) {
-        let err = self.err.clone();
-        let err_time = self.err_time.clone();
+        self.show_notice(Notice::Error(error));
+    }
+
+    pub fn set_info(&mut self, info: String) {
+        self.show_notice(Notice::Info(info));
+    }
+
+    fn show_notice(&mut self, notice: Notice) {
+        let notice_arc = self.notice.clone();
+        let notice_time = self.notice_time.clone();
         tokio::spawn(async move {
-            set_error(error, err, err_time);
+            show_notice(notice, notice_arc, notice_time);
         });
     }
 
     pub fn set_topic_page(&mut self, page: TopicPage) {
-        if self.topic_page != TopicPage::MessagesRecv && self.receive_handle.is_some() {
-            let handle = self.receive_handle.take().unwrap();
-            handle.abort();
+        // The detail view is opened from the received messages, keep receiving
+        // in the background so nothing is missed when going back.
+        if !matches!(page, TopicPage::MessagesRecv | TopicPage::MessageDetail) {
+            if let Some(handle) = self.receive_handle.take() {
+                handle.abort();
+            }
+        }
+        // Dropping the search view stops its task.
+        if !matches!(page, TopicPage::Search | TopicPage::MessageDetail) {
+            self.search = None;
+        }
+        if page != TopicPage::Import {
+            self.import = None;
+        }
+        if page != TopicPage::Load {
+            self.load = None;
+        }
+        if page != TopicPage::Redrive {
+            self.redrive = None;
         }
         self.topic_page = page;
     }
@@ -107,6 +268,19 @@ impl TopicTab {
         self.render_left_bar(topic_list, buf);
 
         self.render_selected_item(topic_detail, buf);
+
+        match &self.popup {
+            Some(TopicPopup::Create(form)) => form.render(area, buf),
+            Some(TopicPopup::Delete(confirm)) => confirm.render(area, buf),
+            Some(TopicPopup::Start(picker)) => picker.render(area, buf),
+            Some(TopicPopup::Search(form)) => form.render(area, buf),
+            Some(TopicPopup::Export(form)) => form.render(area, buf),
+            Some(TopicPopup::Import(form)) => form.render(area, buf),
+            Some(TopicPopup::Load(form)) => form.render(area, buf),
+            Some(TopicPopup::Resend(form)) => form.render(area, buf),
+            Some(TopicPopup::Redrive(form)) => form.render(area, buf),
+            None => {}
+        }
     }
 
     fn render_left_bar(&mut self, area: Rect, buf: &mut Buffer) {
@@ -121,8 +295,13 @@ impl TopicTab {
             .topic_list
             .items
             .iter()
-            .enumerate()
-            .map(|(_, topic)| ListItem::new(Text::from(topic.name.clone())))
+            .map(|topic| {
+                if self.topic_list.marked.contains(&topic.name) {
+                    ListItem::new(Text::from(format!("* {}", topic.name))).style(THEME.error)
+                } else {
+                    ListItem::new(Text::from(topic.name.clone()))
+                }
+            })
             .collect();
 
         let list = List::new(items)
@@ -135,15 +314,35 @@ impl TopicTab {
     }
 
     fn render_selected_item(&mut self, area: Rect, buf: &mut Buffer) {
-        let topic = match self.topic_list.state.selected() {
-            Some(index) => &self.topic_list.items[index],
+        let topic = match self.topic_list.selected() {
+            Some(topic) => topic.clone(),
             None => return,
         };
 
         match self.topic_page {
-            TopicPage::Normal | TopicPage::Info => self.render_topic_info(area, buf, topic),
-            TopicPage::Messages => self.render_topic_messages(area, buf, topic),
-            TopicPage::MessagesRecv => self.render_topic_messages_recv(area, buf, topic),
+            TopicPage::Normal | TopicPage::Info => self.render_topic_info(area, buf, &topic),
+            TopicPage::Messages => self.render_topic_messages(area, buf, &topic),
+            TopicPage::MessagesRecv => self.render_topic_messages_recv(area, buf, &topic),
+            TopicPage::MessageDetail => match &mut self.message_detail {
+                Some(detail) => detail.render(area, buf),
+                None => self.render_topic_messages_recv(area, buf, &topic),
+            },
+            TopicPage::Search => match &mut self.search {
+                Some(search) => search.render(area, buf),
+                None => self.render_topic_info(area, buf, &topic),
+            },
+            TopicPage::Import => match &mut self.import {
+                Some(import) => import.render(area, buf),
+                None => self.render_topic_info(area, buf, &topic),
+            },
+            TopicPage::Load => match &mut self.load {
+                Some(load) => load.render(area, buf),
+                None => self.render_topic_info(area, buf, &topic),
+            },
+            TopicPage::Redrive => match &mut self.redrive {
+                Some(redrive) => redrive.render(area, buf),
+                None => self.render_topic_info(area, buf, &topic),
+            },
             TopicPage::Send | TopicPage::SendEdit => self.render_topic_send(area, buf),
         }
     }
@@ -159,8 +358,7 @@ impl TopicTab {
         let items: Vec<ListItem> = topic
             .partitions
             .iter()
-            .enumerate()
-            .map(|(_, p)| {
+            .map(|p| {
                 let content = Text::from(vec![
                     Line::from(Span::raw(format!(
                         "Partition: {}    Leader: {}",
@@ -191,26 +389,65 @@ impl TopicTab {
         Widget::render(list, area, buf);
     }
 
-    fn render_topic_messages_recv(&self, area: Rect, buf: &mut Buffer, topic: &KafkaTopic) {
-        let block = messages_block(topic);
-
+    fn render_topic_messages_recv(&mut self, area: Rect, buf: &mut Buffer, topic: &KafkaTopic) {
         let messages = self.messages.lock().unwrap();
+        if self.redecoded.swap(false, Ordering::Relaxed) {
+            self.filter.reset();
+        }
+        self.filter.update(&messages);
+        let area = if self.filter.is_visible() {
+            let [bar, list] =
+                Layout::vertical([Constraint::Length(3), Constraint::Fill(1)]).areas(area);
+            self.filter.render(bar, buf);
+            list
+        } else {
+            area
+        };
+        let mut count = match self.filter.count(&messages) {
+            count if count == messages.len() => format!("{} messages", count),
+            count => format!("{} of {} messages", count, messages.len()),
+        };
+        if messages.dropped() > 0 {
+            count.push_str(&format!(", {} dropped", messages.dropped()));
+        }
+        if messages.is_paused() {
+            count.push_str(", paused");
+        } else if self.message_table.is_following() {
+            count.push_str(", following");
+        }
+
+        let topic = self.recv_topic.as_ref().unwrap_or(topic);
+        let partitions: Vec<String> = topic.partitions.iter().map(|p| p.id.to_string()).collect();
+        let (key_format, payload_format) = self.deserializers.formats(&topic.name);
+        let block = Block::new()
+            .title(
+                Line::raw(format!(
+                    "Reading {} partitions [{}] from {} ({}) [{}] [key: {}, payload: {}]",
+                    topic.name,
+                    partitions.join(", "),
+                    self.recv_position,
+                    count,
+                    self.bytes_view,
+                    key_format,
+                    payload_format
+                ))
+                .centered(),
+            )
+            .borders(Borders::ALL)
+            .border_set(symbols::border::ROUNDED)
+            .border_style(THEME.borders)
+            .padding(Padding::horizontal(1));
+
         if !messages.is_empty() {
-            let items: Vec<ListItem> = messages
-                .iter()
-                .map(|m| {
-                    ListItem::new(Text::from(format!("{}: {} {}", m.offset, m.key, m.payload)))
-                })
-                .collect();
-            let list = List::new(items).block(block);
-            Widget::render(list, area, buf);
+            self.message_table
+                .render(area, buf, block, &messages, &self.filter, self.bytes_view);
             drop(messages);
             return;
         }
 
         drop(messages);
 
-        let text = Text::from(vec![Line::raw("No messages or recv failed")]).style(THEME.tip);
+        let text = Text::from(vec![Line::raw("Waiting for messages")]).style(THEME.tip);
 
         let center_area = center(
             area,
@@ -223,7 +460,8 @@ impl TopicTab {
 
     fn render_topic_messages(&self, area: Rect, buf: &mut Buffer, topic: &KafkaTopic) {
         let block = messages_block(topic);
-        let text = Text::from(vec![Line::raw("Enter to recv messages")]).style(THEME.tip);
+        let text =
+            Text::from(vec![Line::raw("Enter to recv messages, e to export")]).style(THEME.tip);
 
         let center_area = center(
             area,
@@ -244,27 +482,135 @@ impl TopicTab {
         self.send_form.render(area, buf);
     }
 
-    pub fn bottom_bar_spans(&self) -> Vec<Span> {
-        let err = self.err.lock().unwrap();
-        if let Some(err) = &*err {
-            return vec![Span::raw(err.clone()).style(THEME.error)];
+    pub fn send_payload(&self) -> String {
+        self.send_form.payload()
+    }
+
+    pub fn set_send_payload(&mut self, payload: &str) {
+        self.send_form.set_payload(payload);
+    }
+
+    pub fn handle_paste(&mut self, text: &str) {
+        if self.topic_page == TopicPage::SendEdit && self.popup.is_none() {
+            self.send_form.paste(text);
         }
-        drop(err);
+    }
 
-        let keys = [
-            ("K/↑", "Up"),
-            ("J/↓", "Down"),
-            ("Q/Esc", "Quit"),
-            ("g/G", "First/Last"),
-        ];
+    pub fn bottom_bar_spans(&self) -> Vec<Span<'_>> {
+        let notice = self.notice.lock().unwrap();
+        match &*notice {
+            Some(Notice::Error(err)) => return vec![Span::raw(err.clone()).style(THEME.error)],
+            Some(Notice::Info(info)) => return vec![Span::raw(info.clone()).style(THEME.info)],
+            None => {}
+        }
+        drop(notice);
 
-        keys.iter()
-            .flat_map(|(key, desc)| {
-                let key = Span::styled(format!(" {key} "), THEME.key_binding.key);
-                let desc = Span::styled(format!(" {desc} "), THEME.key_binding.description);
-                [key, desc]
-            })
-            .collect()
+        let mut spans = Vec::new();
+        if let Some(export) = self.running_export() {
+            let scanned = export.progress.lock().unwrap().scanned;
+            let status = format!("Exporting to {}: {} messages ", export.path, scanned);
+            spans.push(Span::raw(status).style(THEME.info));
+            spans.push(Span::styled(" Esc ", THEME.key_binding.key));
+            spans.push(Span::styled(
+                " Cancel export ",
+                THEME.key_binding.description,
+            ));
+        }
+
+        let keys: &[(&str, &str)] = match self.topic_page {
+            TopicPage::MessagesRecv if self.filter.is_editing() => {
+                &[("Enter", "Apply"), ("Esc", "Cancel")]
+            }
+            TopicPage::MessagesRecv => &[
+                ("K/↑", "Up"),
+                ("J/↓", "Down"),
+                ("PgUp/PgDn", "Page"),
+                ("H/←", "Left"),
+                ("L/→", "Right"),
+                ("Q/Esc", "Back"),
+                ("g/G", "First/Follow"),
+                ("1-6", "Columns"),
+                ("Enter", "Detail"),
+                ("s", "Start position"),
+                ("p", "Pause"),
+                ("e", "Export"),
+                ("v", "View"),
+                ("/", "Filter"),
+                ("f/F", "Payload/Key format"),
+            ],
+            TopicPage::Search => &[
+                ("K/↑", "Up"),
+                ("J/↓", "Down"),
+                ("Q/Esc", "Back"),
+                ("g/G", "First/Last"),
+                ("Enter", "Detail"),
+                ("x", "Stop"),
+            ],
+            TopicPage::SendEdit => &[
+                ("Tab", "Next field"),
+                ("Esc", "Back"),
+                ("Ctrl+S", "Send"),
+                ("Ctrl+K/Ctrl+T", "Null key/value"),
+                ("Ctrl+O/Ctrl+W", "Load/Save template"),
+                ("Ctrl+E", "$EDITOR"),
+                ("Ctrl+Z", "Undo"),
+            ],
+            TopicPage::Import => &[
+                ("K/↑", "Up"),
+                ("J/↓", "Down"),
+                ("Q/Esc", "Back"),
+                ("Enter", "Send dry run"),
+                ("x", "Stop"),
+            ],
+            TopicPage::Load => &[
+                ("K/↑", "Up"),
+                ("J/↓", "Down"),
+                ("Q/Esc", "Back"),
+                ("x", "Stop"),
+            ],
+            TopicPage::Redrive => &[
+                ("K/↑", "Up"),
+                ("J/↓", "Down"),
+                ("Q/Esc", "Back"),
+                ("p", "Pause/Resume"),
+                ("x", "Cancel"),
+            ],
+            TopicPage::MessageDetail => &[
+                ("K/↑", "Up"),
+                ("J/↓", "Down"),
+                ("PgUp/PgDn", "Page"),
+                ("Q/Esc", "Back"),
+                ("v", "View"),
+                ("[/]", "Select fold"),
+                ("z", "Fold"),
+                ("-/+", "Fold all"),
+                ("R", "Re-produce"),
+            ],
+            _ => &[
+                ("K/↑", "Up"),
+                ("J/↓", "Down"),
+                ("Q/Esc", "Quit"),
+                ("g/G", "First/Last"),
+                ("n", "New"),
+                ("Space", "Mark"),
+                ("d", "Delete"),
+                ("S", "Search"),
+                ("I", "Import"),
+                ("L", "Load test"),
+                ("R", "Redrive"),
+            ],
+        };
+
+        spans.extend(keys.iter().flat_map(|(key, desc)| {
+            let key = Span::styled(format!(" {key} "), THEME.key_binding.key);
+            let desc = Span::styled(format!(" {desc} "), THEME.key_binding.description);
+            [key, desc]
+        }));
+        spans
+    }
+
+    fn running_export(&self) -> Option<&RangeExport> {
+        self.export.as_ref().filter(|e| !e.handle.is_finished())
     }
 }
 
@@ -291,6 +637,16 @@ impl TopicTab {
                     }
                     self.topic_list.items.push(kafka_topic);
                 }
+
+                let items = &self.topic_list.items;
+                self.topic_list
+                    .marked
+                    .retain(|name| items.iter().any(|topic| &topic.name == name));
+                if let Some(index) = self.topic_list.state.selected() {
+                    if index >= items.len() {
+                        self.topic_list.state.select(items.len().checked_sub(1));
+                    }
+                }
             }
             Err(e) => {
                 self.set_error(e.to_string());
@@ -299,13 +655,6 @@ impl TopicTab {
         };
         drop(consumer);
     }
-
-    pub async fn create_topic(&mut self, admin: &AdminClient<DefaultClientContext>) {
-        let topic = NewTopic::new("test", 1, rdkafka::admin::TopicReplication::Fixed(1));
-        if let Err(e) = admin.create_topics(&[topic], &AdminOptions::new()).await {
-            self.set_error(e.to_string());
-        }
-    }
 }
 
 impl TopicTab {
@@ -315,9 +664,29 @@ impl TopicTab {
         consumer: Arc<Mutex<BaseConsumer>>,
         producer: &FutureProducer,
         admin: &AdminClient<DefaultClientContext>,
+        broker_count: usize,
     ) -> Result<Mode> {
+        if let Some(popup) = &mut self.popup {
+            let action = match popup {
+                TopicPopup::Create(form) => form.handle_key_press(key, admin).await,
+                TopicPopup::Delete(confirm) => confirm.handle_key_press(key, admin).await,
+                TopicPopup::Start(picker) => picker.handle_key_press(key),
+                TopicPopup::Search(form) => form.handle_key_press(key),
+                TopicPopup::Export(form) => form.handle_key_press(key),
+                TopicPopup::Import(form) => form.handle_key_press(key),
+                TopicPopup::Load(form) => form.handle_key_press(key),
+                TopicPopup::Resend(form) => form.handle_key_press(key),
+                TopicPopup::Redrive(form) => form.handle_key_press(key),
+            };
+            return Ok(self.handle_popup_action(action, consumer, producer));
+        }
+
         if self.topic_page == TopicPage::SendEdit {
-            let page = match self.send_form.handle_key_press(key, producer).await {
+            // The app hands the terminal over to the editor.
+            if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('e') {
+                return Ok(Mode::Editor);
+            }
+            let page = match self.send_form.handle_key_press(key, producer, admin).await {
                 Ok(page) => page,
                 Err(e) => {
                     self.set_error(e.to_string());
@@ -329,7 +698,86 @@ impl TopicTab {
             return Ok(Mode::Tab);
         }
 
+        if self.topic_page == TopicPage::MessageDetail {
+            if key.code == KeyCode::Char('R') {
+                self.open_resend();
+                return Ok(Mode::Tab);
+            }
+            let page = match &mut self.message_detail {
+                Some(detail) => detail.handle_key_press(key),
+                None => TopicPage::MessagesRecv,
+            };
+            self.set_topic_page(page);
+            return Ok(Mode::Tab);
+        }
+
+        if self.topic_page == TopicPage::Search {
+            let page = match &mut self.search {
+                Some(search) => search.handle_key_press(key),
+                None => TopicPage::Normal,
+            };
+            match page {
+                TopicPage::MessageDetail => {
+                    if let Some(message) = self.search.as_ref().and_then(|s| s.selected_message()) {
+                        self.message_detail = Some(MessageDetail::new(message, TopicPage::Search));
+                        self.set_topic_page(TopicPage::MessageDetail);
+                    }
+                }
+                page => self.set_topic_page(page),
+            }
+            return Ok(Mode::Tab);
+        }
+
+        if self.topic_page == TopicPage::Import {
+            let page = match &mut self.import {
+                Some(import) => import.handle_key_press(key),
+                None => TopicPage::Normal,
+            };
+            self.set_topic_page(page);
+            return Ok(Mode::Tab);
+        }
+
+        if self.topic_page == TopicPage::Load {
+            let page = match &mut self.load {
+                Some(load) => load.handle_key_press(key),
+                None => TopicPage::Normal,
+            };
+            self.set_topic_page(page);
+            return Ok(Mode::Tab);
+        }
+
+        if self.topic_page == TopicPage::Redrive {
+            let page = match &mut self.redrive {
+                Some(redrive) => redrive.handle_key_press(key),
+                None => TopicPage::Normal,
+            };
+            self.set_topic_page(page);
+            return Ok(Mode::Tab);
+        }
+
+        if self.topic_page == TopicPage::MessagesRecv && self.filter.is_editing() {
+            if self.filter.handle_key_press(key) {
+                self.message_table.reset();
+            }
+            return Ok(Mode::Tab);
+        }
+
+        if self.topic_page == TopicPage::MessagesRecv {
+            let messages = self.messages.lock().unwrap();
+            if self
+                .message_table
+                .handle_key_press(key, &messages, &self.filter)
+            {
+                return Ok(Mode::Tab);
+            }
+        }
+
         match key.code {
+            // Dropping the export stops its task.
+            KeyCode::Esc if self.running_export().is_some() => {
+                self.export = None;
+                self.set_info("Export cancelled".to_string());
+            }
             KeyCode::Esc | KeyCode::Char('q') => match self.topic_page {
                 TopicPage::Normal => return Ok(Mode::TabChoose),
                 _ => self.set_topic_page(TopicPage::Normal),
@@ -343,31 +791,49 @@ impl TopicTab {
             KeyCode::Char('k') | KeyCode::Up => self.select_previous(),
             KeyCode::Char('l') | KeyCode::Right => self.topic_detail(),
             KeyCode::Char('n') => {
-                self.create_topic(admin).await;
+                self.popup = Some(TopicPopup::Create(TopicCreateForm::new(broker_count)));
+            }
+            KeyCode::Char('i') if self.topic_page == TopicPage::Send => {
+                self.set_topic_page(TopicPage::SendEdit)
+            }
+            KeyCode::Char(' ') if self.topic_page == TopicPage::Normal => self.toggle_mark(),
+            KeyCode::Char('d') if self.topic_page == TopicPage::Normal => self.delete_topics(),
+            KeyCode::Char('s') if self.topic_page == TopicPage::MessagesRecv => {
+                self.pick_start_position()
+            }
+            KeyCode::Char('p') if self.topic_page == TopicPage::MessagesRecv => {
+                let mut messages = self.messages.lock().unwrap();
+                let paused = messages.is_paused();
+                messages.set_paused(!paused);
+            }
+            KeyCode::Char('v') if self.topic_page == TopicPage::MessagesRecv => {
+                self.bytes_view = self.bytes_view.next()
+            }
+            KeyCode::Char('S') if self.topic_page == TopicPage::Normal => self.open_search(),
+            KeyCode::Char('I') if self.topic_page == TopicPage::Normal => self.open_import(),
+            KeyCode::Char('L') if self.topic_page == TopicPage::Normal => self.open_load(),
+            KeyCode::Char('R') if self.topic_page == TopicPage::Normal => self.open_redrive(),
+            KeyCode::Char('e')
+                if matches!(
+                    self.topic_page,
+                    TopicPage::Messages | TopicPage::MessagesRecv
+                ) =>
+            {
+                self.open_export()
+            }
+            KeyCode::Char('/') if self.topic_page == TopicPage::MessagesRecv => {
+                self.filter.start_editing()
+            }
+            KeyCode::Char('f') if self.topic_page == TopicPage::MessagesRecv => {
+                self.cycle_format(Field::Payload)
+            }
+            KeyCode::Char('F') if self.topic_page == TopicPage::MessagesRecv => {
+                self.cycle_format(Field::Key)
             }
-            KeyCode::Char('i') => match self.topic_page {
-                TopicPage::Send => self.set_topic_page(TopicPage::SendEdit),
-                _ => {}
-            },
-            // KeyCode::Char('d') => self.delete_topic(producer),
             KeyCode::Enter => match self.topic_page {
                 TopicPage::Send => self.set_topic_page(TopicPage::SendEdit),
-                TopicPage::Messages => {
-                    let messages = Arc::clone(&self.messages);
-                    let consumer = Arc::clone(&consumer);
-                    let err = self.err.clone();
-                    self.receive_handle = Some(tokio::spawn(async move {
-                        if let Err(e) = recv_messages(messages, consumer).await {
-                            let mut err = err.lock().unwrap();
-                            *err = Some(e.to_string());
-                        } else {
-                            let mut err = err.lock().unwrap();
-                            *err = Some("Recv messages finished".to_string());
-                        }
-                    }));
-                    self.set_topic_page(TopicPage::MessagesRecv);
-                    return Ok(Mode::Tab);
-                }
+                TopicPage::Messages => self.pick_start_position(),
+                TopicPage::MessagesRecv => self.open_message_detail(),
                 _ => self.topic_detail(),
             },
             _ => {}
@@ -376,6 +842,328 @@ impl TopicTab {
         Ok(Mode::Tab)
     }
 
+    fn pick_start_position(&mut self) {
+        if let Some(topic) = self.topic_list.selected() {
+            self.popup = Some(TopicPopup::Start(MessageStartPicker::new(topic.clone())));
+        }
+    }
+
+    fn open_search(&mut self) {
+        if let Some(topic) = self.topic_list.selected() {
+            self.popup = Some(TopicPopup::Search(SearchForm::new(topic.clone())));
+        }
+    }
+
+    /// Received messages are exported from the topic being read, ranges from
+    /// the selected topic.
+    fn open_export(&mut self) {
+        let has_shown = self.topic_page == TopicPage::MessagesRecv;
+        let topic = match &self.recv_topic {
+            Some(topic) if has_shown => topic,
+            _ => match self.topic_list.selected() {
+                Some(topic) => topic,
+                None => return,
+            },
+        };
+        self.popup = Some(TopicPopup::Export(ExportForm::new(
+            topic.clone(),
+            has_shown,
+        )));
+    }
+
+    fn export(&mut self, export: Export) {
+        let path = export.path.display().to_string();
+        let exporter = match Exporter::create(export.format, &export.path) {
+            Ok(exporter) => exporter,
+            Err(e) => return self.set_error(format!("{:#}", e)),
+        };
+        match export.source {
+            ExportSource::Shown => {
+                let result = self.export_shown(exporter);
+                match result {
+                    Ok(count) => self.set_info(format!("Exported {} messages to {}", count, path)),
+                    Err(e) => self.set_error(format!("Export failed: {:#}", e)),
+                }
+            }
+            ExportSource::Range(range) => {
+                self.set_info(format!("Exporting {} to {}", export.topic.name, path));
+                let config = self.client_config.clone();
+                let notice = self.notice.clone();
+                let notice_time = self.notice_time.clone();
+                let progress = Arc::new(Mutex::new(SearchProgress::default()));
+                let scan = progress.clone();
+                let done = path.clone();
+                let handle = tokio::spawn(async move {
+                    let result = export_range(&config, &export.topic, &range, exporter, scan);
+                    let message = match result.await {
+                        Ok(count) => {
+                            Notice::Info(format!("Exported {} messages to {}", count, done))
+                        }
+                        Err(e) => Notice::Error(format!("Export failed: {:#}", e)),
+                    };
+                    show_notice(message, notice, notice_time);
+                });
+                // A new export replaces and stops the one before.
+                self.export = Some(RangeExport {
+                    path,
+                    progress,
+                    handle,
+                });
+            }
+        }
+    }
+
+    fn export_shown(&self, mut exporter: Exporter) -> Result<usize> {
+        let messages = self.messages.lock().unwrap();
+        for row in 0..self.filter.count(&messages) {
+            let message = self
+                .filter
+                .seq(row, &messages)
+                .and_then(|seq| messages.get(seq));
+            if let Some(message) = message {
+                exporter.write(message)?;
+            }
+        }
+        exporter.finish()
+    }
+
+    fn open_import(&mut self) {
+        if let Some(topic) = self.topic_list.selected() {
+            self.popup = Some(TopicPopup::Import(ImportForm::new(&topic.name)));
+        }
+    }
+
+    fn open_redrive(&mut self) {
+        if let Some(topic) = self.topic_list.selected() {
+            let topics = self
+                .topic_list
+                .items
+                .iter()
+                .map(|t| t.name.clone())
+                .collect();
+            self.popup = Some(TopicPopup::Redrive(RedriveForm::new(topic.clone(), topics)));
+        }
+    }
+
+    /// Offers the send form when it is filled in for the topic, and the
+    /// saved templates of the topic.
+    fn open_load(&mut self) {
+        let Some(topic) = self.topic_list.selected().map(|t| t.name.clone()) else {
+            return;
+        };
+        let mut templates = Vec::new();
+        if self.send_form.get_topic() == topic {
+            if let Ok(template) = self.send_form.template("Send form".to_string()) {
+                if template.payload.as_deref() != Some("") {
+                    templates.push(template);
+                }
+            }
+        }
+        match self.templates.load(&topic) {
+            Ok(saved) => templates.extend(saved),
+            Err(e) => self.set_error(format!("{:#}", e)),
+        }
+        self.popup = Some(TopicPopup::Load(LoadForm::new(&topic, templates)));
+    }
+
+    fn open_resend(&mut self) {
+        if let Some(detail) = &self.message_detail {
+            let topics = self
+                .topic_list
+                .items
+                .iter()
+                .map(|t| t.name.clone())
+                .collect();
+            self.popup = Some(TopicPopup::Resend(ResendForm::new(
+                &detail.message().topic,
+                topics,
+            )));
+        }
+    }
+
+    /// Selects the topic and fills its send form with the message of the
+    /// detail view, to be edited before sending.
+    fn resend(&mut self, topic: String) {
+        let Some(message) = self.message_detail.as_ref().map(|d| d.message().clone()) else {
+            return;
+        };
+        let Some(index) = self.topic_list.items.iter().position(|t| t.name == topic) else {
+            self.set_error(format!("No topic {}", topic));
+            return;
+        };
+        self.send_form.set_topic(&topic);
+        if let Err(e) = self.send_form.load_message(&message) {
+            self.set_error(format!("Failed to re-produce: {}", e));
+            return;
+        }
+        self.topic_list.state.select(Some(index));
+        self.set_topic_page(TopicPage::SendEdit);
+    }
+
+    fn open_message_detail(&mut self) {
+        let messages = self.messages.lock().unwrap();
+        let message = self
+            .message_table
+            .selected(&messages, &self.filter)
+            .and_then(|seq| messages.get(seq).cloned());
+        drop(messages);
+
+        if let Some(message) = message {
+            self.message_detail = Some(MessageDetail::new(message, TopicPage::MessagesRecv));
+            self.set_topic_page(TopicPage::MessageDetail);
+        }
+    }
+
+    /// Switches the key or payload format of the topic being read and decodes
+    /// the received messages again.
+    fn cycle_format(&mut self, field: Field) {
+        let topic = match &self.recv_topic {
+            Some(topic) => topic.name.clone(),
+            None => return,
+        };
+        let (key, payload) = self.deserializers.formats(&topic);
+        let format = match field {
+            Field::Key => key.next(),
+            Field::Payload => payload.next(),
+        };
+        self.deserializers.set_format(&topic, field, format);
+
+        // The format is switched even if saving it fails.
+        let deserializers = self.deserializers.clone();
+        let notice = self.notice.clone();
+        let notice_time = self.notice_time.clone();
+        tokio::spawn(async move {
+            if let Err(e) = deserializers.save_formats().await {
+                show_notice(Notice::Error(format!("{:#}", e)), notice, notice_time);
+            }
+        });
+
+        let messages = Arc::clone(&self.messages);
+        let deserializers = self.deserializers.clone();
+        let redecoded = self.redecoded.clone();
+        tokio::spawn(async move {
+            decode_messages(messages, deserializers).await;
+            redecoded.store(true, Ordering::Relaxed);
+        });
+    }
+
+    /// Assigns the consumer to the selected topic at `position` and streams
+    /// its messages into `messages`.
+    fn start_receiving(&mut self, consumer: Arc<Mutex<BaseConsumer>>, position: StartPosition) {
+        let mut topic = match self.topic_list.selected() {
+            Some(topic) => topic.clone(),
+            None => return,
+        };
+        topic.partitions = position.partitions(&topic).into_iter().cloned().collect();
+
+        if let Some(handle) = self.receive_handle.take() {
+            handle.abort();
+        }
+        self.messages.lock().unwrap().clear();
+        self.message_table.reset();
+        self.filter.reset();
+        self.recv_topic = Some(topic.clone());
+        self.recv_position = position.clone();
+
+        let messages = Arc::clone(&self.messages);
+        let notice = self.notice.clone();
+        let notice_time = self.notice_time.clone();
+        let deserializers = self.deserializers.clone();
+        self.receive_handle = Some(tokio::spawn(async move {
+            if let Err(e) =
+                recv_messages(messages, consumer, deserializers, &topic, &position).await
+            {
+                show_notice(Notice::Error(e.to_string()), notice, notice_time);
+            }
+        }));
+        self.set_topic_page(TopicPage::MessagesRecv);
+    }
+
+    fn handle_popup_action(
+        &mut self,
+        action: PopupAction,
+        consumer: Arc<Mutex<BaseConsumer>>,
+        producer: &FutureProducer,
+    ) -> Mode {
+        match action {
+            PopupAction::None => {}
+            PopupAction::Close => self.popup = None,
+            PopupAction::Receive(position) => {
+                self.popup = None;
+                self.start_receiving(consumer, position);
+            }
+            PopupAction::Search(search) => {
+                self.popup = None;
+                self.search = Some(SearchView::start(
+                    search,
+                    self.client_config.clone(),
+                    self.deserializers.clone(),
+                ));
+                self.set_topic_page(TopicPage::Search);
+            }
+            PopupAction::Export(export) => {
+                self.popup = None;
+                self.export(export);
+            }
+            PopupAction::Import(import) => {
+                self.popup = None;
+                self.import = Some(ImportView::new(import, producer.clone()));
+                self.set_topic_page(TopicPage::Import);
+            }
+            PopupAction::Load(load) => {
+                self.popup = None;
+                self.load = Some(LoadView::start(
+                    load,
+                    self.deserializers.clone(),
+                    producer.clone(),
+                ));
+                self.set_topic_page(TopicPage::Load);
+            }
+            PopupAction::Redrive(redrive) => {
+                self.popup = None;
+                self.redrive = Some(RedriveView::start(
+                    redrive,
+                    self.client_config.clone(),
+                    self.deserializers.clone(),
+                    producer.clone(),
+                ));
+                self.set_topic_page(TopicPage::Redrive);
+            }
+            PopupAction::Resend(topic) => {
+                self.popup = None;
+                self.resend(topic);
+            }
+            PopupAction::Refresh(message) => {
+                self.popup = None;
+                self.set_info(message);
+                return Mode::Refresh;
+            }
+        }
+        Mode::Tab
+    }
+
+    fn toggle_mark(&mut self) {
+        if let Some(name) = self.topic_list.selected().map(|t| t.name.clone()) {
+            if !self.topic_list.marked.remove(&name) {
+                self.topic_list.marked.insert(name);
+            }
+        }
+    }
+
+    /// Opens the delete confirmation for the marked topics, or the selected
+    /// one if nothing is marked.
+    fn delete_topics(&mut self) {
+        let mut topics: Vec<String> = self.topic_list.marked.iter().cloned().collect();
+        if topics.is_empty() {
+            match self.topic_list.selected() {
+                Some(topic) => topics.push(topic.name.clone()),
+                None => return,
+            }
+        }
+        topics.sort();
+        self.popup = Some(TopicPopup::Delete(TopicDeleteConfirm::new(topics)));
+    }
+
     fn select_none(&mut self) {
         match self.topic_page {
             TopicPage::Normal => self.topic_list.state.select(None),
@@ -386,9 +1174,16 @@ impl TopicTab {
     fn select_next(&mut self) {
         match self.topic_page {
             TopicPage::Normal => self.topic_list.state.select_next(),
+            // Moved by the message table.
+            TopicPage::MessagesRecv => {}
 
             TopicPage::Info => self.set_topic_page(TopicPage::Messages),
-            TopicPage::Messages | TopicPage::MessagesRecv => self.set_topic_page(TopicPage::Send),
+            TopicPage::Messages
+            | TopicPage::MessageDetail
+            | TopicPage::Search
+            | TopicPage::Import
+            | TopicPage::Load
+            | TopicPage::Redrive => self.set_topic_page(TopicPage::Send),
             TopicPage::Send | TopicPage::SendEdit => self.set_topic_page(TopicPage::Info),
         }
     }
@@ -396,16 +1191,22 @@ impl TopicTab {
     fn select_previous(&mut self) {
         match self.topic_page {
             TopicPage::Normal => self.topic_list.state.select_previous(),
+            TopicPage::MessagesRecv => {}
 
-            TopicPage::Info => self.topic_page = TopicPage::Send,
-            TopicPage::Messages | TopicPage::MessagesRecv => self.set_topic_page(TopicPage::Info),
+            TopicPage::Info => self.set_topic_page(TopicPage::Send),
+            TopicPage::Messages
+            | TopicPage::MessageDetail
+            | TopicPage::Search
+            | TopicPage::Import
+            | TopicPage::Load
+            | TopicPage::Redrive => self.set_topic_page(TopicPage::Info),
             TopicPage::Send | TopicPage::SendEdit => self.set_topic_page(TopicPage::Messages),
         }
     }
 
     fn topic_detail(&mut self) {
         if self.topic_list.state.selected().is_some() {
-            self.topic_page = TopicPage::Info;
+            self.set_topic_page(TopicPage::Info);
         }
     }
 
@@ -418,7 +1219,7 @@ impl TopicTab {
     }
 }
 
-fn center(area: Rect, horizontal: Constraint, vertical: Constraint) -> Rect {
+pub fn center(area: Rect, horizontal: Constraint, vertical: Constraint) -> Rect {
     let [area] = Layout::horizontal([horizontal])
         .flex(Flex::Center)
         .areas(area);
@@ -427,28 +1228,84 @@ fn center(area: Rect, horizontal: Constraint, vertical: Constraint) -> Rect {
 }
 
 pub async fn recv_messages(
-    messages: Arc<Mutex<Vec<KafkaMessage>>>,
+    messages: Arc<Mutex<MessageBuffer>>,
     consumer: Arc<Mutex<BaseConsumer>>,
+    deserializers: Arc<DeserializerRegistry>,
+    topic: &KafkaTopic,
+    position: &StartPosition,
 ) -> Result<()> {
-    loop {
+    {
         let consumer = consumer.lock().unwrap();
-        match consumer.poll(POLL_TIMEOUT) {
-            Some(Ok(message)) => {
-                let mut messages = messages.lock().unwrap();
-                messages.push(KafkaMessage::from(message));
-                drop(messages);
-            }
-            Some(Err(e)) => {
-                return Err(eyre!(e));
+        let assignment = position.assignment(&consumer, topic)?;
+        consumer.assign(&assignment)?;
+    }
+
+    let mut paused = false;
+    loop {
+        // Paused partitions are not fetched, so nothing piles up in the
+        // client while the viewer is paused.
+        let pause = messages.lock().unwrap().is_paused();
+        if pause != paused {
+            let consumer = consumer.lock().unwrap();
+            let assignment = consumer.assignment()?;
+            if pause {
+                consumer.pause(&assignment)?;
+            } else {
+                consumer.resume(&assignment)?;
             }
-            None => {
-                continue;
+            paused = pause;
+        }
+        if paused {
+            tokio::time::sleep(POLL_TIMEOUT).await;
+            continue;
+        }
+
+        // The poll blocks the thread with the consumer locked, so it is kept
+        // short and the lock is let go before decoding. Other users of the
+        // consumer, such as the metadata refresh, get their turn in between.
+        let message = {
+            let consumer = consumer.lock().unwrap();
+            consumer
+                .poll(POLL_TIMEOUT)
+                .map(|message| message.map(KafkaMessage::from))
+        };
+        match message {
+            Some(Ok(mut message)) => {
+                deserializers.decode_message(&mut message).await;
+                messages.lock().unwrap().push(message);
             }
+            Some(Err(e)) => return Err(eyre!(e)),
+            None => {}
         }
+        tokio::task::yield_now().await;
     }
 }
 
-fn messages_block(topic: &KafkaTopic) -> Block {
+/// Decodes the received messages again, one at a time so the receive task
+/// is not held up.
+async fn decode_messages(
+    messages: Arc<Mutex<MessageBuffer>>,
+    deserializers: Arc<DeserializerRegistry>,
+) {
+    let (start, end) = {
+        let messages = messages.lock().unwrap();
+        (messages.start(), messages.end())
+    };
+    for seq in start..end {
+        // Sequence numbers are not reused, messages dropped or cleared in
+        // the meantime are just not found.
+        let message = messages.lock().unwrap().get(seq).cloned();
+        let Some(mut message) = message else {
+            continue;
+        };
+        deserializers.decode_message(&mut message).await;
+        if let Some(m) = messages.lock().unwrap().get_mut(seq) {
+            *m = message;
+        }
+    }
+}
+
+fn messages_block(topic: &KafkaTopic) -> Block<'_> {
     Block::new()
         .title(Line::raw(format!("Messages for {}", topic.name)).centered())
         .borders(Borders::ALL)
@@ -457,24 +1314,31 @@ fn messages_block(topic: &KafkaTopic) -> Block {
         .padding(Padding::horizontal(1))
 }
 
-fn set_error(
-    error: String,
-    err_arc: Arc<Mutex<Option<String>>>,
-    err_time_arc: Arc<Mutex<Option<SystemTime>>>,
+/// Shown in place of the key bindings for a few seconds.
+#[derive(Debug, Clone)]
+enum Notice {
+    Error(String),
+    Info(String),
+}
+
+fn show_notice(
+    notice: Notice,
+    notice_arc: Arc<Mutex<Option<Notice>>>,
+    notice_time_arc: Arc<Mutex<Option<SystemTime>>>,
 ) {
-    let mut err = err_arc.lock().unwrap();
-    *err = Some(error.clone());
-    let mut err_time = err_time_arc.lock().unwrap();
-    *err_time = Some(SystemTime::now());
-    drop(err);
-    drop(err_time);
+    let mut current = notice_arc.lock().unwrap();
+    *current = Some(notice);
+    let mut notice_time = notice_time_arc.lock().unwrap();
+    *notice_time = Some(SystemTime::now());
+    drop(current);
+    drop(notice_time);
 
     std::thread::sleep(Duration::from_secs(5));
-    let time = err_time_arc.lock().unwrap();
+    let time = notice_time_arc.lock().unwrap();
     if let Some(t) = *time {
         if t.elapsed().unwrap() >= Duration::from_secs(5) {
-            let mut err = err_arc.lock().unwrap();
-            *err = None;
+            let mut current = notice_arc.lock().unwrap();
+            *current = None;
         }
     }
 }
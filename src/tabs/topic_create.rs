@@ -0,0 +1,247 @@
+use color_eyre::{eyre::eyre, Result};
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    symbols,
+    text::Line,
+    widgets::{Block, Borders, Clear, Paragraph, Widget, Wrap},
+};
+use rdkafka::{
+    admin::{AdminClient, AdminOptions, NewTopic, TopicReplication},
+    client::DefaultClientContext,
+};
+
+use super::{
+    input::Input,
+    topic::{center, PopupAction},
+};
+use crate::{constant::ADMIN_TIMEOUT, theme::THEME};
+
+pub struct TopicCreateForm {
+    field: CreateField,
+    name: Input,
+    partitions: Input,
+    replication: Input,
+    configs: Input,
+
+    broker_count: usize,
+    error: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum CreateField {
+    #[default]
+    Name,
+    Partitions,
+    Replication,
+    Configs,
+}
+
+impl CreateField {
+    fn next(self) -> Self {
+        match self {
+            CreateField::Name => CreateField::Partitions,
+            CreateField::Partitions => CreateField::Replication,
+            CreateField::Replication => CreateField::Configs,
+            CreateField::Configs => CreateField::Name,
+        }
+    }
+
+    fn prev(self) -> Self {
+        match self {
+            CreateField::Name => CreateField::Configs,
+            CreateField::Partitions => CreateField::Name,
+            CreateField::Replication => CreateField::Partitions,
+            CreateField::Configs => CreateField::Replication,
+        }
+    }
+}
+
+impl TopicCreateForm {
+    pub fn new(broker_count: usize) -> Self {
+        let mut partitions = Input::default();
+        partitions.set_value("1");
+        let mut replication = Input::default();
+        replication.set_value(&broker_count.clamp(1, 3).to_string());
+
+        Self {
+            field: CreateField::default(),
+            name: Input::default(),
+            partitions,
+            replication,
+            configs: Input::default(),
+            broker_count,
+            error: None,
+        }
+    }
+
+    pub fn render(&self, area: Rect, buf: &mut Buffer) {
+        let area = center(area, Constraint::Length(64), Constraint::Length(16));
+        Clear.render(area, buf);
+
+        let block = Block::new()
+            .title(Line::raw("Create Topic").centered())
+            .borders(Borders::ALL)
+            .border_set(symbols::border::ROUNDED)
+            .border_style(THEME.borders)
+            .style(THEME.root);
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        let [name, partitions, replication, configs, status] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Fill(1),
+        ])
+        .areas(inner);
+
+        let mut render_input = |input: &Input, title: &str, area: Rect, field: CreateField| {
+            let block = Block::new()
+                .title(Line::raw(title))
+                .border_set(symbols::border::ROUNDED)
+                .border_style(THEME.borders)
+                .borders(Borders::ALL);
+            Paragraph::new(input.line(field == self.field))
+                .block(block)
+                .render(area, buf);
+        };
+
+        render_input(&self.name, "Name", name, CreateField::Name);
+        render_input(
+            &self.partitions,
+            "Partitions",
+            partitions,
+            CreateField::Partitions,
+        );
+        render_input(
+            &self.replication,
+            &format!("Replication factor (brokers: {})", self.broker_count),
+            replication,
+            CreateField::Replication,
+        );
+        render_input(
+            &self.configs,
+            "Configs (cleanup.policy=compact, retention.ms=...)",
+            configs,
+            CreateField::Configs,
+        );
+
+        let status_line = match &self.error {
+            Some(err) => Line::raw(err.as_str()).style(THEME.error),
+            None => Line::raw("Tab: next field  Enter: create  Esc: cancel").style(THEME.tip),
+        };
+        Paragraph::new(status_line)
+            .wrap(Wrap { trim: true })
+            .render(status, buf);
+    }
+
+    pub async fn handle_key_press(
+        &mut self,
+        key: &KeyEvent,
+        admin: &AdminClient<DefaultClientContext>,
+    ) -> PopupAction {
+        match key.code {
+            KeyCode::Esc => return PopupAction::Close,
+            KeyCode::Tab | KeyCode::Down => self.field = self.field.next(),
+            KeyCode::BackTab | KeyCode::Up => self.field = self.field.prev(),
+            KeyCode::Enter => match self.submit(admin).await {
+                Ok(name) => return PopupAction::Refresh(format!("Topic {} created", name)),
+                Err(e) => self.error = Some(e.to_string()),
+            },
+            KeyCode::Char(c)
+                if !c.is_ascii_digit()
+                    && matches!(
+                        self.field,
+                        CreateField::Partitions | CreateField::Replication
+                    ) => {}
+            _ => {
+                let input = match self.field {
+                    CreateField::Name => &mut self.name,
+                    CreateField::Partitions => &mut self.partitions,
+                    CreateField::Replication => &mut self.replication,
+                    CreateField::Configs => &mut self.configs,
+                };
+                input.handle_key_press(key);
+            }
+        }
+        PopupAction::None
+    }
+
+    async fn submit(&mut self, admin: &AdminClient<DefaultClientContext>) -> Result<String> {
+        let name = self.name.value().trim();
+        validate_topic_name(name)?;
+
+        let partitions: i32 = self
+            .partitions
+            .value()
+            .parse()
+            .ok()
+            .filter(|p| *p > 0)
+            .ok_or_else(|| eyre!("Partitions must be a positive number"))?;
+        let replication: i32 = self
+            .replication
+            .value()
+            .parse()
+            .ok()
+            .filter(|r| *r > 0)
+            .ok_or_else(|| eyre!("Replication factor must be a positive number"))?;
+        if self.broker_count > 0 && replication as usize > self.broker_count {
+            return Err(eyre!(
+                "Replication factor {} exceeds broker count {}",
+                replication,
+                self.broker_count
+            ));
+        }
+        let configs = parse_configs(self.configs.value())?;
+
+        let mut topic = NewTopic::new(name, partitions, TopicReplication::Fixed(replication));
+        for (key, value) in &configs {
+            topic = topic.set(key, value);
+        }
+
+        let options = AdminOptions::new().operation_timeout(Some(ADMIN_TIMEOUT));
+        for result in admin.create_topics(&[topic], &options).await? {
+            if let Err((topic, code)) = result {
+                return Err(eyre!("Failed to create {}: {}", topic, code));
+            }
+        }
+        Ok(name.to_string())
+    }
+}
+
+fn validate_topic_name(name: &str) -> Result<()> {
+    if name.is_empty() {
+        return Err(eyre!("Topic name is empty"));
+    }
+    if name == "." || name == ".." {
+        return Err(eyre!("Topic name cannot be \".\" or \"..\""));
+    }
+    if name.len() > 249 {
+        return Err(eyre!("Topic name is longer than 249 characters"));
+    }
+    if let Some(c) = name
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-')))
+    {
+        return Err(eyre!("Topic name contains illegal character '{}'", c));
+    }
+    Ok(())
+}
+
+fn parse_configs(input: &str) -> Result<Vec<(&str, &str)>> {
+    input
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| match entry.split_once('=') {
+            Some((key, value)) if !key.trim().is_empty() => Ok((key.trim(), value.trim())),
+            _ => Err(eyre!(
+                "Invalid config entry \"{}\", expected key=value",
+                entry
+            )),
+        })
+        .collect()
+}
@@ -0,0 +1,154 @@
+use color_eyre::{eyre::eyre, Result};
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    symbols,
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Clear, Paragraph, Widget, Wrap},
+};
+use rdkafka::{
+    admin::{AdminClient, AdminOptions},
+    client::DefaultClientContext,
+};
+
+use super::{
+    input::Input,
+    topic::{center, PopupAction},
+};
+use crate::{constant::ADMIN_TIMEOUT, theme::THEME};
+
+/// Asks the user to type a confirmation phrase before deleting topics.
+pub struct TopicDeleteConfirm {
+    topics: Vec<String>,
+    confirmation: Input,
+    error: Option<String>,
+}
+
+impl TopicDeleteConfirm {
+    pub fn new(topics: Vec<String>) -> Self {
+        Self {
+            topics,
+            confirmation: Input::default(),
+            error: None,
+        }
+    }
+
+    /// A single topic is confirmed by its name, several by the phrase
+    /// `delete <count> topics`.
+    fn expected(&self) -> String {
+        match self.topics.as_slice() {
+            [topic] => topic.clone(),
+            topics => format!("delete {} topics", topics.len()),
+        }
+    }
+
+    pub fn render(&self, area: Rect, buf: &mut Buffer) {
+        let listed = self.topics.len().min(8) as u16;
+        let area = center(area, Constraint::Length(64), Constraint::Length(listed + 9));
+        Clear.render(area, buf);
+
+        let block = Block::new()
+            .title(Line::raw("Delete Topics").centered())
+            .borders(Borders::ALL)
+            .border_set(symbols::border::ROUNDED)
+            .border_style(THEME.error)
+            .style(THEME.root);
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        let [topics, prompt, input, status] = Layout::vertical([
+            Constraint::Length(listed),
+            Constraint::Length(2),
+            Constraint::Length(3),
+            Constraint::Fill(1),
+        ])
+        .areas(inner);
+
+        let mut lines: Vec<Line> = self
+            .topics
+            .iter()
+            .take(listed as usize)
+            .map(|topic| Line::raw(format!("  {}", topic)).style(THEME.content))
+            .collect();
+        if self.topics.len() > listed as usize {
+            let more = self.topics.len() - listed as usize + 1;
+            lines.pop();
+            lines.push(Line::raw(format!("  ... and {} more", more)).style(THEME.content));
+        }
+        Paragraph::new(lines).render(topics, buf);
+
+        Paragraph::new(Text::from(vec![
+            Line::from(""),
+            Line::from(vec![
+                Span::raw("Type ").style(THEME.tip),
+                Span::raw(self.expected()).style(THEME.error),
+                Span::raw(" to confirm").style(THEME.tip),
+            ]),
+        ]))
+        .render(prompt, buf);
+
+        let block = Block::new()
+            .border_set(symbols::border::ROUNDED)
+            .border_style(THEME.borders)
+            .borders(Borders::ALL);
+        Paragraph::new(self.confirmation.line(true))
+            .block(block)
+            .render(input, buf);
+
+        let status_line = match &self.error {
+            Some(err) => Line::raw(err.as_str()).style(THEME.error),
+            None => Line::raw("Enter: delete  Esc: cancel").style(THEME.tip),
+        };
+        Paragraph::new(status_line)
+            .wrap(Wrap { trim: true })
+            .render(status, buf);
+    }
+
+    pub async fn handle_key_press(
+        &mut self,
+        key: &KeyEvent,
+        admin: &AdminClient<DefaultClientContext>,
+    ) -> PopupAction {
+        match key.code {
+            KeyCode::Esc => return PopupAction::Close,
+            KeyCode::Enter => match self.submit(admin).await {
+                Ok(()) => {
+                    return PopupAction::Refresh(format!("Deleted {}", self.topics.join(", ")))
+                }
+                Err(e) => self.error = Some(e.to_string()),
+            },
+            _ => {
+                self.confirmation.handle_key_press(key);
+            }
+        }
+        PopupAction::None
+    }
+
+    async fn submit(&mut self, admin: &AdminClient<DefaultClientContext>) -> Result<()> {
+        if self.confirmation.value() != self.expected() {
+            return Err(eyre!("Confirmation does not match"));
+        }
+
+        let topics: Vec<&str> = self.topics.iter().map(String::as_str).collect();
+        let options = AdminOptions::new().operation_timeout(Some(ADMIN_TIMEOUT));
+        let results = admin.delete_topics(&topics, &options).await?;
+
+        let failed: Vec<_> = results
+            .into_iter()
+            .filter_map(|result| result.err())
+            .collect();
+        if !failed.is_empty() {
+            // Topics that were deleted are gone either way, only keep the
+            // failed ones around for a retry.
+            self.topics = failed.iter().map(|(topic, _)| topic.clone()).collect();
+            self.confirmation.set_value("");
+            let reasons: Vec<String> = failed
+                .iter()
+                .map(|(topic, code)| format!("{}: {}", topic, code))
+                .collect();
+            return Err(eyre!("Failed to delete {}", reasons.join(", ")));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,488 @@
+use std::sync::{Arc, Mutex};
+
+use color_eyre::{eyre::eyre, Result};
+use crossterm::event::{KeyCode, KeyEvent};
+use futures::FutureExt;
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    symbols,
+    text::{Line, Span},
+    widgets::{
+        Block, Borders, Clear, HighlightSpacing, LineGauge, List, ListItem, ListState, Padding,
+        Paragraph, StatefulWidget, Widget, Wrap,
+    },
+};
+use rdkafka::producer::FutureProducer;
+use tokio::task::JoinHandle;
+
+use super::{
+    input::Input,
+    message_table::visible_offset,
+    topic::{center, PopupAction, TopicPage},
+};
+use crate::{
+    import::{read_messages, run_import, Import, ImportProgress, MAX_FAILURES},
+    kafka::KafkaMessage,
+    payload::{bytes_summary, BytesView},
+    theme::THEME,
+    timestamp::format_timestamp,
+};
+
+/// Messages listed by a dry run.
+const MAX_PREVIEW: usize = 1_000;
+
+/// Asks for the export to replay and how to send it.
+pub struct ImportForm {
+    field: ImportField,
+    path: Input,
+    topic: Input,
+    keep_partitions: bool,
+    keep_timestamps: bool,
+    rate: Input,
+    dry_run: bool,
+    error: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum ImportField {
+    #[default]
+    Path,
+    Topic,
+    Partitions,
+    Timestamps,
+    Rate,
+    DryRun,
+}
+
+impl ImportField {
+    const ALL: [ImportField; 6] = [
+        ImportField::Path,
+        ImportField::Topic,
+        ImportField::Partitions,
+        ImportField::Timestamps,
+        ImportField::Rate,
+        ImportField::DryRun,
+    ];
+
+    fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|f| *f == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    fn prev(self) -> Self {
+        let index = Self::ALL.iter().position(|f| *f == self).unwrap_or(0);
+        Self::ALL[(index + Self::ALL.len() - 1) % Self::ALL.len()]
+    }
+}
+
+impl ImportForm {
+    pub fn new(topic: &str) -> Self {
+        let mut topic_input = Input::default();
+        topic_input.set_value(topic);
+        Self {
+            field: ImportField::default(),
+            path: Input::default(),
+            topic: topic_input,
+            keep_partitions: true,
+            keep_timestamps: true,
+            rate: Input::default(),
+            dry_run: true,
+            error: None,
+        }
+    }
+
+    pub fn render(&self, area: Rect, buf: &mut Buffer) {
+        let area = center(area, Constraint::Length(64), Constraint::Length(22));
+        Clear.render(area, buf);
+
+        let block = Block::new()
+            .title(Line::raw("Import messages").centered())
+            .borders(Borders::ALL)
+            .border_set(symbols::border::ROUNDED)
+            .border_style(THEME.borders)
+            .style(THEME.root);
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        let [path, topic, partitions, timestamps, rate, dry_run, status] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Fill(1),
+        ])
+        .areas(inner);
+
+        let render_field = |line: Line, title: &str, area: Rect, buf: &mut Buffer| {
+            let block = Block::new()
+                .title(Line::raw(title.to_string()))
+                .border_set(symbols::border::ROUNDED)
+                .border_style(THEME.borders)
+                .borders(Borders::ALL);
+            Paragraph::new(line).block(block).render(area, buf);
+        };
+        let choice = |value: &str, field: ImportField| {
+            let line = Line::raw(format!("‹ {} ›", value));
+            if field == self.field {
+                line.style(THEME.tabs_selected)
+            } else {
+                line
+            }
+        };
+
+        render_field(
+            self.path.line(self.field == ImportField::Path),
+            "JSON Lines file, as exported",
+            path,
+            buf,
+        );
+        render_field(
+            self.topic.line(self.field == ImportField::Topic),
+            "Into topic",
+            topic,
+            buf,
+        );
+        let partitions_choice = if self.keep_partitions {
+            "Keep the original partitions"
+        } else {
+            "Partition by key"
+        };
+        render_field(
+            choice(partitions_choice, ImportField::Partitions),
+            "Partitions",
+            partitions,
+            buf,
+        );
+        let timestamps_choice = if self.keep_timestamps {
+            "Keep the original timestamps"
+        } else {
+            "Time of sending"
+        };
+        render_field(
+            choice(timestamps_choice, ImportField::Timestamps),
+            "Timestamps",
+            timestamps,
+            buf,
+        );
+        render_field(
+            self.rate.line(self.field == ImportField::Rate),
+            "Messages a second (empty: as fast as possible)",
+            rate,
+            buf,
+        );
+        let dry_run_choice = if self.dry_run {
+            "Only show what would be sent"
+        } else {
+            "Send"
+        };
+        render_field(
+            choice(dry_run_choice, ImportField::DryRun),
+            "Dry run",
+            dry_run,
+            buf,
+        );
+
+        let status_line = match &self.error {
+            Some(err) => Line::raw(err.as_str()).style(THEME.error),
+            None => Line::raw("Tab: next field  ←/→: choose  Enter: import  Esc: cancel")
+                .style(THEME.tip),
+        };
+        Paragraph::new(status_line)
+            .wrap(Wrap { trim: true })
+            .render(status, buf);
+    }
+
+    pub fn handle_key_press(&mut self, key: &KeyEvent) -> PopupAction {
+        match key.code {
+            KeyCode::Esc => return PopupAction::Close,
+            KeyCode::Tab | KeyCode::Down => self.field = self.field.next(),
+            KeyCode::BackTab | KeyCode::Up => self.field = self.field.prev(),
+            KeyCode::Enter => match self.import() {
+                Ok(import) => return PopupAction::Import(import),
+                Err(e) => self.error = Some(e.to_string()),
+            },
+            KeyCode::Left | KeyCode::Right | KeyCode::Char(' ')
+                if matches!(
+                    self.field,
+                    ImportField::Partitions | ImportField::Timestamps | ImportField::DryRun
+                ) =>
+            {
+                self.error = None;
+                match self.field {
+                    ImportField::Partitions => self.keep_partitions = !self.keep_partitions,
+                    ImportField::Timestamps => self.keep_timestamps = !self.keep_timestamps,
+                    _ => self.dry_run = !self.dry_run,
+                }
+            }
+            _ => {
+                let input = match self.field {
+                    ImportField::Path => &mut self.path,
+                    ImportField::Topic => &mut self.topic,
+                    ImportField::Rate => &mut self.rate,
+                    _ => return PopupAction::None,
+                };
+                if input.handle_key_press(key) {
+                    self.error = None;
+                }
+            }
+        }
+        PopupAction::None
+    }
+
+    fn import(&self) -> Result<Import> {
+        let path = self.path.value().trim();
+        if path.is_empty() {
+            return Err(eyre!("Enter the file to import"));
+        }
+        let topic = self.topic.value().trim();
+        if topic.is_empty() {
+            return Err(eyre!("Enter the topic to import into"));
+        }
+        let rate = match self.rate.value().trim() {
+            "" => None,
+            rate => match rate.parse() {
+                Ok(rate) if rate > 0 => Some(rate),
+                _ => return Err(eyre!("Invalid rate \"{}\"", rate)),
+            },
+        };
+        Ok(Import {
+            path: path.into(),
+            topic: topic.to_string(),
+            keep_partitions: self.keep_partitions,
+            keep_timestamps: self.keep_timestamps,
+            rate,
+            dry_run: self.dry_run,
+        })
+    }
+}
+
+/// The messages of an import, and its progress once it runs.
+pub struct ImportView {
+    import: Import,
+    /// Read off the UI thread, empty until `reading` is done.
+    messages: Arc<Vec<KafkaMessage>>,
+    reading: Option<JoinHandle<Result<Vec<KafkaMessage>>>>,
+    read_error: Option<String>,
+    progress: Arc<Mutex<ImportProgress>>,
+    producer: FutureProducer,
+    state: ListState,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl ImportView {
+    /// Starts reading the file, sending follows unless it is a dry run.
+    pub fn new(import: Import, producer: FutureProducer) -> Self {
+        let path = import.path.clone();
+        Self {
+            import,
+            messages: Arc::new(Vec::new()),
+            reading: Some(tokio::task::spawn_blocking(move || read_messages(&path))),
+            read_error: None,
+            progress: Arc::new(Mutex::new(ImportProgress::default())),
+            producer,
+            state: ListState::default(),
+            handle: None,
+        }
+    }
+
+    /// Takes the messages once the file is read.
+    fn poll_reading(&mut self) {
+        let Some(reading) = &mut self.reading else {
+            return;
+        };
+        let Some(result) = reading.now_or_never() else {
+            return;
+        };
+        self.reading = None;
+        match result.map_err(|e| eyre!(e)).and_then(|read| read) {
+            Ok(messages) => {
+                self.messages = Arc::new(messages);
+                if !self.import.dry_run {
+                    self.start();
+                }
+            }
+            Err(e) => self.read_error = Some(format!("{:#}", e)),
+        }
+    }
+
+    fn is_read(&self) -> bool {
+        self.reading.is_none() && self.read_error.is_none()
+    }
+
+    fn start(&mut self) {
+        self.import.dry_run = false;
+        let import = self.import.clone();
+        let messages = self.messages.clone();
+        let producer = self.producer.clone();
+        let progress = self.progress.clone();
+        self.state.select(None);
+        self.handle = Some(tokio::spawn(async move {
+            let result = run_import(import, messages, producer, progress.clone()).await;
+            let mut progress = progress.lock().unwrap();
+            progress.finished = true;
+            progress.error = result.err().map(|e| e.to_string());
+        }));
+    }
+
+    pub fn render(&mut self, area: Rect, buf: &mut Buffer) {
+        self.poll_reading();
+        let total = self.messages.len();
+        let title = if let Some(error) = &self.read_error {
+            format!("Import {}: {}", self.import.path.display(), error)
+        } else if self.reading.is_some() {
+            format!("Reading {}…", self.import.path.display())
+        } else if self.import.dry_run {
+            let shown = if total > MAX_PREVIEW {
+                format!(", first {} shown", MAX_PREVIEW)
+            } else {
+                String::new()
+            };
+            format!(
+                "Dry run: {} messages{} from {} into {} (Enter to send)",
+                total,
+                shown,
+                self.import.path.display(),
+                self.import.topic
+            )
+        } else {
+            let progress = self.progress.lock().unwrap();
+            let status = match (&progress.error, progress.finished) {
+                (Some(error), _) => format!("failed: {}", error),
+                (None, true) => "done".to_string(),
+                (None, false) if self.handle.as_ref().is_some_and(|h| h.is_finished()) => {
+                    "stopped".to_string()
+                }
+                (None, false) => "sending".to_string(),
+            };
+            format!(
+                "Import {} into {}: {} of {} sent, {} failed ({})",
+                self.import.path.display(),
+                self.import.topic,
+                progress.sent,
+                total,
+                progress.failed,
+                status
+            )
+        };
+        let block = Block::new()
+            .title(Line::raw(title).centered())
+            .borders(Borders::ALL)
+            .border_set(symbols::border::ROUNDED)
+            .border_style(THEME.borders)
+            .padding(Padding::horizontal(1));
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        if !self.is_read() {
+            return;
+        }
+        if self.import.dry_run {
+            // Only the messages on screen are turned into items.
+            let count = total.min(MAX_PREVIEW);
+            let height = inner.height as usize;
+            let selected = match self.state.selected() {
+                Some(selected) if count > 0 => Some(selected.min(count - 1)),
+                _ => None,
+            };
+            self.state.select(selected);
+            let offset = visible_offset(self.state.offset(), selected, height)
+                .min(count.saturating_sub(height));
+            *self.state.offset_mut() = offset;
+            let items: Vec<ListItem> = self.messages[offset..count.min(offset + height)]
+                .iter()
+                .map(|m| self.preview(m))
+                .collect();
+            let list = List::new(items)
+                .highlight_style(THEME.tabs_selected)
+                .highlight_symbol(">")
+                .highlight_spacing(HighlightSpacing::Always);
+            let mut state = ListState::default().with_selected(selected.map(|i| i - offset));
+            StatefulWidget::render(list, inner, buf, &mut state);
+            return;
+        }
+
+        let [gauge, failures] =
+            Layout::vertical([Constraint::Length(2), Constraint::Fill(1)]).areas(inner);
+        let progress = self.progress.lock().unwrap();
+        let done = progress.sent + progress.failed;
+        let ratio = if total == 0 {
+            1.0
+        } else {
+            done as f64 / total as f64
+        };
+        LineGauge::default()
+            .filled_style(THEME.tabs_selected)
+            .label(format!("{} of {}", done, total))
+            .ratio(ratio.clamp(0.0, 1.0))
+            .render(gauge, buf);
+
+        let mut items: Vec<ListItem> = progress
+            .failures
+            .iter()
+            .map(|failure| ListItem::new(Line::raw(failure.clone()).style(THEME.error)))
+            .collect();
+        if progress.failed as usize > MAX_FAILURES {
+            items.push(ListItem::new(
+                Line::raw(format!("first {} failures kept", MAX_FAILURES)).style(THEME.tip),
+            ));
+        }
+        drop(progress);
+        let list = List::new(items)
+            .highlight_style(THEME.tabs_selected)
+            .highlight_symbol(">")
+            .highlight_spacing(HighlightSpacing::Always);
+        StatefulWidget::render(list, failures, buf, &mut self.state);
+    }
+
+    /// Where and when a message would be sent, and what.
+    fn preview(&self, message: &KafkaMessage) -> ListItem<'static> {
+        let partition = if self.import.keep_partitions {
+            format!("P{}", message.partition)
+        } else {
+            "by key".to_string()
+        };
+        let timestamp = match message.timestamp.millis() {
+            Some(millis) if self.import.keep_timestamps => format_timestamp(millis),
+            _ => "now".to_string(),
+        };
+        ListItem::new(Line::from(vec![
+            Span::raw(format!("{} {} ", partition, timestamp)),
+            bytes_summary(message.key.as_deref(), None, BytesView::Text, 40),
+            Span::raw(" "),
+            bytes_summary(message.payload.as_deref(), None, BytesView::Text, 400),
+        ]))
+    }
+
+    /// Esc leaves and stops the import, Enter sends what a dry run showed.
+    pub fn handle_key_press(&mut self, key: &KeyEvent) -> TopicPage {
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => return TopicPage::Normal,
+            KeyCode::Char('j') | KeyCode::Down => self.state.select_next(),
+            KeyCode::Char('k') | KeyCode::Up => self.state.select_previous(),
+            KeyCode::Char('g') | KeyCode::Home => self.state.select_first(),
+            KeyCode::Char('G') | KeyCode::End => self.state.select_last(),
+            KeyCode::Char('x') => {
+                if let Some(handle) = &self.handle {
+                    handle.abort();
+                }
+            }
+            KeyCode::Enter if self.import.dry_run && self.is_read() => self.start(),
+            _ => {}
+        }
+        TopicPage::Import
+    }
+}
+
+impl Drop for ImportView {
+    fn drop(&mut self) {
+        if let Some(reading) = &self.reading {
+            reading.abort();
+        }
+        if let Some(handle) = &self.handle {
+            handle.abort();
+        }
+    }
+}
@@ -0,0 +1,506 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use color_eyre::{eyre::eyre, Result};
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    symbols,
+    text::Line,
+    widgets::{
+        Block, Borders, Clear, HighlightSpacing, LineGauge, List, ListItem, ListState, Padding,
+        Paragraph, StatefulWidget, Widget, Wrap,
+    },
+};
+use rdkafka::producer::FutureProducer;
+use tokio::task::JoinHandle;
+
+use super::{
+    input::Input,
+    topic::{center, PopupAction, TopicPage},
+};
+use crate::{
+    codec::DeserializerRegistry,
+    import::MAX_FAILURES,
+    load::{run_load, KeyDistribution, LoadProgress, LoadTest},
+    template::Template,
+    theme::THEME,
+};
+
+/// Asks for the template and how many messages to make of it.
+pub struct LoadForm {
+    topic: String,
+    field: LoadField,
+    templates: Vec<Template>,
+    template: usize,
+    /// A number of messages, or a rate for a number of seconds.
+    timed: bool,
+    amount: Input,
+    rate: Input,
+    keys: KeyKind,
+    key_set: Input,
+    error: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum LoadField {
+    #[default]
+    Template,
+    Amount,
+    Count,
+    Rate,
+    Keys,
+    KeySet,
+}
+
+impl LoadField {
+    const ALL: [LoadField; 6] = [
+        LoadField::Template,
+        LoadField::Amount,
+        LoadField::Count,
+        LoadField::Rate,
+        LoadField::Keys,
+        LoadField::KeySet,
+    ];
+
+    fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|f| *f == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    fn prev(self) -> Self {
+        let index = Self::ALL.iter().position(|f| *f == self).unwrap_or(0);
+        Self::ALL[(index + Self::ALL.len() - 1) % Self::ALL.len()]
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum KeyKind {
+    #[default]
+    Fixed,
+    Sequential,
+    Random,
+}
+
+impl KeyKind {
+    fn next(self) -> Self {
+        match self {
+            KeyKind::Fixed => KeyKind::Sequential,
+            KeyKind::Sequential => KeyKind::Random,
+            KeyKind::Random => KeyKind::Fixed,
+        }
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            KeyKind::Fixed => "The template key",
+            KeyKind::Sequential => "Sequential, 0, 1, 2, ...",
+            KeyKind::Random => "Random from the set below",
+        }
+    }
+}
+
+impl LoadForm {
+    /// `templates` has the send form first when it is filled in for the topic.
+    pub fn new(topic: &str, templates: Vec<Template>) -> Self {
+        let mut amount = Input::default();
+        amount.set_value("1000");
+        Self {
+            topic: topic.to_string(),
+            field: LoadField::default(),
+            templates,
+            template: 0,
+            timed: false,
+            amount,
+            rate: Input::default(),
+            keys: KeyKind::default(),
+            key_set: Input::default(),
+            error: None,
+        }
+    }
+
+    pub fn render(&self, area: Rect, buf: &mut Buffer) {
+        let area = center(area, Constraint::Length(64), Constraint::Length(22));
+        Clear.render(area, buf);
+
+        let block = Block::new()
+            .title(Line::raw(format!("Load test of {}", self.topic)).centered())
+            .borders(Borders::ALL)
+            .border_set(symbols::border::ROUNDED)
+            .border_style(THEME.borders)
+            .style(THEME.root);
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        let [template, amount, count, rate, keys, key_set, status] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Fill(1),
+        ])
+        .areas(inner);
+
+        let render_field = |line: Line, title: &str, area: Rect, buf: &mut Buffer| {
+            let block = Block::new()
+                .title(Line::raw(title.to_string()))
+                .border_set(symbols::border::ROUNDED)
+                .border_style(THEME.borders)
+                .borders(Borders::ALL);
+            Paragraph::new(line).block(block).render(area, buf);
+        };
+        let choice = |value: &str, field: LoadField| {
+            let line = Line::raw(format!("‹ {} ›", value));
+            if field == self.field {
+                line.style(THEME.tabs_selected)
+            } else {
+                line
+            }
+        };
+
+        let template_name = self
+            .templates
+            .get(self.template)
+            .map_or("none", |t| t.name.as_str());
+        render_field(
+            choice(template_name, LoadField::Template),
+            "Template",
+            template,
+            buf,
+        );
+        let amount_choice = if self.timed {
+            "At a rate for a number of seconds"
+        } else {
+            "A number of messages"
+        };
+        render_field(
+            choice(amount_choice, LoadField::Amount),
+            "Amount",
+            amount,
+            buf,
+        );
+        render_field(
+            self.amount.line(self.field == LoadField::Count),
+            if self.timed { "Seconds" } else { "Messages" },
+            count,
+            buf,
+        );
+        render_field(
+            self.rate.line(self.field == LoadField::Rate),
+            if self.timed {
+                "Messages a second"
+            } else {
+                "Messages a second (empty: as fast as possible)"
+            },
+            rate,
+            buf,
+        );
+        render_field(
+            choice(self.keys.title(), LoadField::Keys),
+            "Keys",
+            keys,
+            buf,
+        );
+        render_field(
+            self.key_set.line(self.field == LoadField::KeySet),
+            "Key set, separated by commas",
+            key_set,
+            buf,
+        );
+
+        let status_line = match &self.error {
+            Some(err) => Line::raw(err.as_str()).style(THEME.error),
+            None => Line::raw("Tab: next field  ←/→: choose  Enter: start  Esc: cancel")
+                .style(THEME.tip),
+        };
+        Paragraph::new(status_line)
+            .wrap(Wrap { trim: true })
+            .render(status, buf);
+    }
+
+    pub fn handle_key_press(&mut self, key: &KeyEvent) -> PopupAction {
+        match key.code {
+            KeyCode::Esc => return PopupAction::Close,
+            KeyCode::Tab | KeyCode::Down => self.field = self.field.next(),
+            KeyCode::BackTab | KeyCode::Up => self.field = self.field.prev(),
+            KeyCode::Enter => match self.load() {
+                Ok(load) => return PopupAction::Load(load),
+                Err(e) => self.error = Some(e.to_string()),
+            },
+            KeyCode::Left | KeyCode::Right | KeyCode::Char(' ')
+                if matches!(
+                    self.field,
+                    LoadField::Template | LoadField::Amount | LoadField::Keys
+                ) =>
+            {
+                self.error = None;
+                match self.field {
+                    LoadField::Template if !self.templates.is_empty() => {
+                        let count = self.templates.len();
+                        self.template = match key.code {
+                            KeyCode::Left => (self.template + count - 1) % count,
+                            _ => (self.template + 1) % count,
+                        };
+                    }
+                    LoadField::Amount => self.timed = !self.timed,
+                    LoadField::Keys => self.keys = self.keys.next(),
+                    _ => {}
+                }
+            }
+            _ => {
+                let input = match self.field {
+                    LoadField::Count => &mut self.amount,
+                    LoadField::Rate => &mut self.rate,
+                    LoadField::KeySet => &mut self.key_set,
+                    _ => return PopupAction::None,
+                };
+                if input.handle_key_press(key) {
+                    self.error = None;
+                }
+            }
+        }
+        PopupAction::None
+    }
+
+    fn load(&self) -> Result<LoadTest> {
+        let template = self.templates.get(self.template).cloned().ok_or_else(|| {
+            eyre!("No template, fill in the send form or save a template with Ctrl+W")
+        })?;
+        let amount: u64 = match self.amount.value().trim().parse() {
+            Ok(amount) if amount > 0 => amount,
+            _ => return Err(eyre!("Invalid amount \"{}\"", self.amount.value().trim())),
+        };
+        let rate = match self.rate.value().trim() {
+            "" if self.timed => return Err(eyre!("Enter the messages a second")),
+            "" => None,
+            rate => match rate.parse::<u32>() {
+                Ok(rate) if rate > 0 => Some(rate),
+                _ => return Err(eyre!("Invalid rate \"{}\"", rate)),
+            },
+        };
+        let keys = match self.keys {
+            KeyKind::Fixed => KeyDistribution::Fixed,
+            KeyKind::Sequential => KeyDistribution::Sequential,
+            KeyKind::Random => {
+                let keys: Vec<String> = self
+                    .key_set
+                    .value()
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|key| !key.is_empty())
+                    .map(String::from)
+                    .collect();
+                if keys.is_empty() {
+                    return Err(eyre!("Enter the keys to choose from"));
+                }
+                KeyDistribution::Random(keys)
+            }
+        };
+        let count = match rate {
+            Some(rate) if self.timed => amount
+                .checked_mul(rate as u64)
+                .ok_or_else(|| eyre!("Too many messages"))?,
+            _ => amount,
+        };
+        Ok(LoadTest {
+            topic: self.topic.clone(),
+            template,
+            count,
+            rate,
+            keys,
+        })
+    }
+}
+
+/// Live numbers of a running load test.
+pub struct LoadView {
+    load: LoadTest,
+    progress: Arc<Mutex<LoadProgress>>,
+    state: ListState,
+    handle: JoinHandle<()>,
+    stopped: bool,
+}
+
+impl LoadView {
+    pub fn start(
+        load: LoadTest,
+        deserializers: Arc<DeserializerRegistry>,
+        producer: FutureProducer,
+    ) -> Self {
+        let progress = Arc::new(Mutex::new(LoadProgress::new()));
+        let handle = {
+            let load = load.clone();
+            let progress = progress.clone();
+            tokio::spawn(async move {
+                let result = run_load(load, deserializers, producer, progress.clone()).await;
+                let mut progress = progress.lock().unwrap();
+                progress.finished = Some(Instant::now());
+                progress.error = result.err().map(|e| e.to_string());
+            })
+        };
+        Self {
+            load,
+            progress,
+            state: ListState::default(),
+            handle,
+            stopped: false,
+        }
+    }
+
+    fn stop(&mut self) {
+        self.handle.abort();
+        let mut progress = self.progress.lock().unwrap();
+        if progress.finished.is_none() {
+            progress.finished = Some(Instant::now());
+            self.stopped = true;
+        }
+    }
+
+    pub fn render(&mut self, area: Rect, buf: &mut Buffer) {
+        let progress = self.progress.lock().unwrap();
+        let status = match (&progress.error, progress.finished) {
+            (Some(error), _) => format!("failed: {}", error),
+            (None, Some(_)) if self.stopped => "stopped".to_string(),
+            (None, Some(_)) => "done".to_string(),
+            (None, None) => "sending".to_string(),
+        };
+        let rate = match self.load.rate {
+            Some(rate) => format!("{} a second", rate),
+            None => "as fast as possible".to_string(),
+        };
+        let title = format!(
+            "Load test of {}: {} messages {}, {} keys ({})",
+            self.load.topic,
+            self.load.count,
+            rate,
+            self.load.keys.to_string().to_lowercase(),
+            status
+        );
+        let block = Block::new()
+            .title(Line::raw(title).centered())
+            .borders(Borders::ALL)
+            .border_set(symbols::border::ROUNDED)
+            .border_style(THEME.borders)
+            .padding(Padding::horizontal(1));
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        let [gauge, stats, failures] = Layout::vertical([
+            Constraint::Length(2),
+            Constraint::Length(4),
+            Constraint::Fill(1),
+        ])
+        .areas(inner);
+
+        let done = progress.sent + progress.failed;
+        LineGauge::default()
+            .filled_style(THEME.tabs_selected)
+            .label(format!("{} of {}", done, self.load.count))
+            .ratio((done as f64 / self.load.count.max(1) as f64).clamp(0.0, 1.0))
+            .render(gauge, buf);
+
+        let latencies = &progress.latencies;
+        let percentile = |p: f64| {
+            latencies
+                .percentile(p)
+                .map_or("-".to_string(), format_latency)
+        };
+        let lines = vec![
+            Line::raw(format!(
+                "Delivered   {}, {} failed in {:.1}s",
+                progress.sent,
+                progress.failed,
+                progress.elapsed().as_secs_f64()
+            )),
+            Line::raw(format!(
+                "Throughput  {:.0} msg/s over the last second, {:.0} msg/s on average",
+                progress.throughput,
+                progress.average()
+            )),
+            Line::raw(format!(
+                "Latency     p50 {}  p95 {}  p99 {}  max {}",
+                percentile(50.0),
+                percentile(95.0),
+                percentile(99.0),
+                match latencies.percentile(100.0) {
+                    Some(_) => format_latency(latencies.max()),
+                    None => "-".to_string(),
+                }
+            )),
+        ];
+        Paragraph::new(lines)
+            .style(THEME.content)
+            .render(stats, buf);
+
+        let mut items: Vec<ListItem> = progress
+            .failures
+            .iter()
+            .map(|failure| ListItem::new(Line::raw(failure.clone()).style(THEME.error)))
+            .collect();
+        if progress.failed as usize > MAX_FAILURES {
+            items.push(ListItem::new(
+                Line::raw(format!("first {} failures kept", MAX_FAILURES)).style(THEME.tip),
+            ));
+        }
+        drop(progress);
+        let list = List::new(items)
+            .highlight_style(THEME.tabs_selected)
+            .highlight_symbol(">")
+            .highlight_spacing(HighlightSpacing::Always);
+        StatefulWidget::render(list, failures, buf, &mut self.state);
+    }
+
+    /// Esc leaves and stops the load test.
+    pub fn handle_key_press(&mut self, key: &KeyEvent) -> TopicPage {
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => return TopicPage::Normal,
+            KeyCode::Char('j') | KeyCode::Down => self.state.select_next(),
+            KeyCode::Char('k') | KeyCode::Up => self.state.select_previous(),
+            KeyCode::Char('x') => self.stop(),
+            _ => {}
+        }
+        TopicPage::Load
+    }
+}
+
+impl Drop for LoadView {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+fn format_latency(latency: Duration) -> String {
+    let millis = latency.as_secs_f64() * 1000.0;
+    if millis < 10.0 {
+        format!("{:.2}ms", millis)
+    } else {
+        format!("{:.0}ms", millis)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timed(seconds: &str, rate: &str) -> LoadForm {
+        let mut form = LoadForm::new("orders", vec![Template::default()]);
+        form.timed = true;
+        form.amount.set_value(seconds);
+        form.rate.set_value(rate);
+        form
+    }
+
+    #[test]
+    fn counts_the_messages_of_a_timed_test() {
+        let load = timed("10", "5").load().unwrap();
+        assert_eq!((load.count, load.rate), (50, Some(5)));
+
+        let error = timed(&u64::MAX.to_string(), "2").load().unwrap_err();
+        assert_eq!(error.to_string(), "Too many messages");
+    }
+}
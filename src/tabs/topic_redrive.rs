@@ -0,0 +1,489 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+
+use color_eyre::{eyre::eyre, Result};
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    symbols,
+    text::Line,
+    widgets::{
+        Block, Borders, Clear, HighlightSpacing, LineGauge, List, ListItem, ListState, Padding,
+        Paragraph, StatefulWidget, Widget, Wrap,
+    },
+};
+use rdkafka::{config::ClientConfig, producer::FutureProducer};
+use strum::IntoEnumIterator;
+use tokio::task::JoinHandle;
+
+use super::{
+    input::Input,
+    topic::{center, PopupAction, TopicPage},
+    topic_search::MatchKind,
+};
+use crate::{
+    codec::DeserializerRegistry,
+    import::MAX_FAILURES,
+    kafka::KafkaTopic,
+    redrive::{run_redrive, Redrive, RedriveProgress, PROVENANCE_HEADER},
+    search::{Scope, SearchProgress, SearchRange},
+    theme::THEME,
+};
+
+/// Asks for the range to move, where to and which messages.
+pub struct RedriveForm {
+    topic: KafkaTopic,
+    /// Names of the topics to move into.
+    topics: Vec<String>,
+    field: RedriveField,
+    by_time: bool,
+    from: Input,
+    to: Input,
+    destination: Input,
+    kind: MatchKind,
+    pattern: Input,
+    scope: Scope,
+    resume: bool,
+    error: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum RedriveField {
+    Range,
+    From,
+    To,
+    #[default]
+    Destination,
+    Kind,
+    Pattern,
+    Scope,
+    Resume,
+}
+
+impl RedriveField {
+    const ALL: [RedriveField; 8] = [
+        RedriveField::Range,
+        RedriveField::From,
+        RedriveField::To,
+        RedriveField::Destination,
+        RedriveField::Kind,
+        RedriveField::Pattern,
+        RedriveField::Scope,
+        RedriveField::Resume,
+    ];
+
+    fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|f| *f == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    fn prev(self) -> Self {
+        let index = Self::ALL.iter().position(|f| *f == self).unwrap_or(0);
+        Self::ALL[(index + Self::ALL.len() - 1) % Self::ALL.len()]
+    }
+}
+
+impl RedriveForm {
+    pub fn new(topic: KafkaTopic, topics: Vec<String>) -> Self {
+        Self {
+            topic,
+            topics,
+            field: RedriveField::default(),
+            by_time: false,
+            from: Input::default(),
+            to: Input::default(),
+            destination: Input::default(),
+            kind: MatchKind::default(),
+            pattern: Input::default(),
+            scope: Scope::default(),
+            resume: true,
+            error: None,
+        }
+    }
+
+    pub fn render(&self, area: Rect, buf: &mut Buffer) {
+        let area = center(area, Constraint::Length(64), Constraint::Length(28));
+        Clear.render(area, buf);
+
+        let block = Block::new()
+            .title(Line::raw(format!("Redrive {}", self.topic.name)).centered())
+            .borders(Borders::ALL)
+            .border_set(symbols::border::ROUNDED)
+            .border_style(THEME.borders)
+            .style(THEME.root);
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        let [range, from, to, destination, kind, pattern, scope, resume, status] =
+            Layout::vertical([
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Fill(1),
+            ])
+            .areas(inner);
+
+        let render_field = |line: Line, title: &str, area: Rect, buf: &mut Buffer| {
+            let block = Block::new()
+                .title(Line::raw(title.to_string()))
+                .border_set(symbols::border::ROUNDED)
+                .border_style(THEME.borders)
+                .borders(Borders::ALL);
+            Paragraph::new(line).block(block).render(area, buf);
+        };
+        let choice = |value: &str, field: RedriveField| {
+            let line = Line::raw(format!("‹ {} ›", value));
+            if field == self.field {
+                line.style(THEME.tabs_selected)
+            } else {
+                line
+            }
+        };
+
+        let range_kind = if self.by_time { "Time" } else { "Offsets" };
+        render_field(choice(range_kind, RedriveField::Range), "Range", range, buf);
+        let (from_title, to_title) = if self.by_time {
+            ("From (10:42 yesterday, -1h, ...)", "To (empty: now)")
+        } else {
+            (
+                "From offset (empty: low watermark)",
+                "To offset, inclusive (empty: high watermark)",
+            )
+        };
+        render_field(
+            self.from.line(self.field == RedriveField::From),
+            from_title,
+            from,
+            buf,
+        );
+        render_field(
+            self.to.line(self.field == RedriveField::To),
+            to_title,
+            to,
+            buf,
+        );
+        render_field(
+            self.destination
+                .line(self.field == RedriveField::Destination),
+            "Into topic",
+            destination,
+            buf,
+        );
+        render_field(
+            choice(self.kind.title(), RedriveField::Kind),
+            "Only messages matching",
+            kind,
+            buf,
+        );
+        render_field(
+            self.pattern.line(self.field == RedriveField::Pattern),
+            "Pattern (empty: every message)",
+            pattern,
+            buf,
+        );
+        let scope_title = match self.kind {
+            MatchKind::Query => "In (the query names its fields)",
+            _ => "In",
+        };
+        render_field(
+            choice(&self.scope.to_string(), RedriveField::Scope),
+            scope_title,
+            scope,
+            buf,
+        );
+        let resume_choice = if self.resume {
+            "Resume from the last checkpoint"
+        } else {
+            "Start over"
+        };
+        render_field(
+            choice(resume_choice, RedriveField::Resume),
+            "Checkpoint",
+            resume,
+            buf,
+        );
+
+        let status_line = match &self.error {
+            Some(err) => Line::raw(err.as_str()).style(THEME.error),
+            None => Line::raw("Tab: next field  ←/→: choose  Enter: redrive  Esc: cancel")
+                .style(THEME.tip),
+        };
+        Paragraph::new(status_line)
+            .wrap(Wrap { trim: true })
+            .render(status, buf);
+    }
+
+    pub fn handle_key_press(&mut self, key: &KeyEvent) -> PopupAction {
+        match key.code {
+            KeyCode::Esc => return PopupAction::Close,
+            KeyCode::Tab | KeyCode::Down => self.field = self.field.next(),
+            KeyCode::BackTab | KeyCode::Up => self.field = self.field.prev(),
+            KeyCode::Enter => match self.redrive() {
+                Ok(redrive) => return PopupAction::Redrive(redrive),
+                Err(e) => self.error = Some(e.to_string()),
+            },
+            KeyCode::Left | KeyCode::Right | KeyCode::Char(' ')
+                if matches!(
+                    self.field,
+                    RedriveField::Range
+                        | RedriveField::Kind
+                        | RedriveField::Scope
+                        | RedriveField::Resume
+                ) =>
+            {
+                self.error = None;
+                match self.field {
+                    RedriveField::Range => {
+                        self.by_time = !self.by_time;
+                        self.from.set_value("");
+                        self.to.set_value("");
+                    }
+                    RedriveField::Kind => self.kind = self.kind.next(),
+                    RedriveField::Scope => {
+                        let scopes: Vec<Scope> = Scope::iter().collect();
+                        let index = scopes.iter().position(|s| *s == self.scope).unwrap_or(0);
+                        self.scope = scopes[(index + 1) % scopes.len()];
+                    }
+                    _ => self.resume = !self.resume,
+                }
+            }
+            _ => {
+                let input = match self.field {
+                    RedriveField::From => &mut self.from,
+                    RedriveField::To => &mut self.to,
+                    RedriveField::Destination => &mut self.destination,
+                    RedriveField::Pattern => &mut self.pattern,
+                    _ => return PopupAction::None,
+                };
+                if input.handle_key_press(key) {
+                    self.error = None;
+                }
+            }
+        }
+        PopupAction::None
+    }
+
+    fn redrive(&self) -> Result<Redrive> {
+        let range = if self.by_time {
+            SearchRange::time(self.from.value(), self.to.value())?
+        } else {
+            SearchRange::offsets(self.from.value(), self.to.value())?
+        };
+
+        let destination = self.destination.value().trim();
+        if destination.is_empty() {
+            return Err(eyre!("Enter the topic to move the messages into"));
+        }
+        if destination == self.topic.name {
+            return Err(eyre!("Choose another topic than {}", self.topic.name));
+        }
+        if !self.topics.iter().any(|topic| topic == destination) {
+            return Err(eyre!("No topic \"{}\"", destination));
+        }
+
+        let filter = match self.pattern.value() {
+            "" => None,
+            pattern => Some((self.kind.matcher(pattern)?, self.scope)),
+        };
+
+        Ok(Redrive {
+            source: self.topic.clone(),
+            destination: destination.to_string(),
+            range,
+            filter,
+            resume: self.resume,
+        })
+    }
+}
+
+/// A running or finished redrive and its progress.
+pub struct RedriveView {
+    redrive: Redrive,
+    scan: Arc<Mutex<SearchProgress>>,
+    progress: Arc<Mutex<RedriveProgress>>,
+    pause: Arc<AtomicBool>,
+    state: ListState,
+    handle: JoinHandle<()>,
+}
+
+impl RedriveView {
+    pub fn start(
+        redrive: Redrive,
+        config: ClientConfig,
+        deserializers: Arc<DeserializerRegistry>,
+        producer: FutureProducer,
+    ) -> Self {
+        let scan = Arc::new(Mutex::new(SearchProgress::default()));
+        let progress = Arc::new(Mutex::new(RedriveProgress::default()));
+        let pause = Arc::new(AtomicBool::new(false));
+        let handle = {
+            let redrive = redrive.clone();
+            let scan = scan.clone();
+            let progress = progress.clone();
+            let pause = pause.clone();
+            tokio::spawn(async move {
+                let result = run_redrive(
+                    redrive,
+                    config,
+                    deserializers,
+                    producer,
+                    scan,
+                    progress.clone(),
+                    pause,
+                )
+                .await;
+                let mut progress = progress.lock().unwrap();
+                progress.finished = true;
+                progress.error = result.err().map(|e| e.to_string());
+            })
+        };
+
+        Self {
+            redrive,
+            scan,
+            progress,
+            pause,
+            state: ListState::default(),
+            handle,
+        }
+    }
+
+    pub fn render(&mut self, area: Rect, buf: &mut Buffer) {
+        let scan = self.scan.lock().unwrap();
+        let progress = self.progress.lock().unwrap();
+        let status = match (&progress.error, progress.finished) {
+            (Some(error), _) => format!("failed: {}", error),
+            (None, true) => "done".to_string(),
+            (None, false) if self.handle.is_finished() => "cancelled".to_string(),
+            (None, false) if progress.paused => "paused".to_string(),
+            (None, false) if self.pause.load(Ordering::Relaxed) => "pausing".to_string(),
+            (None, false) => "moving".to_string(),
+        };
+        let filter = match &self.redrive.filter {
+            Some((matcher, _)) => format!(" matching {}", matcher),
+            None => String::new(),
+        };
+        let block = Block::new()
+            .title(
+                Line::raw(format!(
+                    "Redrive {} into {}{}: {} sent, {} skipped, {} failed ({})",
+                    self.redrive.source.name,
+                    self.redrive.destination,
+                    filter,
+                    progress.sent,
+                    progress.skipped,
+                    progress.failed,
+                    status
+                ))
+                .centered(),
+            )
+            .borders(Borders::ALL)
+            .border_set(symbols::border::ROUNDED)
+            .border_style(THEME.borders)
+            .padding(Padding::horizontal(1));
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        let rows = scan.partitions.len().min(8) as u16;
+        let [gauges, checkpoint, failures] = Layout::vertical([
+            Constraint::Length(rows + 1),
+            Constraint::Length(3),
+            Constraint::Fill(1),
+        ])
+        .areas(inner);
+        let gauge_areas =
+            Layout::vertical(vec![Constraint::Length(1); rows as usize]).split(gauges);
+        for (partition, area) in scan.partitions.iter().zip(gauge_areas.iter()) {
+            let label = format!(
+                "P{} {}..{} at {} (low {}, high {})",
+                partition.id,
+                partition.start,
+                partition.end,
+                partition.position.min(partition.end),
+                partition.low,
+                partition.high
+            );
+            LineGauge::default()
+                .filled_style(THEME.tabs_selected)
+                .label(label)
+                .ratio(partition.ratio())
+                .render(*area, buf);
+        }
+        if scan.partitions.len() > rows as usize {
+            let done = scan.partitions.iter().filter(|p| p.is_done()).count();
+            let last = gauge_areas.last().copied().unwrap_or(gauges);
+            Line::raw(format!(
+                "{} of {} partitions done",
+                done,
+                scan.partitions.len()
+            ))
+            .style(THEME.tip)
+            .render(last, buf);
+        }
+
+        let lines = vec![
+            Line::raw(format!(
+                "Checkpoint in group {} after {} of {} scanned messages, {} moved before",
+                self.redrive.group(),
+                progress.checkpoint,
+                scan.scanned,
+                progress.resumed
+            )),
+            Line::raw(format!(
+                "Every message gets a {} header with its topic/partition/offset",
+                PROVENANCE_HEADER
+            ))
+            .style(THEME.tip),
+        ];
+        Paragraph::new(lines)
+            .style(THEME.content)
+            .render(checkpoint, buf);
+
+        let mut items: Vec<ListItem> = progress
+            .failures
+            .iter()
+            .map(|failure| ListItem::new(Line::raw(failure.clone()).style(THEME.error)))
+            .collect();
+        if progress.failed as usize > MAX_FAILURES {
+            items.push(ListItem::new(
+                Line::raw(format!("first {} failures kept", MAX_FAILURES)).style(THEME.tip),
+            ));
+        }
+        drop(progress);
+        drop(scan);
+        let list = List::new(items)
+            .highlight_style(THEME.tabs_selected)
+            .highlight_symbol(">")
+            .highlight_spacing(HighlightSpacing::Always);
+        StatefulWidget::render(list, failures, buf, &mut self.state);
+    }
+
+    /// p pauses at a checkpoint, x cancels, Esc leaves and cancels.
+    pub fn handle_key_press(&mut self, key: &KeyEvent) -> TopicPage {
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => return TopicPage::Normal,
+            KeyCode::Char('j') | KeyCode::Down => self.state.select_next(),
+            KeyCode::Char('k') | KeyCode::Up => self.state.select_previous(),
+            KeyCode::Char('p') => {
+                self.pause.fetch_xor(true, Ordering::Relaxed);
+            }
+            KeyCode::Char('x') => self.handle.abort(),
+            _ => {}
+        }
+        TopicPage::Redrive
+    }
+}
+
+impl Drop for RedriveView {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
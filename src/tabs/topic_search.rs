@@ -0,0 +1,467 @@
+use std::sync::{Arc, Mutex};
+
+use color_eyre::{eyre::eyre, Result};
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    symbols,
+    text::{Line, Span},
+    widgets::{
+        Block, Borders, Clear, HighlightSpacing, LineGauge, List, ListItem, ListState, Padding,
+        Paragraph, StatefulWidget, Widget, Wrap,
+    },
+};
+use rdkafka::config::ClientConfig;
+use regex::Regex;
+use strum::IntoEnumIterator;
+use tokio::task::JoinHandle;
+
+use super::{
+    input::Input,
+    message_table::visible_offset,
+    topic::{center, PopupAction, TopicPage},
+};
+use crate::{
+    codec::DeserializerRegistry,
+    kafka::{KafkaMessage, KafkaTopic},
+    payload::{bytes_summary, BytesView},
+    query::Query,
+    search::{run_search, Matcher, Scope, Search, SearchProgress, SearchRange, MAX_MATCHES},
+    theme::THEME,
+};
+
+/// Asks for the range of a topic to scan and what to look for.
+pub struct SearchForm {
+    topic: KafkaTopic,
+    field: SearchField,
+    by_time: bool,
+    from: Input,
+    to: Input,
+    kind: MatchKind,
+    pattern: Input,
+    scope: Scope,
+    error: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum SearchField {
+    Range,
+    From,
+    To,
+    Kind,
+    #[default]
+    Pattern,
+    Scope,
+}
+
+impl SearchField {
+    const ALL: [SearchField; 6] = [
+        SearchField::Range,
+        SearchField::From,
+        SearchField::To,
+        SearchField::Kind,
+        SearchField::Pattern,
+        SearchField::Scope,
+    ];
+
+    fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|f| *f == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    fn prev(self) -> Self {
+        let index = Self::ALL.iter().position(|f| *f == self).unwrap_or(0);
+        Self::ALL[(index + Self::ALL.len() - 1) % Self::ALL.len()]
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MatchKind {
+    #[default]
+    Substring,
+    Regex,
+    Query,
+}
+
+impl MatchKind {
+    pub fn next(self) -> Self {
+        match self {
+            MatchKind::Substring => MatchKind::Regex,
+            MatchKind::Regex => MatchKind::Query,
+            MatchKind::Query => MatchKind::Substring,
+        }
+    }
+
+    pub fn title(self) -> &'static str {
+        match self {
+            MatchKind::Substring => "Substring",
+            MatchKind::Regex => "Regex",
+            MatchKind::Query => "Query, as in the filter bar",
+        }
+    }
+
+    pub fn matcher(self, pattern: &str) -> Result<Matcher> {
+        Ok(match self {
+            MatchKind::Substring => Matcher::Substring(pattern.to_string()),
+            MatchKind::Regex => {
+                Matcher::Regex(Regex::new(pattern).map_err(|e| eyre!("Invalid regex: {}", e))?)
+            }
+            MatchKind::Query => Matcher::Query(Query::parse(pattern)?),
+        })
+    }
+}
+
+impl SearchForm {
+    pub fn new(topic: KafkaTopic) -> Self {
+        Self {
+            topic,
+            field: SearchField::default(),
+            by_time: false,
+            from: Input::default(),
+            to: Input::default(),
+            kind: MatchKind::default(),
+            pattern: Input::default(),
+            scope: Scope::default(),
+            error: None,
+        }
+    }
+
+    pub fn render(&self, area: Rect, buf: &mut Buffer) {
+        let area = center(area, Constraint::Length(64), Constraint::Length(22));
+        Clear.render(area, buf);
+
+        let block = Block::new()
+            .title(Line::raw(format!("Search {}", self.topic.name)).centered())
+            .borders(Borders::ALL)
+            .border_set(symbols::border::ROUNDED)
+            .border_style(THEME.borders)
+            .style(THEME.root);
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        let [range, from, to, kind, pattern, scope, status] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Fill(1),
+        ])
+        .areas(inner);
+
+        let render_field = |line: Line, title: &str, area: Rect, buf: &mut Buffer| {
+            let block = Block::new()
+                .title(Line::raw(title.to_string()))
+                .border_set(symbols::border::ROUNDED)
+                .border_style(THEME.borders)
+                .borders(Borders::ALL);
+            Paragraph::new(line).block(block).render(area, buf);
+        };
+        let choice = |value: &str, field: SearchField| {
+            let line = Line::raw(format!("‹ {} ›", value));
+            if field == self.field {
+                line.style(THEME.tabs_selected)
+            } else {
+                line
+            }
+        };
+
+        let range_kind = if self.by_time { "Time" } else { "Offsets" };
+        render_field(choice(range_kind, SearchField::Range), "Range", range, buf);
+        let (from_title, to_title) = if self.by_time {
+            ("From (10:42 yesterday, -1h, ...)", "To (empty: now)")
+        } else {
+            (
+                "From offset (empty: low watermark)",
+                "To offset, inclusive (empty: high watermark)",
+            )
+        };
+        render_field(
+            self.from.line(self.field == SearchField::From),
+            from_title,
+            from,
+            buf,
+        );
+        render_field(
+            self.to.line(self.field == SearchField::To),
+            to_title,
+            to,
+            buf,
+        );
+        render_field(
+            choice(self.kind.title(), SearchField::Kind),
+            "Match",
+            kind,
+            buf,
+        );
+        render_field(
+            self.pattern.line(self.field == SearchField::Pattern),
+            "Pattern",
+            pattern,
+            buf,
+        );
+        let scope_title = match self.kind {
+            MatchKind::Query => "In (the query names its fields)",
+            _ => "In",
+        };
+        render_field(
+            choice(&self.scope.to_string(), SearchField::Scope),
+            scope_title,
+            scope,
+            buf,
+        );
+
+        let status_line = match &self.error {
+            Some(err) => Line::raw(err.as_str()).style(THEME.error),
+            None => Line::raw("Tab: next field  ←/→: choose  Enter: search  Esc: cancel")
+                .style(THEME.tip),
+        };
+        Paragraph::new(status_line)
+            .wrap(Wrap { trim: true })
+            .render(status, buf);
+    }
+
+    pub fn handle_key_press(&mut self, key: &KeyEvent) -> PopupAction {
+        match key.code {
+            KeyCode::Esc => return PopupAction::Close,
+            KeyCode::Tab | KeyCode::Down => self.field = self.field.next(),
+            KeyCode::BackTab | KeyCode::Up => self.field = self.field.prev(),
+            KeyCode::Enter => match self.search() {
+                Ok(search) => return PopupAction::Search(search),
+                Err(e) => self.error = Some(e.to_string()),
+            },
+            KeyCode::Left | KeyCode::Right | KeyCode::Char(' ')
+                if matches!(
+                    self.field,
+                    SearchField::Range | SearchField::Kind | SearchField::Scope
+                ) =>
+            {
+                self.error = None;
+                match self.field {
+                    SearchField::Range => {
+                        self.by_time = !self.by_time;
+                        self.from.set_value("");
+                        self.to.set_value("");
+                    }
+                    SearchField::Kind => self.kind = self.kind.next(),
+                    _ => {
+                        let scopes: Vec<Scope> = Scope::iter().collect();
+                        let index = scopes.iter().position(|s| *s == self.scope).unwrap_or(0);
+                        self.scope = scopes[(index + 1) % scopes.len()];
+                    }
+                }
+            }
+            _ => {
+                let input = match self.field {
+                    SearchField::From => &mut self.from,
+                    SearchField::To => &mut self.to,
+                    SearchField::Pattern => &mut self.pattern,
+                    _ => return PopupAction::None,
+                };
+                if input.handle_key_press(key) {
+                    self.error = None;
+                }
+            }
+        }
+        PopupAction::None
+    }
+
+    fn search(&self) -> Result<Search> {
+        let range = if self.by_time {
+            SearchRange::time(self.from.value(), self.to.value())?
+        } else {
+            SearchRange::offsets(self.from.value(), self.to.value())?
+        };
+
+        let pattern = self.pattern.value();
+        if pattern.is_empty() {
+            return Err(eyre!("Enter what to search for"));
+        }
+        let matcher = self.kind.matcher(pattern)?;
+
+        Ok(Search {
+            topic: self.topic.clone(),
+            range,
+            matcher,
+            scope: self.scope,
+        })
+    }
+}
+
+/// A running or finished search, its progress and its matches.
+pub struct SearchView {
+    search: Search,
+    progress: Arc<Mutex<SearchProgress>>,
+    state: ListState,
+    handle: JoinHandle<()>,
+}
+
+impl SearchView {
+    pub fn start(
+        search: Search,
+        config: ClientConfig,
+        deserializers: Arc<DeserializerRegistry>,
+    ) -> Self {
+        let progress = Arc::new(Mutex::new(SearchProgress::default()));
+        let task_progress = progress.clone();
+        let task_search = search.clone();
+        let handle = tokio::spawn(async move {
+            let result = run_search(task_search, config, deserializers, task_progress.clone());
+            let error = result.await.err();
+            let mut progress = task_progress.lock().unwrap();
+            progress.finished = true;
+            progress.error = error.map(|e| e.to_string());
+        });
+
+        Self {
+            search,
+            progress,
+            state: ListState::default(),
+            handle,
+        }
+    }
+
+    pub fn selected_message(&self) -> Option<KafkaMessage> {
+        let progress = self.progress.lock().unwrap();
+        self.state
+            .selected()
+            .and_then(|index| progress.messages.get(index).cloned())
+    }
+
+    pub fn render(&mut self, area: Rect, buf: &mut Buffer) {
+        let progress = self.progress.lock().unwrap();
+        let status = match (&progress.error, progress.finished) {
+            (Some(error), _) => format!("failed: {}", error),
+            (None, true) => "done".to_string(),
+            (None, false) if self.handle.is_finished() => "stopped".to_string(),
+            (None, false) => "searching".to_string(),
+        };
+        let kept = if progress.matched as usize > MAX_MATCHES {
+            format!(", first {} kept", MAX_MATCHES)
+        } else {
+            String::new()
+        };
+        let block = Block::new()
+            .title(
+                Line::raw(format!(
+                    "Search {} for {}: {} matches{} in {} messages ({})",
+                    self.search.topic.name,
+                    self.search.matcher,
+                    progress.matched,
+                    kept,
+                    progress.scanned,
+                    status
+                ))
+                .centered(),
+            )
+            .borders(Borders::ALL)
+            .border_set(symbols::border::ROUNDED)
+            .border_style(THEME.borders)
+            .padding(Padding::horizontal(1));
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        let rows = progress.partitions.len().min(8) as u16;
+        let [gauges, matches] =
+            Layout::vertical([Constraint::Length(rows + 1), Constraint::Fill(1)]).areas(inner);
+        let gauge_areas =
+            Layout::vertical(vec![Constraint::Length(1); rows as usize]).split(gauges);
+        for (partition, area) in progress.partitions.iter().zip(gauge_areas.iter()) {
+            let label = format!(
+                "P{} {}..{} at {} (low {}, high {})",
+                partition.id,
+                partition.start,
+                partition.end,
+                partition.position.min(partition.end),
+                partition.low,
+                partition.high
+            );
+            LineGauge::default()
+                .filled_style(THEME.tabs_selected)
+                .label(label)
+                .ratio(partition.ratio())
+                .render(*area, buf);
+        }
+        if progress.partitions.len() > rows as usize {
+            let done = progress.partitions.iter().filter(|p| p.is_done()).count();
+            let last = gauge_areas.last().copied().unwrap_or(gauges);
+            Line::raw(format!(
+                "{} of {} partitions done",
+                done,
+                progress.partitions.len()
+            ))
+            .style(THEME.tip)
+            .render(last, buf);
+        }
+
+        // Only the matches on screen are turned into items, there can be
+        // many thousands of them.
+        let count = progress.messages.len();
+        let height = matches.height as usize;
+        let selected = match self.state.selected() {
+            Some(selected) if count > 0 => Some(selected.min(count - 1)),
+            _ => None,
+        };
+        self.state.select(selected);
+        let offset =
+            visible_offset(self.state.offset(), selected, height).min(count.saturating_sub(height));
+        *self.state.offset_mut() = offset;
+        let items: Vec<ListItem> = progress.messages[offset..count.min(offset + height)]
+            .iter()
+            .map(|m| {
+                ListItem::new(Line::from(vec![
+                    Span::raw(format!("{}:{} ", m.partition, m.offset)),
+                    bytes_summary(
+                        m.key.as_deref(),
+                        m.decoded_key.as_ref(),
+                        BytesView::Text,
+                        40,
+                    ),
+                    Span::raw(" "),
+                    bytes_summary(
+                        m.payload.as_deref(),
+                        m.decoded_payload.as_ref(),
+                        BytesView::Text,
+                        400,
+                    ),
+                ]))
+            })
+            .collect();
+        drop(progress);
+        let list = List::new(items)
+            .highlight_style(THEME.tabs_selected)
+            .highlight_symbol(">")
+            .highlight_spacing(HighlightSpacing::Always);
+        let mut state = ListState::default().with_selected(selected.map(|i| i - offset));
+        StatefulWidget::render(list, matches, buf, &mut state);
+    }
+
+    /// Enter opens the selected match, Esc leaves and stops the search.
+    pub fn handle_key_press(&mut self, key: &KeyEvent) -> TopicPage {
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('h') | KeyCode::Left => {
+                return TopicPage::Normal
+            }
+            KeyCode::Char('j') | KeyCode::Down => self.state.select_next(),
+            KeyCode::Char('k') | KeyCode::Up => self.state.select_previous(),
+            KeyCode::Char('g') | KeyCode::Home => self.state.select_first(),
+            KeyCode::Char('G') | KeyCode::End => self.state.select_last(),
+            KeyCode::Char('x') => self.handle.abort(),
+            KeyCode::Enter | KeyCode::Char('l') | KeyCode::Right
+                if self.selected_message().is_some() =>
+            {
+                return TopicPage::MessageDetail
+            }
+            _ => {}
+        }
+        TopicPage::Search
+    }
+}
+
+impl Drop for SearchView {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
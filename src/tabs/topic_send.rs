@@ -1,27 +1,91 @@
+use std::sync::Arc;
+
 use color_eyre::{eyre::eyre, Result};
-use crossterm::event::{KeyCode, KeyEvent};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use ratatui::{
     buffer::Buffer,
     layout::{Constraint, Layout, Rect},
-    style::{Color, Modifier},
+    style::Modifier,
     symbols,
     text::{Line, Span},
-    widgets::{Block, Borders, Paragraph, Widget},
+    widgets::{Block, Borders, Clear, Paragraph, Widget, Wrap},
+};
+use rdkafka::{
+    admin::{AdminClient, AdminOptions, ResourceSpecifier},
+    client::DefaultClientContext,
+    message::OwnedHeaders,
+    producer::{FutureProducer, FutureRecord},
 };
-use rdkafka::producer::{FutureProducer, FutureRecord};
+use serde_json::Value;
 
-use super::topic::TopicPage;
-use crate::constant::SEND_TIMEOUT;
+use super::{
+    input::Input,
+    send_template::{TemplateAction, TemplatePicker, TemplatePopup},
+    text_editor::TextEditor,
+    topic::{center, TopicPage},
+};
+use crate::codec::{DeserializerRegistry, Field};
+use crate::constant::{ADMIN_TIMEOUT, SEND_TIMEOUT};
+use crate::kafka::KafkaMessage;
+use crate::template::{expand, Template, TemplateHeader, TemplateStore};
 use crate::theme::THEME;
+use crate::timestamp::parse_timestamp;
 pub struct TopicSendForm {
     field: InputField,
     topic: String,
 
-    partition: String,
-    message: String,
-    key: String,
+    partition: Input,
+    message: TextEditor,
+    key: Input,
+    timestamp: Input,
+    headers: Vec<HeaderInput>,
+    /// Send no key or no value at all, which is not the same as an empty one.
+    null_key: bool,
+    null_value: bool,
+
+    /// Set while a tombstone waits for confirmation, with a warning if any.
+    confirm: Option<Option<String>>,
+    /// Why the key or the payload does not match its schema.
+    error: Option<(Field, String)>,
+    deserializers: Arc<DeserializerRegistry>,
 
-    cursor_index: usize,
+    templates: TemplateStore,
+    template_popup: Option<TemplatePopup>,
+    /// Name of the template loaded last, offered when saving.
+    template: Option<String>,
+    /// Messages sent so far, for `{{seq}}`.
+    seq: u64,
+    /// The key and the payload of a message loaded to be sent again.
+    original_key: Option<Original>,
+    original_payload: Option<Original>,
+}
+
+#[derive(Debug, Default, Clone)]
+struct HeaderInput {
+    name: Input,
+    value: Input,
+    /// The bytes of a header loaded from a message, sent as they are while
+    /// its value is not edited.
+    original: Option<Vec<u8>>,
+}
+
+impl HeaderInput {
+    fn value(&self, seq: u64) -> Result<Vec<u8>> {
+        match &self.original {
+            Some(bytes) if String::from_utf8_lossy(bytes) == self.value.value() => {
+                Ok(bytes.clone())
+            }
+            _ => Ok(expand(self.value.value(), seq)?.into_bytes()),
+        }
+    }
+}
+
+/// The bytes of a key or a payload loaded from a message and the text shown
+/// for them. They are sent as they are while the text is not edited.
+#[derive(Debug, Clone)]
+struct Original {
+    text: String,
+    bytes: Vec<u8>,
 }
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
@@ -30,33 +94,67 @@ enum InputField {
     Message,
     Key,
     Partition,
+    Timestamp,
+    HeaderName(usize),
+    HeaderValue(usize),
 }
 
 impl InputField {
-    pub fn next(&mut self) {
-        *self = match self {
+    /// Goes through the header rows after the timestamp.
+    pub fn next(&mut self, headers: usize) {
+        *self = match *self {
             InputField::Message => InputField::Key,
             InputField::Key => InputField::Partition,
-            InputField::Partition => InputField::Message,
+            InputField::Partition => InputField::Timestamp,
+            InputField::Timestamp if headers > 0 => InputField::HeaderName(0),
+            InputField::HeaderName(i) => InputField::HeaderValue(i),
+            InputField::HeaderValue(i) if i + 1 < headers => InputField::HeaderName(i + 1),
+            _ => InputField::Message,
         };
     }
 }
 
 impl TopicSendForm {
-    pub fn new(topic: &str) -> Self {
+    pub fn new(
+        topic: &str,
+        deserializers: Arc<DeserializerRegistry>,
+        templates: TemplateStore,
+    ) -> Self {
         Self {
             field: InputField::default(),
             topic: topic.to_string(),
-            message: String::new(),
-            key: String::new(),
-            partition: String::new(),
-
-            cursor_index: 0,
+            message: TextEditor::default(),
+            key: Input::default(),
+            partition: Input::default(),
+            timestamp: Input::default(),
+            headers: Vec::new(),
+            null_key: false,
+            null_value: false,
+            confirm: None,
+            error: None,
+            deserializers,
+            templates,
+            template_popup: None,
+            template: None,
+            seq: 0,
+            original_key: None,
+            original_payload: None,
         }
     }
 
+    /// Starts over with an empty form for another topic.
     pub fn set_topic(&mut self, topic: &str) {
         self.topic = topic.to_string();
+        self.field = InputField::default();
+        self.partition.set_value("");
+        self.timestamp.set_value("");
+        self.headers.clear();
+        self.null_key = false;
+        self.null_value = false;
+        self.confirm = None;
+        self.error = None;
+        self.template_popup = None;
+        self.template = None;
         self.empty();
     }
 
@@ -64,58 +162,169 @@ impl TopicSendForm {
         &self.topic
     }
 
-    pub fn render(&self, area: Rect, buf: &mut Buffer) {
-        let [key, partition, message] = Layout::vertical([
-            Constraint::Length(3),
+    pub fn payload(&self) -> String {
+        self.message.text()
+    }
+
+    /// Replaces the payload, as edited outside the form.
+    pub fn set_payload(&mut self, payload: &str) {
+        self.message.set_text(payload);
+        self.field = InputField::Message;
+    }
+
+    /// Pastes into the focused field. Single line fields keep the first line.
+    pub fn paste(&mut self, text: &str) {
+        if self.field == InputField::Message {
+            self.message.insert_str(text);
+            return;
+        }
+        let digits_only = self.field == InputField::Partition;
+        if let Some(input) = self.input_mut() {
+            text.lines()
+                .next()
+                .unwrap_or_default()
+                .chars()
+                .filter(|c| !digits_only || c.is_ascii_digit())
+                .for_each(|c| input.enter_char(c));
+        }
+    }
+
+    pub fn render(&mut self, area: Rect, buf: &mut Buffer) {
+        let [fields, headers, message] = Layout::vertical([
             Constraint::Length(3),
+            Constraint::Length(self.headers.len().max(1) as u16 + 2),
             Constraint::Fill(1),
         ])
         .areas(area);
+        let [key, partition, timestamp] = Layout::horizontal([
+            Constraint::Fill(2),
+            Constraint::Length(16),
+            Constraint::Fill(1),
+        ])
+        .areas(fields);
 
-        let mut render_paragraph = |text: String, title: &str, area: Rect, field: InputField| {
-            let block = Block::new()
-                .title(Line::raw(title))
+        let field_block = |title: &str| {
+            Block::new()
+                .title(Line::raw(title.to_string()))
                 .border_set(symbols::border::ROUNDED)
                 .border_style(THEME.borders)
-                .borders(Borders::ALL);
-
-            let line = if field == self.field {
-                Line::from(if self.cursor_index < text.len() {
-                    vec![
-                        Span::raw(&text[0..self.cursor_index]).style(THEME.content),
-                        Span::raw(&text[self.cursor_index..self.cursor_index + 1])
-                            .style(THEME.content.bg(Color::White)),
-                        Span::raw(&text[self.cursor_index + 1..]).style(THEME.content),
-                    ]
-                } else {
-                    vec![
-                        Span::raw(text.clone()).style(THEME.content),
-                        Span::raw(" ").style(THEME.content.bg(Color::White)),
-                    ]
-                })
-                .style(THEME.content.add_modifier(Modifier::UNDERLINED))
-            } else {
-                Line::from(vec![Span::raw(text.clone()).style(THEME.content)])
+                .borders(Borders::ALL)
+        };
+        // Keys and payloads with a schema are typed as JSON.
+        let schema_block = |name: &str, keys: &str, field: Field| {
+            let title = match self.deserializers.schema_format(&self.topic, field) {
+                Some(format) => format!("{} as {} JSON ({})", name, format, keys),
+                None => format!("{} ({})", name, keys),
             };
-
-            let paragraph = Paragraph::new(line).block(block);
-
-            paragraph.render(area, buf);
+            let block = field_block(&title);
+            match &self.error {
+                Some((error_field, error)) if *error_field == field => block
+                    .title_bottom(Line::raw(error.clone()).style(THEME.error))
+                    .border_style(THEME.error),
+                _ => block,
+            }
         };
 
-        render_paragraph(
-            self.message.clone(),
+        let null = |focused: bool| {
+            let style = match focused {
+                true => THEME.tip.add_modifier(Modifier::UNDERLINED),
+                false => THEME.tip,
+            };
+            Line::raw("null").style(style)
+        };
+        let block = schema_block(
             "Message",
-            message,
-            InputField::Message,
-        );
-        render_paragraph(self.key.clone(), "Key", key, InputField::Key);
-        render_paragraph(
-            self.partition.clone(),
-            "Partition",
-            partition,
-            InputField::Partition,
+            "Ctrl+S send, Ctrl+T null, Ctrl+E $EDITOR, Ctrl+Z undo",
+            Field::Payload,
         );
+        if self.null_value {
+            Paragraph::new(null(self.field == InputField::Message))
+                .block(block)
+                .render(message, buf);
+        } else {
+            let focused = self.field == InputField::Message;
+            self.message.render(message, buf, block, focused);
+        }
+
+        let line = match self.null_key {
+            true => null(self.field == InputField::Key),
+            false => self.key.line(self.field == InputField::Key),
+        };
+        Paragraph::new(line)
+            .block(schema_block("Key", "Ctrl+K null", Field::Key))
+            .render(key, buf);
+        Paragraph::new(self.partition.line(self.field == InputField::Partition))
+            .block(field_block("Partition"))
+            .render(partition, buf);
+        Paragraph::new(self.timestamp.line(self.field == InputField::Timestamp))
+            .block(field_block("Timestamp (empty: now)"))
+            .render(timestamp, buf);
+
+        let lines: Vec<Line> = if self.headers.is_empty() {
+            vec![Line::raw("No headers").style(THEME.tip)]
+        } else {
+            self.headers
+                .iter()
+                .enumerate()
+                .map(|(i, header)| {
+                    let mut spans = header
+                        .name
+                        .line(self.field == InputField::HeaderName(i))
+                        .spans;
+                    spans.push(Span::raw(": "));
+                    spans.extend(
+                        header
+                            .value
+                            .line(self.field == InputField::HeaderValue(i))
+                            .spans,
+                    );
+                    Line::from(spans)
+                })
+                .collect()
+        };
+        Paragraph::new(lines)
+            .block(field_block("Headers (Ctrl+N add, Ctrl+D remove)"))
+            .render(headers, buf);
+
+        if let Some(warning) = &self.confirm {
+            self.render_confirm(area, buf, warning.as_deref());
+        }
+        if let Some(popup) = &self.template_popup {
+            popup.render(area, buf, &self.topic);
+        }
+    }
+
+    fn render_confirm(&self, area: Rect, buf: &mut Buffer, warning: Option<&str>) {
+        let area = center(area, Constraint::Length(64), Constraint::Length(8));
+        Clear.render(area, buf);
+        let block = Block::new()
+            .title(Line::raw("Send Tombstone").centered())
+            .borders(Borders::ALL)
+            .border_set(symbols::border::ROUNDED)
+            .border_style(match warning {
+                Some(_) => THEME.error,
+                None => THEME.borders,
+            })
+            .style(THEME.root);
+
+        let key = match self.null_key {
+            true => "no key".to_string(),
+            false => format!("key \"{}\"", self.key.value()),
+        };
+        let mut lines = vec![
+            Line::raw(format!("Send a null value with {} to {}?", key, self.topic))
+                .style(THEME.content),
+            Line::from(""),
+        ];
+        if let Some(warning) = warning {
+            lines.push(Line::raw(warning.to_string()).style(THEME.error));
+            lines.push(Line::from(""));
+        }
+        lines.push(Line::raw("Enter: send  Esc: cancel").style(THEME.tip));
+        Paragraph::new(lines)
+            .wrap(Wrap { trim: true })
+            .block(block)
+            .render(area, buf);
     }
 }
 
@@ -124,96 +333,359 @@ impl TopicSendForm {
         &mut self,
         key: &KeyEvent,
         producer: &FutureProducer,
+        admin: &AdminClient<DefaultClientContext>,
     ) -> Result<TopicPage> {
-        match key.code {
-            KeyCode::Enter => match self.field {
-                InputField::Message => {
-                    self.submit(producer).await?;
-                    return Ok(TopicPage::Messages);
+        if let Some(popup) = &mut self.template_popup {
+            match popup.handle_key_press(key) {
+                TemplateAction::None => {}
+                TemplateAction::Close => self.template_popup = None,
+                TemplateAction::Load(template) => {
+                    self.template_popup = None;
+                    self.load_template(template);
+                }
+                TemplateAction::Delete(name) => self.templates.delete(&self.topic, &name)?,
+                TemplateAction::Save(name) => {
+                    self.template_popup = None;
+                    let template = self.template(name.clone())?;
+                    self.templates.save(&self.topic, template)?;
+                    self.template = Some(name);
+                }
+            }
+            return Ok(TopicPage::SendEdit);
+        }
+
+        if self.confirm.is_some() {
+            match key.code {
+                KeyCode::Enter => {
+                    self.confirm = None;
+                    return self.send(producer).await;
+                }
+                KeyCode::Esc => self.confirm = None,
+                _ => {}
+            }
+            return Ok(TopicPage::SendEdit);
+        }
+
+        if key.modifiers.contains(KeyModifiers::CONTROL) {
+            match key.code {
+                KeyCode::Char('s') if self.null_value => {
+                    self.confirm = Some(self.tombstone_warning(admin).await);
+                }
+                KeyCode::Char('s') => return self.send(producer).await,
+                KeyCode::Char('o') => {
+                    let templates = self.templates.load(&self.topic)?;
+                    self.template_popup = Some(TemplatePopup::Pick(TemplatePicker::new(templates)));
+                }
+                KeyCode::Char('w') => {
+                    let mut name = Input::default();
+                    name.set_value(self.template.as_deref().unwrap_or_default());
+                    self.template_popup = Some(TemplatePopup::Save(name));
+                }
+                KeyCode::Char('k') => self.null_key = !self.null_key,
+                KeyCode::Char('t') => self.null_value = !self.null_value,
+                KeyCode::Char('n') => self.add_header(),
+                KeyCode::Char('d') => self.remove_header(),
+                KeyCode::Char('z') if self.field == InputField::Message => {
+                    self.message.handle_key_press(key);
                 }
-                _ => self.change_field(),
-            },
+                _ => {}
+            }
+            return Ok(TopicPage::SendEdit);
+        }
+
+        match key.code {
             KeyCode::Tab => self.change_field(),
-            KeyCode::Esc => {
-                return Ok(TopicPage::Send);
+            KeyCode::Esc => return Ok(TopicPage::Send),
+            _ if self.field == InputField::Message => {
+                self.message.handle_key_press(key);
+            }
+            KeyCode::Enter => self.change_field(),
+            KeyCode::Char(c) if self.field == InputField::Partition && !c.is_ascii_digit() => {}
+            _ => {
+                if let Some(input) = self.input_mut() {
+                    input.handle_key_press(key);
+                }
             }
-            KeyCode::Left => self.move_cursor_left(),
-            KeyCode::Right => self.move_cursor_right(),
-            KeyCode::Char(c) => self.enter_char(c),
-            KeyCode::Backspace => self.delete_char(),
-            _ => {}
         }
         Ok(TopicPage::SendEdit)
     }
 
     fn change_field(&mut self) {
-        self.field.next();
-        self.cursor_index = match self.field {
-            InputField::Message => self.message.len(),
-            InputField::Key => self.key.len(),
-            InputField::Partition => self.partition.len(),
+        self.field.next(self.headers.len());
+    }
+
+    /// The single line input that has the focus.
+    fn input_mut(&mut self) -> Option<&mut Input> {
+        match self.field {
+            InputField::Message => None,
+            InputField::Key => Some(&mut self.key),
+            InputField::Partition => Some(&mut self.partition),
+            InputField::Timestamp => Some(&mut self.timestamp),
+            InputField::HeaderName(i) => self.headers.get_mut(i).map(|h| &mut h.name),
+            InputField::HeaderValue(i) => self.headers.get_mut(i).map(|h| &mut h.value),
+        }
+    }
+
+    /// Adds a header after the focused one, or at the end.
+    fn add_header(&mut self) {
+        let index = match self.field {
+            InputField::HeaderName(i) | InputField::HeaderValue(i) => i + 1,
+            _ => self.headers.len(),
         };
+        self.headers.insert(index, HeaderInput::default());
+        self.field = InputField::HeaderName(index);
     }
 
-    fn move_cursor_left(&mut self) {
-        let cursor_moved_left = self.cursor_index.saturating_sub(1);
-        self.cursor_index = cursor_moved_left;
+    fn remove_header(&mut self) {
+        let index = match self.field {
+            InputField::HeaderName(i) | InputField::HeaderValue(i) => i,
+            _ => return,
+        };
+        self.headers.remove(index);
+        self.field = match index.checked_sub(1) {
+            _ if index < self.headers.len() => InputField::HeaderName(index),
+            Some(previous) => InputField::HeaderName(previous),
+            None => InputField::Timestamp,
+        };
     }
 
-    fn move_cursor_right(&mut self) {
-        let cursor_moved_right = self.cursor_index.saturating_add(1);
-        self.cursor_index = cursor_moved_right;
+    /// The form as a template, the timestamp is left out.
+    pub fn template(&self, name: String) -> Result<Template> {
+        let partition = match self.partition.value() {
+            "" => None,
+            partition => Some(
+                partition
+                    .parse()
+                    .map_err(|_| eyre!("Invalid partition \"{}\"", partition))?,
+            ),
+        };
+        Ok(Template {
+            name,
+            key: (!self.null_key).then(|| self.key.value().to_string()),
+            partition,
+            payload: (!self.null_value).then(|| self.message.text()),
+            headers: self
+                .headers
+                .iter()
+                .map(|header| TemplateHeader {
+                    name: header.name.value().to_string(),
+                    value: header.value.value().to_string(),
+                })
+                .collect(),
+        })
     }
 
-    fn enter_char(&mut self, c: char) {
-        match self.field {
-            InputField::Message => {
-                self.message.insert(self.cursor_index, c);
-            }
-            InputField::Key => {
-                self.key.insert(self.cursor_index, c);
-            }
-            InputField::Partition => {
-                if !c.is_digit(10) {
-                    return;
-                }
-                self.partition.insert(self.cursor_index, c);
-            }
+    fn load_template(&mut self, template: Template) {
+        self.null_key = template.key.is_none();
+        self.key
+            .set_value(template.key.as_deref().unwrap_or_default());
+        self.partition.set_value(
+            &template
+                .partition
+                .map(|p| p.to_string())
+                .unwrap_or_default(),
+        );
+        self.null_value = template.payload.is_none();
+        self.message
+            .set_text(template.payload.as_deref().unwrap_or_default());
+        self.headers = template
+            .headers
+            .iter()
+            .map(|header| {
+                let mut input = HeaderInput::default();
+                input.name.set_value(&header.name);
+                input.value.set_value(&header.value);
+                input
+            })
+            .collect();
+        self.field = InputField::Message;
+        self.error = None;
+        self.template = Some(template.name);
+        self.original_key = None;
+        self.original_payload = None;
+    }
+
+    /// Fills the form with a consumed message to send it again to the form's
+    /// topic. Keys and payloads read with a schema are edited as JSON and
+    /// encoded for the topic, unless they are sent unedited to a topic that
+    /// encodes them alike. Unedited headers are sent as they were read.
+    pub fn load_message(&mut self, message: &KafkaMessage) -> Result<()> {
+        let (key, original_key) = self.loaded_text(
+            message,
+            Field::Key,
+            message.key.as_deref(),
+            message.decoded_key.as_ref(),
+        )?;
+        let (payload, original_payload) = self.loaded_text(
+            message,
+            Field::Payload,
+            message.payload.as_deref(),
+            message.decoded_payload.as_ref(),
+        )?;
+        let headers = message
+            .headers
+            .iter()
+            .map(|header| TemplateHeader {
+                name: header.key.clone(),
+                value: String::from_utf8_lossy(&header.value).into_owned(),
+            })
+            .collect();
+        self.load_template(Template {
+            name: String::new(),
+            key,
+            partition: None,
+            payload,
+            headers,
+        });
+        for (input, header) in self.headers.iter_mut().zip(&message.headers) {
+            input.original = Some(header.value.clone());
+        }
+        self.template = None;
+        self.original_key = original_key;
+        self.original_payload = original_payload;
+        Ok(())
+    }
+
+    /// The text a key or a payload is edited as, and its bytes when they
+    /// can be sent to the form's topic as they are.
+    fn loaded_text(
+        &self,
+        message: &KafkaMessage,
+        field: Field,
+        raw: Option<&[u8]>,
+        decoded: Option<&Value>,
+    ) -> Result<(Option<String>, Option<Original>)> {
+        let Some(raw) = raw else {
+            return Ok((None, None));
+        };
+        let schema = self
+            .deserializers
+            .read_schema_format(&message.topic, field, raw)
+            .is_some();
+        let text = match decoded {
+            Some(value) if schema && field == Field::Key => value.to_string(),
+            Some(value) if schema => serde_json::to_string_pretty(value)?,
+            _ => String::from_utf8_lossy(raw).into_owned(),
         };
-        self.move_cursor_right();
+        let original = self
+            .deserializers
+            .same_encoding(&message.topic, &self.topic, field, raw)
+            .then(|| Original {
+                text: text.clone(),
+                bytes: raw.to_vec(),
+            });
+        Ok((Some(text), original))
     }
 
-    fn delete_char(&mut self) {
-        if self.cursor_index == 0 {
-            return;
+    /// Warns about tombstones on compacted topics, where they delete every
+    /// earlier message with the same key.
+    async fn tombstone_warning(&self, admin: &AdminClient<DefaultClientContext>) -> Option<String> {
+        let options = AdminOptions::new().request_timeout(Some(ADMIN_TIMEOUT));
+        let resources = [ResourceSpecifier::Topic(&self.topic)];
+        let policy = match admin.describe_configs(&resources, &options).await {
+            Ok(results) => results
+                .into_iter()
+                .next()
+                .and_then(Result::ok)
+                .and_then(|config| config.get("cleanup.policy").and_then(|e| e.value.clone())),
+            Err(e) => return Some(format!("Failed to read cleanup.policy: {}", e)),
+        };
+        let compacted =
+            policy.is_some_and(|policy| policy.split(',').any(|p| p.trim() == "compact"));
+        match (compacted, self.null_key) {
+            (false, _) => None,
+            (true, true) => Some(
+                "cleanup.policy=compact: the broker rejects messages without a key".to_string(),
+            ),
+            (true, false) => Some(format!(
+                "cleanup.policy=compact: compaction removes every message with key \"{}\"",
+                self.key.value()
+            )),
         }
+    }
 
-        match self.field {
-            InputField::Message => {
-                self.message.remove(self.cursor_index - 1);
-            }
-            InputField::Key => {
-                self.key.remove(self.cursor_index - 1);
-            }
-            InputField::Partition => {
-                self.partition.remove(self.cursor_index - 1);
+    /// Schema errors are shown with the field instead of failing the send.
+    async fn send(&mut self, producer: &FutureProducer) -> Result<TopicPage> {
+        let (key, payload) = match self.encode().await {
+            Ok(encoded) => encoded,
+            Err(error) => {
+                self.error = Some(error);
+                return Ok(TopicPage::SendEdit);
             }
-        }
-        self.move_cursor_left();
+        };
+        self.error = None;
+        self.submit(producer, key.as_deref(), payload.as_deref())
+            .await?;
+        self.seq += 1;
+        Ok(TopicPage::Messages)
     }
 
-    pub async fn submit(&mut self, producer: &FutureProducer) -> Result<()> {
-        if self.message.is_empty() || self.key.is_empty() {
-            return Err(eyre!("Message or key is empty"));
-        }
+    /// The key and the payload as sent, with their placeholders expanded.
+    /// `None` when they are null. Unedited ones loaded from a message are
+    /// sent as they were read.
+    async fn encode(&self) -> Result<(Option<Vec<u8>>, Option<Vec<u8>>), (Field, String)> {
+        let encode = |field: Field, text: String| async move {
+            let original = match field {
+                Field::Key => &self.original_key,
+                Field::Payload => &self.original_payload,
+            };
+            if let Some(original) = original.as_ref().filter(|o| o.text == text) {
+                return Ok(original.bytes.clone());
+            }
+            let text = expand(&text, self.seq).map_err(|e| (field, e.to_string()))?;
+            self.deserializers
+                .encoder(&self.topic, field)
+                .await
+                .and_then(|encoder| encoder.encode(&text))
+                .map_err(|e| (field, e.to_string()))
+        };
+        let key = match self.null_key {
+            true => None,
+            false => Some(encode(Field::Key, self.key.value().to_string()).await?),
+        };
+        let payload = match self.null_value {
+            true => None,
+            false => Some(encode(Field::Payload, self.message.text()).await?),
+        };
+        Ok((key, payload))
+    }
 
+    async fn submit(
+        &mut self,
+        producer: &FutureProducer,
+        key: Option<&[u8]>,
+        payload: Option<&[u8]>,
+    ) -> Result<()> {
         let topic = self.topic.clone();
-        let mut record = FutureRecord::to(&topic)
-            .payload(self.message.as_bytes())
-            .key(self.key.as_bytes());
+        let mut record: FutureRecord<[u8], [u8]> = FutureRecord::to(&topic);
+        if let Some(key) = key {
+            record = record.key(key);
+        }
+        if let Some(payload) = payload {
+            record = record.payload(payload);
+        }
 
-        if !self.partition.is_empty() {
-            record = record.partition(self.partition.parse().unwrap());
+        if !self.partition.value().is_empty() {
+            let partition = self
+                .partition
+                .value()
+                .parse()
+                .map_err(|_| eyre!("Invalid partition \"{}\"", self.partition.value()))?;
+            record = record.partition(partition);
+        }
+        if !self.timestamp.value().trim().is_empty() {
+            record = record.timestamp(parse_timestamp(self.timestamp.value().trim())?);
+        }
+        if !self.headers.is_empty() {
+            let mut headers = OwnedHeaders::new_with_capacity(self.headers.len());
+            for header in &self.headers {
+                let name = header.name.value().trim();
+                if name.is_empty() {
+                    return Err(eyre!("Header without a name"));
+                }
+                headers = headers.add(name, &header.value(self.seq)?);
+            }
+            record = record.headers(headers);
         }
 
         producer
@@ -224,10 +696,12 @@ impl TopicSendForm {
         Ok(())
     }
 
+    /// Clears the message and key. Partition, timestamp, headers and the null
+    /// toggles are kept for sending more messages alike.
     pub fn empty(&mut self) {
         self.message.clear();
-        self.key.clear();
-        self.partition.clear();
-        self.cursor_index = 0;
+        self.key.set_value("");
+        self.original_key = None;
+        self.original_payload = None;
     }
 }
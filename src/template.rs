@@ -0,0 +1,220 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use chrono::Utc;
+use color_eyre::{
+    eyre::{eyre, Context},
+    Result,
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// The contents of the send form, saved under a name to send again.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Template {
+    pub name: String,
+    /// `None` for a null key.
+    pub key: Option<String>,
+    pub partition: Option<i32>,
+    /// `None` for a tombstone.
+    pub payload: Option<String>,
+    #[serde(default)]
+    pub headers: Vec<TemplateHeader>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TemplateHeader {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct TemplateFile {
+    #[serde(default)]
+    templates: Vec<Template>,
+}
+
+/// Templates by topic, a file for each topic in the `templates` directory
+/// of the config directory.
+#[derive(Debug, Clone)]
+pub struct TemplateStore {
+    dir: PathBuf,
+}
+
+impl TemplateStore {
+    pub fn new(config_dir: &Path) -> Self {
+        Self {
+            dir: config_dir.join("templates"),
+        }
+    }
+
+    /// Topic names are limited to characters that are safe in file names.
+    fn path(&self, topic: &str) -> PathBuf {
+        self.dir.join(format!("{}.toml", topic))
+    }
+
+    /// A topic without a file has no templates.
+    pub fn load(&self, topic: &str) -> Result<Vec<Template>> {
+        let path = self.path(topic);
+        match fs::read_to_string(&path) {
+            Ok(text) => toml::from_str::<TemplateFile>(&text)
+                .map(|file| file.templates)
+                .wrap_err_with(|| format!("Invalid {}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e).wrap_err_with(|| format!("Failed to read {}", path.display())),
+        }
+    }
+
+    /// Replaces the template of the same name, if there is one.
+    pub fn save(&self, topic: &str, template: Template) -> Result<()> {
+        let mut templates = self.load(topic)?;
+        match templates.iter_mut().find(|t| t.name == template.name) {
+            Some(saved) => *saved = template,
+            None => templates.push(template),
+        }
+        self.write(topic, templates)
+    }
+
+    pub fn delete(&self, topic: &str, name: &str) -> Result<()> {
+        let mut templates = self.load(topic)?;
+        templates.retain(|t| t.name != name);
+        self.write(topic, templates)
+    }
+
+    fn write(&self, topic: &str, templates: Vec<Template>) -> Result<()> {
+        let path = self.path(topic);
+        fs::create_dir_all(&self.dir)
+            .wrap_err_with(|| format!("Failed to create {}", self.dir.display()))?;
+        fs::write(&path, toml::to_string(&TemplateFile { templates })?)
+            .wrap_err_with(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+/// Expands `{{uuid}}`, `{{now_ms}}`, `{{seq}}` and `{{random_int MIN MAX}}`,
+/// anything else in double braces is left as it is.
+pub fn expand(text: &str, seq: u64) -> Result<String> {
+    let mut expanded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}").map(|end| start + end) else {
+            break;
+        };
+        expanded.push_str(&rest[..start]);
+        match placeholder(&rest[start + 2..end], seq)? {
+            Some(value) => expanded.push_str(&value),
+            None => expanded.push_str(&rest[start..end + 2]),
+        }
+        rest = &rest[end + 2..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+fn placeholder(text: &str, seq: u64) -> Result<Option<String>> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let value = match words.as_slice() {
+        ["uuid"] => Uuid::new_v4().to_string(),
+        ["now_ms"] => Utc::now().timestamp_millis().to_string(),
+        ["seq"] => seq.to_string(),
+        ["random_int", min, max] => {
+            let invalid = || eyre!("Invalid {{{{{}}}}}, expected integers", text.trim());
+            let min: i64 = min.parse().map_err(|_| invalid())?;
+            let max: i64 = max.parse().map_err(|_| invalid())?;
+            if min > max {
+                return Err(eyre!("Invalid {{{{{}}}}}, {} > {}", text.trim(), min, max));
+            }
+            rand::thread_rng().gen_range(min..=max).to_string()
+        }
+        ["random_int", ..] => return Err(eyre!("Expected {{{{random_int MIN MAX}}}}")),
+        _ => return Ok(None),
+    };
+    Ok(Some(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expands_sequence_numbers() {
+        assert_eq!(expand("order-{{seq}}", 7).unwrap(), "order-7");
+        assert_eq!(expand("{{ seq }}/{{seq}}", 0).unwrap(), "0/0");
+        assert_eq!(expand("no placeholders", 1).unwrap(), "no placeholders");
+        assert_eq!(expand("", 1).unwrap(), "");
+    }
+
+    #[test]
+    fn expands_generated_values() {
+        let id = expand("{{uuid}}", 0).unwrap();
+        assert!(Uuid::parse_str(&id).is_ok(), "{}", id);
+        assert_ne!(expand("{{uuid}}", 0).unwrap(), id);
+
+        let before = Utc::now().timestamp_millis();
+        let now: i64 = expand("{{now_ms}}", 0).unwrap().parse().unwrap();
+        assert!((before..=Utc::now().timestamp_millis()).contains(&now));
+
+        for _ in 0..100 {
+            let n: i64 = expand("{{random_int -3 3}}", 0).unwrap().parse().unwrap();
+            assert!((-3..=3).contains(&n));
+        }
+        assert_eq!(expand("{{random_int 5 5}}", 0).unwrap(), "5");
+    }
+
+    #[test]
+    fn leaves_other_braces_alone() {
+        assert_eq!(
+            expand(r#"{"id": {{seq}}, "x": {{unknown}}}"#, 3).unwrap(),
+            r#"{"id": 3, "x": {{unknown}}}"#
+        );
+        assert_eq!(expand("{{seq", 3).unwrap(), "{{seq");
+        assert_eq!(expand("seq}} {{seq}}", 3).unwrap(), "seq}} 3");
+        assert_eq!(expand("{{}}", 3).unwrap(), "{{}}");
+    }
+
+    #[test]
+    fn invalid_random_ints() {
+        let error = |text| expand(text, 0).unwrap_err().to_string();
+        assert_eq!(
+            error("{{random_int a 3}}"),
+            "Invalid {{random_int a 3}}, expected integers"
+        );
+        assert_eq!(
+            error("{{random_int 3 1}}"),
+            "Invalid {{random_int 3 1}}, 3 > 1"
+        );
+        assert_eq!(error("{{random_int 3}}"), "Expected {{random_int MIN MAX}}");
+    }
+
+    #[test]
+    fn stores_templates_by_topic() {
+        let dir = std::env::temp_dir().join(format!("kata-templates-{}", std::process::id()));
+        let store = TemplateStore::new(&dir);
+        let template = |name: &str, payload: &str| Template {
+            name: name.to_string(),
+            key: Some("k-{{seq}}".to_string()),
+            partition: None,
+            payload: Some(payload.to_string()),
+            headers: vec![TemplateHeader {
+                name: "source".to_string(),
+                value: "kata".to_string(),
+            }],
+        };
+
+        assert!(store.load("orders").unwrap().is_empty());
+        store.save("orders", template("a", "1")).unwrap();
+        store.save("orders", template("b", "2")).unwrap();
+        store.save("orders", template("a", "3")).unwrap();
+        assert_eq!(
+            store.load("orders").unwrap(),
+            vec![template("a", "3"), template("b", "2")]
+        );
+        assert!(store.load("payments").unwrap().is_empty());
+        store.delete("orders", "a").unwrap();
+        assert_eq!(store.load("orders").unwrap(), vec![template("b", "2")]);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
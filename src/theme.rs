@@ -9,7 +9,10 @@ pub struct Theme {
     pub key_binding: KeyBinding,
     pub content: Style,
     pub error: Style,
+    pub info: Style,
     pub tip: Style,
+    pub placeholder: Style,
+    pub json: JsonStyle,
 }
 
 pub struct KeyBinding {
@@ -17,6 +20,14 @@ pub struct KeyBinding {
     pub description: Style,
 }
 
+pub struct JsonStyle {
+    pub key: Style,
+    pub string: Style,
+    pub number: Style,
+    pub literal: Style,
+    pub punctuation: Style,
+}
+
 pub const THEME: Theme = Theme {
     root: Style::new().bg(DARK_BLUE),
     app_title: Style::new()
@@ -36,17 +47,29 @@ pub const THEME: Theme = Theme {
     },
     content: Style::new().fg(LIGHT_GRAY).bg(DARK_BLUE),
     error: Style::new().fg(RED).bg(DARK_BLUE),
+    info: Style::new().fg(LIGHT_GREEN).bg(DARK_BLUE),
     tip: Style::new()
         .fg(LIGHT_GRAY)
         .bg(DARK_BLUE)
         .add_modifier(Modifier::BOLD),
+    placeholder: Style::new()
+        .fg(MID_GRAY)
+        .bg(DARK_BLUE)
+        .add_modifier(Modifier::ITALIC),
+    json: JsonStyle {
+        key: Style::new().fg(LIGHT_BLUE).bg(DARK_BLUE),
+        string: Style::new().fg(LIGHT_GREEN).bg(DARK_BLUE),
+        number: Style::new().fg(LIGHT_YELLOW).bg(DARK_BLUE),
+        literal: Style::new().fg(LIGHT_RED).bg(DARK_BLUE),
+        punctuation: Style::new().fg(LIGHT_GRAY).bg(DARK_BLUE),
+    },
 };
 
 const DARK_BLUE: Color = Color::Rgb(16, 24, 48);
-// const LIGHT_BLUE: Color = Color::Rgb(64, 96, 192);
-// const LIGHT_YELLOW: Color = Color::Rgb(192, 192, 96);
-// const LIGHT_GREEN: Color = Color::Rgb(64, 192, 96);
-// const LIGHT_RED: Color = Color::Rgb(192, 96, 96);
+const LIGHT_BLUE: Color = Color::Rgb(64, 96, 192);
+const LIGHT_YELLOW: Color = Color::Rgb(192, 192, 96);
+const LIGHT_GREEN: Color = Color::Rgb(64, 192, 96);
+const LIGHT_RED: Color = Color::Rgb(192, 96, 96);
 const RED: Color = Color::Rgb(215, 0, 0);
 const BLACK: Color = Color::Rgb(8, 8, 8); // not really black, often #080808
 const DARK_GRAY: Color = Color::Rgb(68, 68, 68);
@@ -0,0 +1,227 @@
+use chrono::{
+    DateTime, Duration, Local, LocalResult, NaiveDate, NaiveDateTime, NaiveTime, TimeZone,
+};
+use color_eyre::{eyre::eyre, Result};
+
+const TIME_FORMATS: [&str; 2] = ["%H:%M:%S", "%H:%M"];
+const DATE_TIME_FORMATS: [&str; 4] = [
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%d %H:%M",
+    "%Y-%m-%dT%H:%M:%S",
+];
+
+/// Parses a point in time typed by the user into epoch milliseconds.
+///
+/// Besides epoch milliseconds and RFC 3339 this accepts local times such as
+/// `2024-05-01 10:42`, `10:42`, `10:42 yesterday` and relative offsets like
+/// `-15m`, `-2h` or `-1d`. Relative offsets need a unit.
+pub fn parse_timestamp(input: &str) -> Result<i64> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err(eyre!("Timestamp is empty"));
+    }
+
+    // A minus always starts a relative offset, `-15` could be meant as
+    // minutes as well as seconds and is refused.
+    if let Some(relative) = input.strip_prefix('-') {
+        let relative = relative.trim();
+        if !relative.is_empty() && relative.chars().all(|c| c.is_ascii_digit()) {
+            return Err(eyre!(
+                "Ambiguous timestamp \"{}\", give a unit as in {}m",
+                input,
+                input
+            ));
+        }
+        return Ok((Local::now() - parse_duration(relative)?).timestamp_millis());
+    }
+    if let Ok(millis) = input.parse::<i64>() {
+        return Ok(millis);
+    }
+    if let Ok(time) = DateTime::parse_from_rfc3339(input) {
+        return Ok(time.timestamp_millis());
+    }
+
+    for format in DATE_TIME_FORMATS {
+        if let Ok(time) = NaiveDateTime::parse_from_str(input, format) {
+            return local_millis(time);
+        }
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(input, "%Y-%m-%d") {
+        return local_millis(date.and_time(NaiveTime::MIN));
+    }
+
+    let (time, days_ago) = if let Some(time) = input.strip_suffix("yesterday") {
+        (time, 1)
+    } else if let Some(time) = input.strip_prefix("yesterday") {
+        (time, 1)
+    } else if let Some(time) = input.strip_suffix("today") {
+        (time, 0)
+    } else if let Some(time) = input.strip_prefix("today") {
+        (time, 0)
+    } else {
+        (input, 0)
+    };
+    let time = time.trim();
+    let date = Local::now().date_naive() - Duration::days(days_ago);
+    for format in TIME_FORMATS {
+        if let Ok(time) = NaiveTime::parse_from_str(time, format) {
+            return local_millis(date.and_time(time));
+        }
+    }
+
+    Err(eyre!("Unrecognized timestamp \"{}\"", input))
+}
+
+/// Parses durations like `30s`, `15m`, `2h` or `1d`.
+pub fn parse_duration(input: &str) -> Result<Duration> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len());
+    let (amount, unit) = input.split_at(split);
+    let amount: i64 = amount
+        .parse()
+        .map_err(|_| eyre!("Invalid duration \"{}\"", input))?;
+    match unit.trim() {
+        "ms" => Ok(Duration::milliseconds(amount)),
+        "s" | "" => Ok(Duration::seconds(amount)),
+        "m" => Ok(Duration::minutes(amount)),
+        "h" => Ok(Duration::hours(amount)),
+        "d" => Ok(Duration::days(amount)),
+        unit => Err(eyre!("Unknown duration unit \"{}\"", unit)),
+    }
+}
+
+/// Formats epoch milliseconds as a local date time.
+pub fn format_timestamp(millis: i64) -> String {
+    match Local.timestamp_millis_opt(millis) {
+        LocalResult::Single(time) | LocalResult::Ambiguous(time, _) => {
+            time.format("%Y-%m-%d %H:%M:%S%.3f").to_string()
+        }
+        LocalResult::None => millis.to_string(),
+    }
+}
+
+fn local_millis(time: NaiveDateTime) -> Result<i64> {
+    match Local.from_local_datetime(&time) {
+        LocalResult::Single(time) | LocalResult::Ambiguous(time, _) => Ok(time.timestamp_millis()),
+        LocalResult::None => Err(eyre!("{} does not exist in the local time zone", time)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Days, NaiveDate};
+
+    use super::*;
+
+    fn local(date: NaiveDate, time: &str) -> i64 {
+        let time = NaiveTime::parse_from_str(time, "%H:%M:%S%.f").unwrap();
+        local_millis(date.and_time(time)).unwrap()
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn error(input: &str) -> String {
+        parse_timestamp(input).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn epoch_millis_and_rfc3339() {
+        assert_eq!(parse_timestamp("1700000000000").unwrap(), 1_700_000_000_000);
+        assert_eq!(parse_timestamp(" 0 ").unwrap(), 0);
+        assert_eq!(
+            parse_timestamp("2023-11-14T22:13:20Z").unwrap(),
+            1_700_000_000_000
+        );
+        assert_eq!(
+            parse_timestamp("2023-11-14T23:13:20.5+01:00").unwrap(),
+            1_700_000_000_500
+        );
+    }
+
+    #[test]
+    fn local_dates_and_times() {
+        let may = date(2024, 5, 1);
+        assert_eq!(
+            parse_timestamp("2024-05-01 10:42:07.25").unwrap(),
+            local(may, "10:42:07.25")
+        );
+        assert_eq!(
+            parse_timestamp("2024-05-01 10:42:07").unwrap(),
+            local(may, "10:42:07")
+        );
+        assert_eq!(
+            parse_timestamp("2024-05-01 10:42").unwrap(),
+            local(may, "10:42:00")
+        );
+        assert_eq!(
+            parse_timestamp("2024-05-01T10:42:07").unwrap(),
+            local(may, "10:42:07")
+        );
+        assert_eq!(
+            parse_timestamp("2024-05-01").unwrap(),
+            local(may, "00:00:00")
+        );
+    }
+
+    #[test]
+    fn times_of_today_and_yesterday() {
+        let today = Local::now().date_naive();
+        let yesterday = today - Days::new(1);
+        assert_eq!(parse_timestamp("10:42").unwrap(), local(today, "10:42:00"));
+        assert_eq!(
+            parse_timestamp("10:42:07 today").unwrap(),
+            local(today, "10:42:07")
+        );
+        assert_eq!(
+            parse_timestamp("10:42 yesterday").unwrap(),
+            local(yesterday, "10:42:00")
+        );
+        assert_eq!(
+            parse_timestamp("yesterday 23:59").unwrap(),
+            local(yesterday, "23:59:00")
+        );
+    }
+
+    #[test]
+    fn relative_offsets() {
+        let now = Local::now().timestamp_millis();
+        let ago = |input| now - parse_timestamp(input).unwrap();
+        // The parser reads the clock after `now`, so less time has passed.
+        let about = |millis: i64, expected: i64| (expected - 5_000..=expected).contains(&millis);
+        assert!(about(ago("-30s"), 30_000));
+        assert!(about(ago("-15m"), 15 * 60_000));
+        assert!(about(ago("-2h"), 2 * 3_600_000));
+        assert!(about(ago("- 1d"), 86_400_000));
+        assert!(about(ago("-500ms"), 500));
+    }
+
+    #[test]
+    fn ambiguous_and_invalid_input() {
+        assert_eq!(
+            error("-15"),
+            "Ambiguous timestamp \"-15\", give a unit as in -15m"
+        );
+        assert_eq!(error("  "), "Timestamp is empty");
+        assert_eq!(error("-15w"), "Unknown duration unit \"w\"");
+        assert_eq!(error("-m"), "Invalid duration \"m\"");
+        assert_eq!(error("soon"), "Unrecognized timestamp \"soon\"");
+        assert_eq!(error("25:00"), "Unrecognized timestamp \"25:00\"");
+        assert_eq!(error("2024-13-01"), "Unrecognized timestamp \"2024-13-01\"");
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("30").unwrap(), Duration::seconds(30));
+        assert_eq!(
+            parse_duration("250ms").unwrap(),
+            Duration::milliseconds(250)
+        );
+        assert_eq!(parse_duration(" 3 h").unwrap(), Duration::hours(3));
+        assert!(parse_duration("").is_err());
+    }
+}